@@ -0,0 +1,17 @@
+// Feeds arbitrary bytes to GameState::from_fen, run from core with: cargo +nightly fuzz run from_fen
+#![no_main]
+use core::*;
+use libfuzzer_sys::fuzz_target;
+
+
+// Parsing must never panic, and anything it accepts has to be consistent and survive a round trip
+fuzz_target!(|data: &[u8]| {
+    let Ok(fen) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Some(state) = GameState::from_fen(fen) {
+        assert_eq!(state.validate(), Ok(()));
+        let written = state.to_fen();
+        assert_eq!(GameState::from_fen(&written).map(|state| state.to_fen()), Some(written));
+    }
+});
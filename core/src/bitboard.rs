@@ -41,6 +41,58 @@ impl Bitboard {
         result = ( result >> 32)       | ( result       << 32);
         return Self(result);
     }
+    // Flips files, a1 becomes h1
+    pub fn mirror_horizontal(self) -> Self {
+        const K1: u64 = 0x5555555555555555;
+        const K2: u64 = 0x3333333333333333;
+        const K4: u64 = 0x0F0F0F0F0F0F0F0F;
+
+        let mut result: u64 = self.0;
+        result = ((result >> 1) & K1) | ((result & K1) << 1);
+        result = ((result >> 2) & K2) | ((result & K2) << 2);
+        result = ((result >> 4) & K4) | ((result & K4) << 4);
+        Self(result)
+    }
+    // Flips about the a8-h1 diagonal, which holds the indices 0, 9, .., 63
+    pub fn flip_diagonal(self) -> Self {
+        const K1: u64 = 0x5500550055005500;
+        const K2: u64 = 0x3333000033330000;
+        const K4: u64 = 0x0F0F0F0F00000000;
+
+        let mut result: u64 = self.0;
+        let mut swap = K4 & (result ^ (result << 28));
+        result ^= swap ^ (swap >> 28);
+        swap = K2 & (result ^ (result << 14));
+        result ^= swap ^ (swap >> 14);
+        swap = K1 & (result ^ (result << 7));
+        result ^= swap ^ (swap >> 7);
+        Self(result)
+    }
+    // Flips about the a1-h8 diagonal
+    pub fn flip_anti_diagonal(self) -> Self {
+        const K1: u64 = 0xAA00AA00AA00AA00;
+        const K2: u64 = 0xCCCC0000CCCC0000;
+        const K4: u64 = 0xF0F0F0F00F0F0F0F;
+
+        let mut result: u64 = self.0;
+        let mut swap = result ^ (result << 36);
+        result ^= K4 & (swap ^ (result >> 36));
+        swap = K2 & (result ^ (result << 18));
+        result ^= swap ^ (swap >> 18);
+        swap = K1 & (result ^ (result << 9));
+        result ^= swap ^ (swap >> 9);
+        Self(result)
+    }
+    // Quarter turns as seen from white's side of the board, a1 goes to a8 clockwise and to h1 counter clockwise
+    pub fn rotate_clockwise(self) -> Self {
+        self.flip_diagonal().mirror_horizontal()
+    }
+    pub fn rotate_counter_clockwise(self) -> Self {
+        self.flip_diagonal().mirror_vertical()
+    }
+    pub fn rotate_180(self) -> Self {
+        self.mirror_vertical().mirror_horizontal()
+    }
 
     pub fn set_bit(&mut self, coord: Coord) {
         if let Some(index) = coord.index() {
@@ -210,6 +262,17 @@ impl Bitboard {
         self
     }
     pub fn fill_south_east(mut self) -> Self {
+        // Masks to avoid wrapping
+        const MASK_A: u64 = !Bitboard::FILE_MASK[0];
+        const MASK_B: u64 = MASK_A & (MASK_A <<  9);
+        const MASK_C: u64 = MASK_B & (MASK_B << 18);
+
+        self.0 |= MASK_A & (self.0 <<  9);
+        self.0 |= MASK_B & (self.0 << 18);
+        self.0 |= MASK_C & (self.0 << 36);
+        self
+    }
+    pub fn fill_south_west(mut self) -> Self {
         // Masks to avoid wrapping
         const MASK_A: u64 = !Bitboard::FILE_MASK[7];
         const MASK_B: u64 = MASK_A & (MASK_A <<  7);
@@ -220,21 +283,105 @@ impl Bitboard {
         self.0 |= MASK_C & (self.0 << 28);
         self
     }
-    pub fn fill_south_west(mut self) -> Self {
-        // Masks to avoid wrapping
-        const MASK_A: u64 = !Bitboard::FILE_MASK[0];
-        const MASK_B: u64 = MASK_A & (MASK_A <<  9);
-        const MASK_C: u64 = MASK_B & (MASK_B << 18);
 
-        self.0 |= MASK_A & (self.0 <<  9);
-        self.0 |= MASK_B & (self.0 << 18);
-        self.0 |= MASK_C & (self.0 << 36);
-        self
-    }
 
+    // Single step shifts, bits pushed over the edge of the board are dropped instead of wrapping
+    pub fn shift_north(self) -> Self {
+        Self(self.0 >> 8)
+    }
+    pub fn shift_south(self) -> Self {
+        Self(self.0 << 8)
+    }
+    pub fn shift_east(self) -> Self {
+        Self((self.0 << 1) & !Bitboard::FILE_MASK[0])
+    }
+    pub fn shift_west(self) -> Self {
+        Self((self.0 >> 1) & !Bitboard::FILE_MASK[7])
+    }
+    pub fn shift_north_east(self) -> Self {
+        Self((self.0 >> 7) & !Bitboard::FILE_MASK[0])
+    }
+    pub fn shift_north_west(self) -> Self {
+        Self((self.0 >> 9) & !Bitboard::FILE_MASK[7])
+    }
+    pub fn shift_south_east(self) -> Self {
+        Self((self.0 << 9) & !Bitboard::FILE_MASK[0])
+    }
+    pub fn shift_south_west(self) -> Self {
+        Self((self.0 << 7) & !Bitboard::FILE_MASK[7])
+    }
 
-    pub fn count() {
 
+    pub fn from_square(square: Square) -> Self {
+        Self(1 << square as u8)
+    }
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square as u8) != 0
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+    // The set square with the lowest index, towards a8
+    pub fn lsb(self) -> Option<Square> {
+        match self.0 {
+            0 => None,
+            bits => Square::from_index(bits.trailing_zeros() as usize),
+        }
+    }
+    // The set square with the highest index, towards h1
+    pub fn msb(self) -> Option<Square> {
+        match self.0 {
+            0 => None,
+            bits => Square::from_index(63 - bits.leading_zeros() as usize),
+        }
+    }
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let result = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(result)
+    }
+    pub fn iter(self) -> BitboardIter {
+        self.into_iter()
+    }
+}
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+    fn into_iter(self) -> Self::IntoIter {
+        BitboardIter(self)
+    }
+}
+// Iterates over the set squares from a8 to h1
+pub struct BitboardIter(Bitboard);
+impl Iterator for BitboardIter {
+    type Item = Square;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_lsb()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count() as usize;
+        (count, Some(count))
+    }
+}
+impl ExactSizeIterator for BitboardIter {}
+// 8 x x . . . . . .
+// 7 . . . . . . . .
+//   ...
+//   a b c d e f g h
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in 0..8 {
+            write!(f, "{}", 8 - rank)?;
+            for file in 0..8 {
+                let symbol = if self.is_occupied(rank * 8 + file) { 'x' } else { '.' };
+                write!(f, " {}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  a b c d e f g h")
     }
 }
 impl std::ops::Not for Bitboard {
@@ -316,11 +463,15 @@ impl PlayerBitboard {
     }
     pub fn attacks(&self, mut occlusion: Bitboard, color: Color) -> Bitboard {
         occlusion |= self.occupancy();
+        let sliders = sliders::sliding_attacks();
+        let diagonal = (self.bishops | self.queens).iter()
+            .fold(Bitboard(0), |result, square| result | sliders.bishop_attacks(square, occlusion));
+        let straight = (self.rooks | self.queens).iter()
+            .fold(Bitboard(0), |result, square| result | sliders.rook_attacks(square, occlusion));
         Self::generate_pawn_attacks(self.pawns, color) |
         Self::generate_knight_attacks(self.knights) |
-        Self::generate_bishop_attacks(self.bishops, occlusion) |
-        Self::generate_rook_attacks(self.rooks, occlusion) |
-        Self::generate_queen_attacks(self.queens, occlusion) |
+        diagonal |
+        straight |
         Self::generate_king_attacks(self.king)
     }
 
@@ -367,27 +518,19 @@ impl PlayerBitboard {
             }
         }
     }
-    pub fn generate_pawn_movement(pieces: Bitboard, color: Color, mut occlusion: Bitboard) -> Bitboard {
+    pub fn generate_pawn_movement(pieces: Bitboard, color: Color, occlusion: Bitboard) -> Bitboard {
         match color {
             Color::White => {
-                // north
-                occlusion.0 = occlusion.0 | occlusion.0 >> 8;
-                Bitboard(
-                    (
-                        pieces.0 >> 8 |
-                        ((Bitboard::RANK_MASK[6] & pieces.0) >> 16)
-                    ) & !occlusion.0
-                )
+                // north, twice from the second rank if both tiles are free
+                let single = pieces.shift_north() & !occlusion;
+                let double = (single & Bitboard(Bitboard::RANK_MASK[5])).shift_north() & !occlusion;
+                single | double
             }
             Color::Black => {
-                // south
-                occlusion.0 = occlusion.0 | occlusion.0 << 8;
-                Bitboard(
-                    (
-                        pieces.0 << 8 |
-                        ((Bitboard::RANK_MASK[6] & pieces.0) << 16)
-                    ) & !occlusion.0
-                )
+                // south, twice from the seventh rank if both tiles are free
+                let single = pieces.shift_south() & !occlusion;
+                let double = (single & Bitboard(Bitboard::RANK_MASK[2])).shift_south() & !occlusion;
+                single | double
             }
         }
     }
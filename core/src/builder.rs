@@ -0,0 +1,260 @@
+use crate::*;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SetupError {
+    MissingKing(Color),
+    TooManyKings(Color),
+    PawnOnBackRank(Coord),
+    // The side that just moved can not have left its king in check
+    OpponentInCheck(Color),
+    CastlingWithoutKing(Color),
+    CastlingWithoutRook { color: Color, kingside: bool },
+    // The en passant square does not follow a double push of the side that just moved
+    InvalidEnPassant(Coord),
+    TooManyPieces(Color),
+    TooManyPawns(Color),
+    // More promoted pieces than missing pawns
+    TooManyPromotions(Color),
+    InvalidFullmoveCount,
+}
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKing(color) => write!(f, "{:?} has no king", color),
+            Self::TooManyKings(color) => write!(f, "{:?} has more than one king", color),
+            Self::PawnOnBackRank(coord) => write!(f, "pawn on the back rank at {}", coord),
+            Self::OpponentInCheck(color) => write!(f, "{:?} is in check but it is not their move", color),
+            Self::CastlingWithoutKing(color) => write!(f, "{:?} can castle but the king is not on its starting square", color),
+            Self::CastlingWithoutRook { color, kingside: true } => write!(f, "{:?} can castle kingside but the rook is not on its starting square", color),
+            Self::CastlingWithoutRook { color, kingside: false } => write!(f, "{:?} can castle queenside but the rook is not on its starting square", color),
+            Self::InvalidEnPassant(coord) => write!(f, "en passant on {} does not follow a double pawn move", coord),
+            Self::TooManyPieces(color) => write!(f, "{:?} has more than 16 pieces", color),
+            Self::TooManyPawns(color) => write!(f, "{:?} has more than 8 pawns", color),
+            Self::TooManyPromotions(color) => write!(f, "{:?} has more promoted pieces than missing pawns", color),
+            Self::InvalidFullmoveCount => write!(f, "the fullmove number starts at 1"),
+        }
+    }
+}
+impl std::error::Error for SetupError {}
+
+
+// Sets up a position piece by piece, build checks it could come up in a game
+#[derive(Clone, Debug)]
+pub struct PositionBuilder {
+    board: [Option<(PieceType, Color)>; 64],
+    current: Color,
+    // White kingside, white queenside, black kingside, black queenside
+    castling: [bool; 4],
+    // The tile the pawn skipped, as in FEN
+    en_passant: Option<Coord>,
+    halfmove_count: u32,
+    fullmove_count: u32,
+}
+impl PositionBuilder {
+    // An empty board with white to move
+    pub fn new() -> Self {
+        Self {
+            board: [None; 64],
+            current: Color::White,
+            castling: [false; 4],
+            en_passant: None,
+            halfmove_count: 0,
+            fullmove_count: 1,
+        }
+    }
+    pub fn from_state(state: &GameState) -> Self {
+        let mut board = [None; 64];
+        for (index, tile) in board.iter_mut().enumerate() {
+            *tile = state.piece_at(index);
+        }
+        let en_passant = state.en_passant.map(|pawn| match state.current {
+            Color::White => Coord::from(pawn as u8 - 8),
+            Color::Black => Coord::from(pawn as u8 + 8),
+        });
+        Self {
+            board,
+            current: state.current,
+            castling: state.castling_rights(),
+            en_passant,
+            halfmove_count: state.halfmove_count,
+            fullmove_count: state.fullmove_count,
+        }
+    }
+
+
+    pub fn piece_at(&self, coord: Coord) -> Option<(PieceType, Color)> {
+        self.board[coord.index()?]
+    }
+    // Places a piece, replacing whatever was on the tile
+    pub fn place(&mut self, coord: Coord, piece: PieceType, color: Color) -> &mut Self {
+        if let Some(index) = coord.index() {
+            self.board[index] = match piece {
+                PieceType::None => None,
+                _ => Some((piece, color)),
+            };
+        }
+        self
+    }
+    pub fn remove(&mut self, coord: Coord) -> Option<(PieceType, Color)> {
+        self.board[coord.index()?].take()
+    }
+    pub fn clear(&mut self) -> &mut Self {
+        self.board = [None; 64];
+        self
+    }
+
+    pub fn side_to_move(&mut self, color: Color) -> &mut Self {
+        self.current = color;
+        self
+    }
+    pub fn castling(&mut self, color: Color, kingside: bool, allowed: bool) -> &mut Self {
+        self.castling[Self::castling_index(color, kingside)] = allowed;
+        self
+    }
+    // The tile the pawn skipped over, as written in FEN
+    pub fn en_passant(&mut self, coord: Option<Coord>) -> &mut Self {
+        self.en_passant = coord;
+        self
+    }
+    pub fn halfmove_count(&mut self, count: u32) -> &mut Self {
+        self.halfmove_count = count;
+        self
+    }
+    pub fn fullmove_count(&mut self, count: u32) -> &mut Self {
+        self.fullmove_count = count;
+        self
+    }
+
+
+    // Returns every rule the position breaks, or the position with its moves generated
+    pub fn build(&self) -> Result<GameState, Vec<SetupError>> {
+        let mut errors = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            self.check_pieces(color, &mut errors);
+            self.check_castling(color, &mut errors);
+        }
+        for (index, tile) in self.board.iter().enumerate() {
+            let coord = Coord::from(index as u8);
+            if matches!(tile, Some((PieceType::Pawn, _))) && (coord.rank == 0 || coord.rank == 7) {
+                errors.push(SetupError::PawnOnBackRank(coord));
+            }
+        }
+        if let Some(coord) = self.en_passant {
+            if !self.is_en_passant_valid(coord) {
+                errors.push(SetupError::InvalidEnPassant(coord));
+            }
+        }
+        if self.fullmove_count == 0 {
+            errors.push(SetupError::InvalidFullmoveCount);
+        }
+
+        let has_kings = !errors.iter().any(|error| matches!(error, SetupError::MissingKing(_) | SetupError::TooManyKings(_)));
+        let has_room = !errors.iter().any(|error| matches!(error, SetupError::TooManyPieces(_)));
+        if !has_kings || !has_room {
+            return Err(errors);
+        }
+
+        let mut result = GameState::default();
+        for (index, tile) in self.board.iter().enumerate() {
+            if let Some((piece, color)) = *tile {
+                result.push_piece(piece, color, Coord::from(index as u8));
+            }
+        }
+        result.current = self.current;
+        result.set_castling_rights(self.castling);
+        if let Some(coord) = self.en_passant.filter(|&coord| self.is_en_passant_valid(coord)) {
+            let index = coord.index().unwrap();
+            result.board[index].set_en_passant(true);
+            result.en_passant = Some(match self.current {
+                Color::White => index + 8,
+                Color::Black => index - 8,
+            });
+        }
+        result.halfmove_count = self.halfmove_count;
+        result.fullmove_count = self.fullmove_count;
+        result.zobrist = result.compute_hash();
+
+        if result.is_king_checked(!self.current) {
+            errors.push(SetupError::OpponentInCheck(!self.current));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        result.generate_moves(result.current);
+        Ok(result)
+    }
+
+    fn check_pieces(&self, color: Color, errors: &mut Vec<SetupError>) {
+        let count = |piece: PieceType| self.board.iter().filter(|&&tile| tile == Some((piece, color))).count();
+        let kings = count(PieceType::King);
+        let pawns = count(PieceType::Pawn);
+        let total = self.board.iter().filter(|tile| tile.is_some_and(|(_, tile_color)| tile_color == color)).count();
+
+        match kings {
+            0 => errors.push(SetupError::MissingKing(color)),
+            1 => (),
+            _ => errors.push(SetupError::TooManyKings(color)),
+        }
+        if total > 16 {
+            errors.push(SetupError::TooManyPieces(color));
+        }
+        if pawns > 8 {
+            errors.push(SetupError::TooManyPawns(color));
+        }
+        // Every piece beyond the starting set has to be a promoted pawn
+        let promoted = count(PieceType::Queen).saturating_sub(1) +
+            count(PieceType::Rook).saturating_sub(2) +
+            count(PieceType::Bishop).saturating_sub(2) +
+            count(PieceType::Knight).saturating_sub(2);
+        if pawns <= 8 && promoted > 8 - pawns {
+            errors.push(SetupError::TooManyPromotions(color));
+        }
+    }
+    fn check_castling(&self, color: Color, errors: &mut Vec<SetupError>) {
+        let rank = GameState::home_rank(color);
+        let kingside = self.castling[Self::castling_index(color, true)];
+        let queenside = self.castling[Self::castling_index(color, false)];
+        if !kingside && !queenside {
+            return;
+        }
+        if self.piece_at(Coord::new(4, rank)) != Some((PieceType::King, color)) {
+            errors.push(SetupError::CastlingWithoutKing(color));
+        }
+        for (allowed, file, is_kingside) in [(kingside, 7, true), (queenside, 0, false)] {
+            if allowed && self.piece_at(Coord::new(file, rank)) != Some((PieceType::Rook, color)) {
+                errors.push(SetupError::CastlingWithoutRook { color, kingside: is_kingside });
+            }
+        }
+    }
+    fn is_en_passant_valid(&self, coord: Coord) -> bool {
+        // The pawn of the side that just moved went from start to pawn, skipping the given tile
+        let (skipped_rank, direction) = match self.current {
+            Color::White => (2, 1),
+            Color::Black => (5, -1),
+        };
+        if coord.rank != skipped_rank || coord.index().is_none() {
+            return false;
+        }
+        let pawn = coord.offset(0, direction);
+        let start = coord.offset(0, -direction);
+        self.piece_at(pawn) == Some((PieceType::Pawn, !self.current)) &&
+        self.piece_at(coord).is_none() &&
+        self.piece_at(start).is_none()
+    }
+
+    fn castling_index(color: Color, kingside: bool) -> usize {
+        match (color, kingside) {
+            (Color::White, true) => 0,
+            (Color::White, false) => 1,
+            (Color::Black, true) => 2,
+            (Color::Black, false) => 3,
+        }
+    }
+}
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
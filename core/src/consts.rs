@@ -41,10 +41,8 @@ pub fn generate_magics() -> (Vec<u64>, [MagicSquare; 64]){
     const MAX_SIZE: usize = 2usize.pow(12);
     let mut reference = Vec::with_capacity(MAX_SIZE);
     let mut occupancy = Vec::with_capacity(MAX_SIZE);
-    let mut used_reference = vec![0; MAX_SIZE];
 
     let mut offset = 0;
-    let mut count = 0;
     for square in 0..64 {
         // TODO masking is a bit bad, since a rook that on the edge should not be masked out
         let mut mask = PlayerBitboard::generate_rook_attacks(Bitboard(1 << square as u64), Bitboard(0)).0
@@ -101,7 +99,7 @@ pub fn generate_magics() -> (Vec<u64>, [MagicSquare; 64]){
             panic!("FFFFUCKAWD: {}, {}, {}", mask.count_ones(), rook_relevant_bits[square], square);
         }
 
-        let mut result = MagicSquare {
+        let result = MagicSquare {
             offset,
             mask,
             shift,
@@ -127,43 +125,8 @@ pub fn generate_magics() -> (Vec<u64>, [MagicSquare; 64]){
             }
         }
 
+        // The known magics are used as is, wizard::search finds new ones
         magics.push(result);
-        
-
-        // THIS SHIT DOES NOT WORK!!!!!!!!!!
-        continue;
-
-        // Time to find a magic that works
-        println!("staring one");
-        let mut rng = nanorand::WyRand::new_seed(0);
-        'outer: loop {
-            count += 1;
-
-            // Generate a random magic with a high-ish population count
-            loop {
-                result.magic = rng.generate::<u64>();
-                if ((result.magic * result.magic) >> 56).count_ones() > 5 {
-                    break;
-                }
-            }
-
-            used_reference = vec![0; MAX_SIZE];
-
-            // Test the random magic to see ...
-            for (&truth, &blockers) in reference.iter().zip(occupancy.iter()) {
-                let key = result.key(blockers) as usize;
-                let index = key - result.offset;
-                
-                if used_reference[index] == 0 {
-                    // magic has not yet been used
-                    used_reference[index] = truth;
-                } else if used_reference[index] != truth {
-                    continue 'outer;
-                }
-            }
-            break;
-        }
-        println!("found one");
     }
 
     (database, magics.try_into().unwrap())
@@ -245,4 +208,54 @@ const rook_magic_numbers: [u64; 64] = [
     0x12001008414402,
     0x2006104900a0804,
     0x1004081002402
-];
\ No newline at end of file
+];
+
+// Zobrist keys, filled from a fixed seed so hashes are stable between runs and builds.
+// Pieces are indexed by [color][piece type][tile].
+pub const ZOBRIST_PIECES: [[[u64; 64]; 8]; 2] = {
+    let mut result = [[[0; 64]; 8]; 2];
+    let mut state = ZOBRIST_SEED;
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 8 {
+            let mut tile = 0;
+            while tile < 64 {
+                state = splitmix64(state);
+                result[color][piece][tile] = state;
+                tile += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    result
+};
+// White kingside, white queenside, black kingside, black queenside
+pub const ZOBRIST_CASTLING: [u64; 4] = [
+    splitmix64(ZOBRIST_SEED ^ 0x01),
+    splitmix64(ZOBRIST_SEED ^ 0x02),
+    splitmix64(ZOBRIST_SEED ^ 0x03),
+    splitmix64(ZOBRIST_SEED ^ 0x04),
+];
+// Indexed by the file of the en passant square
+pub const ZOBRIST_EN_PASSANT: [u64; 8] = {
+    let mut result = [0; 8];
+    let mut file = 0;
+    while file < 8 {
+        result[file] = splitmix64(ZOBRIST_SEED ^ (0x10 + file as u64));
+        file += 1;
+    }
+    result
+};
+pub const ZOBRIST_BLACK_TO_MOVE: u64 = splitmix64(ZOBRIST_SEED ^ 0x20);
+
+const ZOBRIST_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// https://prng.di.unimi.it/splitmix64.c
+const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,187 @@
+use crate::*;
+use crate::builder::PositionBuilder;
+
+
+//   +-----------------+
+// 8 | r n b q k b n r |
+// 7 | p p p p p p p p |
+// 6 | . . . . . . . . |
+// 5 | . . . . . . . . |
+// 4 | . . . . P . . . |
+// 3 | . . . . . . . . |
+// 2 | P P P P . P P P |
+// 1 | R N B Q K B N R |
+//   +-----------------+
+//     a b c d e f g h
+// Black to move, castling KQkq, en passant e3
+// FEN: rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1
+//
+// The alternate flag, "{:#}", draws the pieces as unicode figurines
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_diagram(f.alternate()))
+    }
+}
+
+impl GameState {
+    pub fn to_diagram(&self, unicode: bool) -> String {
+        let mut result = String::with_capacity(512);
+        result.push_str("  +-----------------+\n");
+        for rank in 0..8 {
+            result.push_str(&format!("{} |", 8 - rank));
+            for file in 0..8 {
+                let symbol = match self.piece_at(rank * 8 + file) {
+                    Some((piece, color)) if unicode => figurine(piece, color),
+                    Some((piece, Color::White)) => san::piece_symbol(piece),
+                    Some((piece, Color::Black)) => san::piece_symbol(piece).to_ascii_lowercase(),
+                    None => '.',
+                };
+                result.push(' ');
+                result.push(symbol);
+            }
+            result.push_str(" |\n");
+        }
+        result.push_str("  +-----------------+\n");
+        result.push_str("    a b c d e f g h\n");
+
+        let fen = self.to_fen();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let side = match self.current {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+        result.push_str(&format!("{} to move, castling {}, en passant {}\n", side, fields[2], fields[3]));
+        result.push_str(&format!("FEN: {}", fen));
+        result
+    }
+
+    // Reads a diagram like the one Display writes, as well as most diagrams from books and bug reports.
+    // Each rank is a line of 8 pieces or empty squares ('.', '-' or '·'), labels and '|' borders are skipped.
+    // Side to move, castling and en passant are read from "Black to move", "castling KQkq" and "en passant e3",
+    // they default to white, no castling and no en passant. The move clocks come from a "FEN:" line if there is one.
+    pub fn from_diagram(diagram: &str) -> Option<Self> {
+        let mut builder = PositionBuilder::new();
+        let mut rank = 0;
+
+        for line in diagram.lines() {
+            if line.trim_start().starts_with("FEN") {
+                // Only the move clocks are taken from a FEN line, the diagram is what counts
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if let [_, _, _, _, _, halfmove, fullmove] = fields[..] {
+                    builder.halfmove_count(halfmove.parse().ok()?);
+                    builder.fullmove_count(fullmove.parse().ok()?);
+                }
+                continue;
+            }
+            let Some(pieces) = parse_rank(line) else {
+                continue;
+            };
+            if rank == 8 {
+                return None;
+            }
+            for (file, piece) in pieces.into_iter().enumerate() {
+                if let Some((piece, color)) = piece {
+                    builder.place(Coord::new(file as u8, rank), piece, color);
+                }
+            }
+            rank += 1;
+        }
+        if rank != 8 {
+            return None;
+        }
+
+        let words: Vec<&str> = diagram.lines()
+            .filter(|line| !line.trim_start().starts_with("FEN"))
+            .flat_map(|line| line.split(|character: char| character.is_whitespace() || character == ','))
+            .filter(|word| !word.is_empty())
+            .collect();
+        for (index, &word) in words.iter().enumerate() {
+            let next = words.get(index + 1).copied();
+            match word.to_lowercase().as_str() {
+                "black" if next.is_some_and(|next| next.eq_ignore_ascii_case("to")) => {
+                    builder.side_to_move(Color::Black);
+                }
+                "castling" => {
+                    for character in next?.chars() {
+                        match character {
+                            'K' => builder.castling(Color::White, true, true),
+                            'Q' => builder.castling(Color::White, false, true),
+                            'k' => builder.castling(Color::Black, true, true),
+                            'q' => builder.castling(Color::Black, false, true),
+                            '-' => &mut builder,
+                            _ => return None,
+                        };
+                    }
+                }
+                "passant" if next != Some("-") => {
+                    builder.en_passant(Some(Coord::parse(next?)?));
+                }
+                _ => (),
+            }
+        }
+
+        builder.build().ok()
+    }
+}
+
+
+// The 8 tiles of a diagram line, None if the line is not a rank
+fn parse_rank(line: &str) -> Option<[Option<(PieceType, Color)>; 8]> {
+    let mut symbols: Vec<char> = line.chars()
+        .filter(|&character| !character.is_whitespace() && character != '|')
+        .collect();
+    // Rank labels on either side
+    if symbols.first().is_some_and(|character| ('1'..='8').contains(character)) {
+        symbols.remove(0);
+    }
+    if symbols.last().is_some_and(|character| ('1'..='8').contains(character)) {
+        symbols.pop();
+    }
+    if symbols.len() != 8 {
+        return None;
+    }
+
+    let mut result = [None; 8];
+    for (tile, symbol) in result.iter_mut().zip(symbols) {
+        *tile = match symbol {
+            '.' | '-' | '·' => None,
+            symbol => Some(parse_piece(symbol)?),
+        };
+    }
+    Some(result)
+}
+fn parse_piece(symbol: char) -> Option<(PieceType, Color)> {
+    let color = if symbol.is_ascii_uppercase() { Color::White } else { Color::Black };
+    let piece = match symbol {
+        'P' | 'p' => PieceType::Pawn,
+        'N' | 'n' => PieceType::Knight,
+        'B' | 'b' => PieceType::Bishop,
+        'R' | 'r' => PieceType::Rook,
+        'Q' | 'q' => PieceType::Queen,
+        'K' | 'k' => PieceType::King,
+        _ => {
+            return [PieceType::King, PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn]
+                .into_iter()
+                .flat_map(|piece| [(piece, Color::White), (piece, Color::Black)])
+                .find(|&(piece, color)| figurine(piece, color) == symbol);
+        }
+    };
+    Some((piece, color))
+}
+fn figurine(piece: PieceType, color: Color) -> char {
+    match (piece, color) {
+        (PieceType::King, Color::White) => '♔',
+        (PieceType::Queen, Color::White) => '♕',
+        (PieceType::Rook, Color::White) => '♖',
+        (PieceType::Bishop, Color::White) => '♗',
+        (PieceType::Knight, Color::White) => '♘',
+        (PieceType::Pawn, Color::White) => '♙',
+        (PieceType::King, Color::Black) => '♚',
+        (PieceType::Queen, Color::Black) => '♛',
+        (PieceType::Rook, Color::Black) => '♜',
+        (PieceType::Bishop, Color::Black) => '♝',
+        (PieceType::Knight, Color::Black) => '♞',
+        (PieceType::Pawn, Color::Black) => '♟',
+        (PieceType::None, _) => '.',
+    }
+}
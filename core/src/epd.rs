@@ -0,0 +1,203 @@
+use crate::*;
+
+
+// Extended Position Description, a FEN without the move counters followed by operations:
+// rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq - bm e6; id "WAC.001";
+// https://www.chessprogramming.org/Extended_Position_Description
+#[derive(Clone, Debug)]
+pub struct Epd {
+    pub position: GameState,
+    // Operations in the order they were read, unknown opcodes are kept as they are
+    pub operations: Vec<(String, Vec<String>)>,
+}
+impl Epd {
+    pub fn new(position: GameState) -> Self {
+        Self {
+            position,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut rest = line.trim();
+        let mut fields: ArrayVec<&str, 4> = ArrayVec::new();
+        while !fields.is_full() {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let operations = parse_operations(rest)?;
+
+        // The move counters are operations in EPD
+        let counter = |opcode: &str, default: u32| -> Option<u32> {
+            match operations.iter().find(|(name, _)| name == opcode) {
+                Some((_, operands)) => operands.first()?.parse().ok(),
+                None => Some(default),
+            }
+        };
+        let fen = format!(
+            "{} {} {} {} {} {}",
+            fields[0], fields[1], fields[2], fields[3],
+            counter("hmvc", 0)?,
+            counter("fmvn", 1)?,
+        );
+        let mut position = GameState::from_fen(&fen)?;
+        position.generate_moves(position.current);
+
+        Some(Self {
+            position,
+            operations,
+        })
+    }
+
+    pub fn to_epd(&self) -> String {
+        let fen = self.position.to_fen();
+        let mut buffer = fen.split_whitespace().take(4).collect::<Vec<&str>>().join(" ");
+        for (opcode, operands) in &self.operations {
+            buffer.push(' ');
+            buffer.push_str(opcode);
+            for operand in operands {
+                buffer.push(' ');
+                if needs_quotes(opcode, operand) {
+                    buffer.push('"');
+                    buffer.push_str(operand);
+                    buffer.push('"');
+                } else {
+                    buffer.push_str(operand);
+                }
+            }
+            buffer.push(';');
+        }
+        buffer
+    }
+
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+    pub fn set_operation(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+            Some((_, old)) => *old = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+    pub fn remove_operation(&mut self, opcode: &str) {
+        self.operations.retain(|(name, _)| name != opcode);
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")?.first().map(String::as_str)
+    }
+    // The comment opcodes c0 to c9
+    pub fn comment(&self, index: usize) -> Option<&str> {
+        self.operation(&format!("c{}", index))?.first().map(String::as_str)
+    }
+    // Analysis count depth, acd
+    pub fn depth(&self) -> Option<u32> {
+        self.operation("acd")?.first()?.parse().ok()
+    }
+
+    // The bm moves resolved against the position, None if any of them is not a legal move
+    pub fn best_moves(&self) -> Option<Vec<Move>> {
+        self.resolve_moves("bm")
+    }
+    // The am moves resolved against the position, None if any of them is not a legal move
+    pub fn avoid_moves(&self) -> Option<Vec<Move>> {
+        self.resolve_moves("am")
+    }
+    pub fn set_best_moves(&mut self, moves: &[Move]) {
+        let operands = moves.iter().map(|&movement| self.position.move_to_san(movement)).collect();
+        self.set_operation("bm", operands);
+    }
+    pub fn set_avoid_moves(&mut self, moves: &[Move]) {
+        let operands = moves.iter().map(|&movement| self.position.move_to_san(movement)).collect();
+        self.set_operation("am", operands);
+    }
+
+    fn resolve_moves(&self, opcode: &str) -> Option<Vec<Move>> {
+        self.operation(opcode)?
+            .iter()
+            .map(|san| self.position.move_from_san(san))
+            .collect()
+    }
+}
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_epd())
+    }
+}
+
+
+// Splits "bm Nf3 Ng5; id \"test; 1\";" into opcodes and operands.
+// String operands are quoted and may contain whitespace and semicolons.
+fn parse_operations(input: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let mut operations = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|character| character.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Some(operations);
+        }
+
+        let mut opcode = String::new();
+        while let Some(&character) = chars.peek() {
+            if character.is_whitespace() || character == ';' {
+                break;
+            }
+            opcode.push(character);
+            chars.next();
+        }
+        if opcode.is_empty() || !opcode.chars().next()?.is_ascii_alphabetic() {
+            return None;
+        }
+
+        let mut operands = Vec::new();
+        loop {
+            match chars.next()? {
+                ';' => break,
+                character if character.is_whitespace() => (),
+                '"' => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            character => operand.push(character),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                character => {
+                    let mut operand = character.to_string();
+                    while let Some(&character) = chars.peek() {
+                        if character.is_whitespace() || character == ';' {
+                            break;
+                        }
+                        operand.push(character);
+                        chars.next();
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+        operations.push((opcode, operands));
+    }
+}
+
+fn needs_quotes(opcode: &str, operand: &str) -> bool {
+    let is_string_opcode = opcode == "id" || {
+        let mut chars = opcode.chars();
+        matches!(chars.next(), Some('c' | 'v')) &&
+        chars.next().is_some_and(|digit| digit.is_ascii_digit()) &&
+        chars.next().is_none()
+    };
+    is_string_opcode || operand.is_empty() || operand.contains(|character: char| character.is_whitespace() || character == ';')
+}
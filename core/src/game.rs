@@ -0,0 +1,294 @@
+use crate::*;
+use crate::pgn::{PgnGame, PgnMove};
+
+use nanoserde::{SerBin, DeBin, DeBinErr, DeJsonErr, DeJsonState};
+
+
+// A game with its history, a single GameState has no memory of how it got there.
+// Undone moves are kept until a different move is played, so they can be redone.
+#[derive(Clone, Debug)]
+pub struct Game {
+    start: GameState,
+    position: GameState,
+    // Every move of the game, including those after the current ply that can be redone
+    moves: Vec<Move>,
+    // Undo info of the moves up to the current ply
+    history: Vec<UndoInfo>,
+    // Hash of the start position and of the position after every move up to the current ply
+    hashes: Vec<u64>,
+
+    // Tag pairs as in PGN, in insertion order
+    pub tags: Vec<(String, String)>,
+    pub result: GameResult,
+}
+impl Game {
+    pub fn new(mut start: GameState) -> Self {
+        start.generate_moves(start.current);
+        Self {
+            hashes: vec![start.hash()],
+            position: start.clone(),
+            start,
+            moves: Vec::new(),
+            history: Vec::new(),
+            tags: Vec::new(),
+            result: GameResult::Ongoing,
+        }
+    }
+    pub fn from_fen(fen: &str) -> Option<Self> {
+        Some(Self::new(GameState::from_fen(fen)?))
+    }
+
+
+    pub fn start_position(&self) -> &GameState {
+        &self.start
+    }
+    pub fn position(&self) -> &GameState {
+        &self.position
+    }
+    // The moves played up to the current ply
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.ply()]
+    }
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+    // The number of moves including those that can be redone
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+
+    // Plays a legal move at the current ply, dropping the moves that could be redone unless it is the next one.
+    // Returns false, leaving the game untouched, if the move is not legal.
+    pub fn play(&mut self, movement: Move) -> bool {
+        if self.moves.get(self.ply()) == Some(&movement) {
+            return self.redo().is_some();
+        }
+        if !self.position.is_legal_move(movement) {
+            return false;
+        }
+        self.moves.truncate(self.ply());
+        self.moves.push(movement);
+        self.advance();
+        true
+    }
+    pub fn play_san(&mut self, san: &str) -> bool {
+        match self.position.move_from_san(san) {
+            Some(movement) => self.play(movement),
+            None => false,
+        }
+    }
+
+    // Takes back the last move, returning it
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        let movement = self.moves[self.ply()];
+        self.position.unmake_move(movement, &undo);
+        self.position.generate_moves(self.position.current);
+        self.hashes.pop();
+        Some(movement)
+    }
+    // Plays the next undone move again, returning it
+    pub fn redo(&mut self) -> Option<Move> {
+        let movement = *self.moves.get(self.ply())?;
+        self.advance();
+        Some(movement)
+    }
+    // Undoes or redoes moves until the given ply is reached, returns false if there are not that many moves
+    pub fn go_to(&mut self, ply: usize) -> bool {
+        if ply > self.len() {
+            return false;
+        }
+        while self.ply() > ply {
+            self.undo();
+        }
+        while self.ply() < ply {
+            self.redo();
+        }
+        true
+    }
+    fn advance(&mut self) {
+        let movement = self.moves[self.ply()];
+        let undo = self.position.make_move(movement);
+        self.position.generate_moves(self.position.current);
+        self.history.push(undo);
+        self.hashes.push(self.position.hash());
+    }
+
+
+    pub fn hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+    // Every inconsistency of the current position, including a kept hash that went stale
+    pub fn validate(&self) -> Result<(), Vec<validate::Inconsistency>> {
+        self.position.validate()
+    }
+    // How often the current position occurred so far, counting itself
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.position.hash();
+        // Positions before the last capture or pawn move can not come back
+        let window = self.position.halfmove_count as usize + 1;
+        self.hashes.iter()
+            .rev()
+            .take(window)
+            .step_by(2)
+            .filter(|&&other| other == hash)
+            .count()
+    }
+    // Whether the game is over by the rules at the current ply, with the reason
+    pub fn end_term(&self) -> GameStateEndTerm {
+        let is_checked = self.position.is_king_checked(self.position.current);
+        if self.position.legal_moves().is_empty() {
+            if is_checked {
+                GameStateEndTerm::Checkmate
+            } else {
+                GameStateEndTerm::Stalemate
+            }
+        } else if self.repetition_count() >= 3 {
+            GameStateEndTerm::ThreeFoldRepetition
+        } else if self.position.halfmove_count >= 100 {
+            GameStateEndTerm::Move50Rule
+        } else {
+            GameStateEndTerm::None
+        }
+    }
+    // The result that follows from the rules at the current ply, Ongoing if the game is not over.
+    // Resignations and agreed draws only show up in the result field.
+    pub fn outcome(&self) -> GameResult {
+        match self.end_term() {
+            GameStateEndTerm::None => GameResult::Ongoing,
+            GameStateEndTerm::Checkmate => match self.position.current {
+                Color::White => GameResult::BlackWins,
+                Color::Black => GameResult::WhiteWins,
+            },
+            _ => GameResult::Draw,
+        }
+    }
+
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+
+    // The mainline of a PGN game, the variations are dropped
+    pub fn from_pgn(pgn: &PgnGame) -> Option<Self> {
+        let mut result = Self::new(pgn.start_position()?);
+        result.tags = pgn.tags.clone();
+        result.result = pgn.result;
+        for movement in &pgn.moves {
+            if !result.play(movement.movement) {
+                return None;
+            }
+        }
+        Some(result)
+    }
+    // The moves up to the current ply as a PGN game
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut result = PgnGame {
+            tags: self.tags.clone(),
+            moves: self.moves().iter().map(|&movement| PgnMove::new(movement)).collect(),
+            result: self.result,
+            ..Default::default()
+        };
+        let fen = self.start.to_fen();
+        if fen != GameState::FEN_CLASSIC {
+            result.set_tag("SetUp", "1");
+            result.set_tag("FEN", &fen);
+        }
+        result.set_tag("Result", self.result.as_str());
+        result
+    }
+
+
+    fn to_record(&self) -> GameRecord {
+        let mut state = self.start.clone();
+        let moves = self.moves.iter()
+            .map(|&movement| {
+                let san = state.move_to_san(movement);
+                state.make_move(movement);
+                san
+            })
+            .collect();
+        GameRecord {
+            fen: self.start.to_fen(),
+            moves,
+            ply: self.ply() as u64,
+            tags: self.tags.iter()
+                .map(|(name, value)| GameRecordTag { name: name.clone(), value: value.clone() })
+                .collect(),
+            result: self.result.as_str().to_string(),
+        }
+    }
+    fn from_record(record: GameRecord) -> Option<Self> {
+        let mut result = Self::from_fen(&record.fen)?;
+        for san in &record.moves {
+            if !result.play_san(san) {
+                return None;
+            }
+        }
+        if !result.go_to(record.ply as usize) {
+            return None;
+        }
+        result.tags = record.tags.into_iter().map(|tag| (tag.name, tag.value)).collect();
+        result.result = GameResult::parse(&record.result)?;
+        Some(result)
+    }
+}
+impl Default for Game {
+    // A game from the standard start position
+    fn default() -> Self {
+        Self::from_fen(GameState::FEN_CLASSIC).unwrap()
+    }
+}
+
+
+// Games are stored as their start FEN and SAN moves, so the format does not depend on the move encoding
+#[derive(SerJson, DeJson, SerBin, DeBin)]
+struct GameRecord {
+    fen: String,
+    moves: Vec<String>,
+    ply: u64,
+    tags: Vec<GameRecordTag>,
+    result: String,
+}
+#[derive(SerJson, DeJson, SerBin, DeBin)]
+struct GameRecordTag {
+    name: String,
+    value: String,
+}
+
+impl SerJson for Game {
+    fn ser_json(&self, d: usize, s: &mut nanoserde::SerJsonState) {
+        self.to_record().ser_json(d, s);
+    }
+}
+impl DeJson for Game {
+    fn de_json(s: &mut DeJsonState, i: &mut std::str::Chars) -> Result<Self, DeJsonErr> {
+        let record = GameRecord::de_json(s, i)?;
+        Self::from_record(record).ok_or_else(|| s.err_parse("game"))
+    }
+}
+impl SerBin for Game {
+    fn ser_bin(&self, output: &mut Vec<u8>) {
+        self.to_record().ser_bin(output);
+    }
+}
+impl DeBin for Game {
+    fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        let start = *offset;
+        let record = GameRecord::de_bin(offset, bytes)?;
+        Self::from_record(record).ok_or(DeBinErr { o: start, l: *offset - start, s: bytes.len() })
+    }
+}
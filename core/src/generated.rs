@@ -0,0 +1,3 @@
+// The tables of the wizard database, as statics that need no startup work.
+// Regenerate with `cargo run -p wizard -- emit-rust` after the database changes.
+include!("generated_tables.rs");
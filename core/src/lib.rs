@@ -1,11 +1,33 @@
-#![feature(total_cmp)]
-#![feature(try_trait_v2)]
-#![feature(never_type)]
-
 pub mod bitboard;
 
 pub mod consts;
 
+pub mod san;
+
+pub mod pgn;
+
+pub mod epd;
+
+pub mod game;
+
+pub mod tree;
+
+pub mod packed;
+
+pub mod builder;
+
+pub mod validate;
+
+pub mod diagram;
+
+pub mod tables;
+
+pub mod sliders;
+
+pub mod generated;
+
+pub mod perft;
+
 use log::{
     info,
     error,
@@ -113,7 +135,7 @@ impl Into<u8> for MoveType {
 }
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Move (u16);
 impl Move {
@@ -790,6 +812,7 @@ impl PlayerState {
 
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Direction {
     North,
     NorthEast,
@@ -833,6 +856,29 @@ impl Coord {
             rank: (self.rank as i8 + rank) as u8,
         }
     }
+
+    // Parses a square name such as "e4"
+    // Rank 0 is the 8th rank, same as the board layout
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        let file = match chars.next()? {
+            file @ 'a'..='h' => file as u8 - b'a',
+            _ => return None,
+        };
+        let rank = match chars.next()? {
+            rank @ '1'..='8' => b'8' - rank as u8,
+            _ => return None,
+        };
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(Self::new(file, rank))
+    }
+}
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file) as char, (b'8' - self.rank) as char)
+    }
 }
 impl std::ops::Add for Coord {
     type Output = Self;
@@ -856,6 +902,7 @@ impl std::ops::Sub for Coord {
 
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GameStateEndTerm {
     None,
     Stalemate,
@@ -866,6 +913,40 @@ pub enum GameStateEndTerm {
 }
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Ongoing,
+}
+impl GameResult {
+    pub fn parse(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Ongoing),
+            _ => None,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Ongoing => "*",
+        }
+    }
+}
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
 // A peice can max hold 28 moves i think
 // TODO verify this, currently i use 32 for buffer spcaes
 type PieceMoves = ArrayVec<Move, 32>;
@@ -883,6 +964,20 @@ pub enum MoveSpecialRules {
 pub type Board = [Tile; 64];
 
 
+// Everything a move throws away, enough to take it back with unmake_move
+#[derive(Clone, Copy, Debug)]
+pub struct UndoInfo {
+    // Index in the piece array, type and coord of the captured piece
+    pub captured: Option<(u8, PieceType, Coord)>,
+    pub castling: [bool; 4],
+    pub en_passant: Option<usize>,
+    pub halfmove_count: u32,
+    pub fullmove_count: u32,
+    // The hash before the move
+    pub zobrist: u64,
+}
+
+
 
 #[derive(Clone, Debug)]
 pub struct GameState {
@@ -895,6 +990,10 @@ pub struct GameState {
 
     pub halfmove_count: u32,
     pub fullmove_count: u32,
+
+    // Zobrist hash of the position, make_move and unmake_move keep it up to date.
+    // Anything else that changes the position has to set it with compute_hash.
+    pub zobrist: u64,
 }
 
 impl GameState {
@@ -926,8 +1025,268 @@ impl GameState {
     }
 
 
+    pub fn piece_at(&self, index: usize) -> Option<(PieceType, Color)> {
+        let tile = self.board[index];
+        if tile.is_empty() {
+            return None;
+        }
+        let color = tile.get_color();
+        let (piece, _, _) = self.get_player(color).pieces[tile.get_index() as usize];
+        Some((piece, color))
+    }
+
+    // All moves for the current player, including those that leave its own king in check
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut state = self.clone();
+        state.generate_moves(state.current);
+        state.get_player(state.current).pieces.iter()
+            .flat_map(|(_, _, moves)| moves.iter().copied())
+            .collect()
+    }
+    // All moves for the current player that do not leave its own king in check
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves().into_iter()
+            .filter(|&movement| !self.is_move_self_check(movement))
+            .collect()
+    }
+    pub fn is_move_self_check(&self, movement: Move) -> bool {
+        let mut state = self.clone();
+        state.play_move_unchecked(movement);
+        state.is_king_checked(self.current)
+    }
+
+
+    // Plays a legal move for the current player and hands the turn over.
+    // Returns false, leaving the position untouched, if the move is not legal.
     pub fn play_move(&mut self, movement: Move) -> bool {
-        false
+        if !self.is_legal_move(movement) {
+            return false;
+        }
+        self.make_move(movement);
+        self.generate_moves(self.current);
+        true
+    }
+    pub fn is_legal_move(&mut self, movement: Move) -> bool {
+        self.generate_moves(self.current);
+        let from = movement.get_from() as usize;
+        let is_generated = self.get_player(self.current).pieces.iter()
+            .any(|(_, coord, moves)| coord.index() == Some(from) && moves.contains(&movement));
+        is_generated && !self.is_move_self_check(movement)
+    }
+
+    // Plays a move without checking it, updates the move counters and hands the turn over.
+    // The returned info takes the move back with unmake_move.
+    pub fn make_move(&mut self, movement: Move) -> UndoInfo {
+        let from = movement.get_from() as usize;
+        let captured_index = match movement.get_type() {
+            MoveType::CaptureEnPassant => self.en_passant,
+            _ if movement.is_capture() => Some(movement.get_target() as usize),
+            _ => None,
+        };
+        let captured = captured_index.map(|index| {
+            let tile = self.board[index];
+            let (piece, coord, _) = self.get_player(!self.current).pieces[tile.get_index() as usize];
+            (tile.get_index(), piece, coord)
+        });
+        let undo = UndoInfo {
+            captured,
+            castling: self.castling_rights(),
+            en_passant: self.en_passant,
+            halfmove_count: self.halfmove_count,
+            fullmove_count: self.fullmove_count,
+            zobrist: self.zobrist,
+        };
+
+        // The castling and en passant keys of before the move are taken out and those of after it put in
+        let mover = self.current;
+        let (moved, _) = self.piece_at(from).unwrap();
+        let mut zobrist = self.zobrist ^ self.castling_key() ^ self.en_passant_key();
+        zobrist ^= Self::piece_key(moved, mover, from);
+        if let Some((_, piece, coord)) = captured {
+            zobrist ^= Self::piece_key(piece, !mover, coord.index().unwrap());
+        }
+
+        let is_pawn_move = moved == PieceType::Pawn;
+        self.play_move_unchecked(movement);
+
+        let to = movement.get_target();
+        let (landed, _) = self.piece_at(to as usize).unwrap();
+        zobrist ^= Self::piece_key(landed, mover, to as usize);
+        match movement.get_type() {
+            MoveType::KingCastle => zobrist ^= Self::piece_key(PieceType::Rook, mover, to as usize + 1) ^ Self::piece_key(PieceType::Rook, mover, to as usize - 1),
+            MoveType::QueenCastle => zobrist ^= Self::piece_key(PieceType::Rook, mover, to as usize - 2) ^ Self::piece_key(PieceType::Rook, mover, to as usize + 1),
+            _ => (),
+        }
+
+        if is_pawn_move || movement.is_capture() {
+            self.halfmove_count = 0;
+        } else {
+            self.halfmove_count += 1;
+        }
+        if self.current == Color::Black {
+            self.fullmove_count += 1;
+        }
+        self.current = !self.current;
+        self.zobrist = zobrist ^ consts::ZOBRIST_BLACK_TO_MOVE ^ self.castling_key() ^ self.en_passant_key();
+
+        #[cfg(debug_assertions)]
+        self.debug_validate("make_move");
+        undo
+    }
+    // Takes back the last move played with make_move.
+    // The piece moves are not regenerated, call generate_moves when they are needed.
+    pub fn unmake_move(&mut self, movement: Move, undo: &UndoInfo) {
+        self.current = !self.current;
+        let from = movement.get_from();
+        let to = movement.get_target();
+
+        if let Some(pawn) = self.en_passant {
+            self.board[Self::en_passant_tile(pawn)].set_en_passant(false);
+        }
+
+        match movement.get_type() {
+            MoveType::KingCastle => self.move_tile(to - 1, to + 1),
+            MoveType::QueenCastle => self.move_tile(to + 1, to - 2),
+            move_type if movement.is_promotion() => {
+                let promotion = san::promotion_type(move_type).unwrap();
+                let index = self.board[to as usize].get_index();
+                let player = self.get_player_mut(self.current);
+                player.bitboard.unset_bit(promotion, Coord::from(to));
+                player.bitboard.set_bit(PieceType::Pawn, Coord::from(to));
+                player.pieces[index as usize].0 = PieceType::Pawn;
+            }
+            _ => (),
+        }
+        self.move_tile(to, from);
+
+        if let Some((index, piece, coord)) = undo.captured {
+            let color = !self.current;
+            let player = self.get_player_mut(color);
+            player.pieces[index as usize] = (piece, coord, ArrayVec::new());
+            player.bitboard.set_bit(piece, coord);
+            self.board[coord.index().unwrap()] = Tile::with_piece(index, color);
+        }
+
+        self.set_castling_rights(undo.castling);
+        self.en_passant = undo.en_passant;
+        if let Some(pawn) = self.en_passant {
+            self.board[Self::en_passant_tile(pawn)].set_en_passant(true);
+        }
+        self.halfmove_count = undo.halfmove_count;
+        self.fullmove_count = undo.fullmove_count;
+        self.zobrist = undo.zobrist;
+
+        #[cfg(debug_assertions)]
+        self.debug_validate("unmake_move");
+    }
+    // Moves whatever is on a tile without touching the castling rights
+    fn move_tile(&mut self, from: u8, to: u8) {
+        let tile = self.board[from as usize];
+        self.board[to as usize] = tile;
+        self.board[from as usize] = Tile::empty();
+
+        let player = self.get_player_mut(tile.get_color());
+        let (piece, coord, _) = &mut player.pieces[tile.get_index() as usize];
+        player.bitboard.unset_bit(*piece, *coord);
+        *coord = Coord::from(to);
+        player.bitboard.set_bit(*piece, *coord);
+    }
+    // The tile a pawn skipped over with its double move
+    fn en_passant_tile(pawn: usize) -> usize {
+        // Black pawns land on the 5th rank, which is the upper half of the board
+        if pawn < 32 {
+            pawn - 8
+        } else {
+            pawn + 8
+        }
+    }
+
+    // White kingside, white queenside, black kingside, black queenside
+    pub fn castling_rights(&self) -> [bool; 4] {
+        [
+            self.white.can_kingside_castle,
+            self.white.can_queenside_castle,
+            self.black.can_kingside_castle,
+            self.black.can_queenside_castle,
+        ]
+    }
+    pub fn set_castling_rights(&mut self, rights: [bool; 4]) {
+        self.white.can_kingside_castle = rights[0];
+        self.white.can_queenside_castle = rights[1];
+        self.black.can_kingside_castle = rights[2];
+        self.black.can_queenside_castle = rights[3];
+    }
+
+    // The same position seen from the other side: ranks mirrored, colors swapped, the other side to move.
+    // Anything that holds for a position, its evaluation or perft count, should hold for its flip.
+    pub fn color_flipped(&self) -> Self {
+        let mut result = Self::default();
+        for index in 0..64 {
+            if let Some((piece, color)) = self.piece_at(index) {
+                let coord = Coord::from(index as u8);
+                result.push_piece(piece, !color, Coord::new(coord.file, 7 - coord.rank));
+            }
+        }
+        result.current = !self.current;
+        let [white_kingside, white_queenside, black_kingside, black_queenside] = self.castling_rights();
+        result.set_castling_rights([black_kingside, black_queenside, white_kingside, white_queenside]);
+        if let Some(pawn) = self.en_passant {
+            let pawn = pawn ^ 0b111000;
+            result.en_passant = Some(pawn);
+            result.board[Self::en_passant_tile(pawn)].set_en_passant(true);
+        }
+        result.halfmove_count = self.halfmove_count;
+        result.fullmove_count = self.fullmove_count;
+        result.zobrist = result.compute_hash();
+        result.generate_moves(result.current);
+        result
+    }
+
+    // Zobrist hash of the pieces, side to move, castling rights and en passant square
+    pub fn hash(&self) -> u64 {
+        self.zobrist
+    }
+    // The hash computed from scratch, which the kept hash has to match
+    pub fn compute_hash(&self) -> u64 {
+        let mut result = self.castling_key() ^ self.en_passant_key();
+        for (index, tile) in self.board.iter().enumerate() {
+            if tile.is_occupied() {
+                let color = tile.get_color();
+                let (piece, _, _) = self.get_player(color).pieces[tile.get_index() as usize];
+                result ^= Self::piece_key(piece, color, index);
+            }
+        }
+        if self.current == Color::Black {
+            result ^= consts::ZOBRIST_BLACK_TO_MOVE;
+        }
+        result
+    }
+    fn piece_key(piece: PieceType, color: Color, index: usize) -> u64 {
+        let piece: u8 = piece.into();
+        consts::ZOBRIST_PIECES[color as usize][piece as usize][index]
+    }
+    fn castling_key(&self) -> u64 {
+        self.castling_rights().iter()
+            .zip(consts::ZOBRIST_CASTLING)
+            .filter(|(right, _)| **right)
+            .fold(0, |result, (_, key)| result ^ key)
+    }
+    // The en passant file only counts when a pawn of the side to move stands next to the pawn that can be taken.
+    // Otherwise the position repeats the one without the en passant square.
+    fn en_passant_key(&self) -> u64 {
+        let Some(pawn) = self.en_passant else {
+            return 0;
+        };
+        let coord = Coord::from(pawn as u8);
+        let can_capture = [-1, 1].iter().any(|&file| {
+            let file = coord.file as i8 + file;
+            (0..8).contains(&file) && self.piece_at(coord.rank as usize * 8 + file as usize) == Some((PieceType::Pawn, self.current))
+        });
+        if can_capture {
+            consts::ZOBRIST_EN_PASSANT[pawn % 8]
+        } else {
+            0
+        }
     }
     pub fn play_move_unchecked(&mut self, movement: Move) {
         let move_type = movement.get_type();
@@ -953,16 +1312,13 @@ impl GameState {
 
 
         if move_type != MoveType::CaptureEnPassant {
-            match self.en_passant {
-                Some(tile_index) => {
-                    let ep_index = match self.current {
-                        Color::White => tile_index - 8,
-                        Color::Black => tile_index + 8,
-                    };
-                    self.board[ep_index as usize].set_en_passant(false);                    
-                    self.en_passant = None;
-                }
-                None => (),
+            if let Some(tile_index) = self.en_passant {
+                let ep_index = match self.current {
+                    Color::White => tile_index - 8,
+                    Color::Black => tile_index + 8,
+                };
+                self.board[ep_index].set_en_passant(false);
+                self.en_passant = None;
             }
         }
         
@@ -979,7 +1335,14 @@ impl GameState {
                 let tile_index = self.en_passant.unwrap();
                 let tile = self.board[tile_index];
                 self.move_capture_piece(tile);
-                self.board[tile.get_index() as usize] = Tile::empty();
+                self.board[tile_index] = Tile::empty();
+                self.en_passant = None;
+            }
+            MoveType::KingCastle => {
+                self.move_castle(to + 1, to - 1);
+            }
+            MoveType::QueenCastle => {
+                self.move_castle(to - 2, to + 1);
             }
             MoveType::PromotionKnight | MoveType::CapturePromotionKnight => {
                 self.move_promote_pawn(to as usize, PieceType::Knight);
@@ -1003,35 +1366,41 @@ impl GameState {
         let index = attacker.get_index() as usize;
         let (attacker_type, coord, _) = &mut player.pieces[index];
 
-        // Clear from bit
-        player.bitboard.unset_bit(*attacker_type, *coord);
-        // Set coord to the new position
-        *coord = Coord::from(to);
-        // Set to bit
-        player.bitboard.set_bit(*attacker_type, *coord);
-
         if *attacker_type == PieceType::King {
             player.can_kingside_castle = false;
             player.can_queenside_castle = false;
         }
         if *attacker_type == PieceType::Rook {
-            let rank = match current {
-                Color::White => 7,
-                Color::Black => 0,
-            };
-
+            // Rights are lost when the rook leaves its starting corner
+            let rank = Self::home_rank(current);
             if *coord == (Coord { rank, file: 0}) {
                 player.can_queenside_castle = false;
             } else if *coord == (Coord { rank, file: 7}) {
                 player.can_kingside_castle = false;
             }
         }
+
+        // Clear from bit
+        player.bitboard.unset_bit(*attacker_type, *coord);
+        // Set coord to the new position
+        *coord = Coord::from(to);
+        // Set to bit
+        player.bitboard.set_bit(*attacker_type, *coord);
     }
     fn move_capture_piece(&mut self, target: Tile) {
+        let rank = Self::home_rank(!self.current);
         let enemy = self.get_player_mut(!self.current);
 
         let index = target.get_index() as usize;
         let piece = &mut enemy.pieces[index];
+        if piece.0 == PieceType::Rook {
+            // A captured rook takes its castling rights with it
+            if piece.1 == (Coord { rank, file: 0}) {
+                enemy.can_queenside_castle = false;
+            } else if piece.1 == (Coord { rank, file: 7}) {
+                enemy.can_kingside_castle = false;
+            }
+        }
         enemy.bitboard.unset_bit(piece.0, piece.1);
         *piece = (PieceType::None, Coord::default(), ArrayVec::new());
     }
@@ -1043,8 +1412,19 @@ impl GameState {
         
         player.pieces[index as usize].0 = promotion;
     }
-    pub fn move_castle(&mut self) {
+    fn move_castle(&mut self, rook_from: u8, rook_to: u8) {
+        let rook = self.board[rook_from as usize];
+        self.board[rook_to as usize] = rook;
+        self.board[rook_from as usize] = Tile::empty();
+        self.move_piece(rook, rook_to);
+    }
 
+    // The rank the pieces of a color start on, rank 0 is the top of the board
+    pub fn home_rank(color: Color) -> u8 {
+        match color {
+            Color::White => 7,
+            Color::Black => 0,
+        }
     }
 
     pub fn generate_king_masks(&self, color: Color) -> (Bitboard, Bitboard, Bitboard) {
@@ -1062,24 +1442,28 @@ impl GameState {
         }
         
         occlusion |= enemy.occupancy() | player.king;
-        let rays_diagonal = PlayerBitboard::generate_rook_attacks(player.king, occlusion);
-        let rays_straight = PlayerBitboard::generate_bishop_attacks(player.king, occlusion);
-        let rays_all = rays_straight | rays_diagonal;
-
-        let king_check_mask = {
-            (rays_diagonal & PlayerBitboard::generate_rook_attacks(enemy.rooks, occlusion)) | 
-            (rays_straight & PlayerBitboard::generate_bishop_attacks(enemy.bishops, occlusion)) |
-            (rays_all & PlayerBitboard::generate_queen_attacks(enemy.queens, occlusion)) |
-            (enemy.knights & PlayerBitboard::generate_knight_attacks(player.king)) |
-            (enemy.pawns & PlayerBitboard::generate_pawn_attacks(player.king, color))
-        };
-
-        let occlusion = enemy.occupancy() | player.king;
-        let king_pin_mask = {
-            (rays_diagonal & PlayerBitboard::generate_rook_attacks(enemy.rooks, occlusion)) | 
-            (rays_straight & PlayerBitboard::generate_bishop_attacks(enemy.bishops, occlusion)) |
-            (rays_all & PlayerBitboard::generate_queen_attacks(enemy.queens, occlusion))
+        let king = player.king.lsb().unwrap();
+        let straight = enemy.rooks | enemy.queens;
+        let diagonal = enemy.bishops | enemy.queens;
+
+        // A check is answered by taking the checker or, for a slider, by stepping in between
+        let king_check_mask = self.checkers(color).iter()
+            .fold(Bitboard(0), |mask, checker| mask | tables::between(king, checker) | Bitboard::from_square(checker));
+
+        // A piece is pinned if it is the only piece between the king and an enemy slider on the same line
+        let own = player.occupancy();
+        let sliders = sliders::sliding_attacks();
+        let pinners = {
+            (sliders.rook_attacks(king, Bitboard(0)) & straight) |
+            (sliders.bishop_attacks(king, Bitboard(0)) & diagonal)
         };
+        let king_pin_mask = pinners.iter()
+            .map(|pinner| (pinner, tables::between(king, pinner)))
+            .filter(|&(_, between)| {
+                let blockers = between & occlusion;
+                blockers.count() == 1 && !(blockers & own).is_empty()
+            })
+            .fold(Bitboard(0), |mask, (pinner, between)| mask | between | Bitboard::from_square(pinner));
 
         (king_check_mask, king_defence_mask, king_pin_mask)
     }
@@ -1124,21 +1508,22 @@ impl GameState {
                 None => return true,
             };
 
+            let tile = board[to_index as usize];
+
+            // A masked out tile still blocks sliding pieces
             // TODO: combine movement mask and other mask since they only need to be calc for a piece once.
             if !movement_mask.is_occupied(to_index as usize) {
-                return false;
+                return tile.is_occupied();
             }
             if king_pin_mask.is_occupied(from_index as usize) {
                 // TODO: The first pin only has to be checked once per piece. 
                 if !king_pin_mask.is_occupied(to_index as usize) {
-                    return false;
+                    return tile.is_occupied();
                 }
             }
 //            if will_move_check_self(piece_type, from, to) {
 //            }
-            
 
-            let tile = board[to_index as usize];
             if tile.is_empty() {
                 // quiet move
                 result.push(Move::new(MoveType::Quiet, from_index, to_index));
@@ -1152,25 +1537,23 @@ impl GameState {
                 true
             }
         };
-        let sliding_moves = |mut result: PieceMoves, piece_type: PieceType, from: Coord, direction: Direction, movement_mask: Bitboard| -> PieceMoves {
-            let (dx, dy ) = match direction {
-                Direction::North        => ( 0, -1),
-                Direction::NorthEast    => ( 1, -1),
-                Direction::NorthWest    => (-1, -1),
-                Direction::South        => ( 0,  1),
-                Direction::SouthEast    => ( 1,  1),
-                Direction::SouthWest    => (-1,  1),
-                Direction::East         => ( 1,  0),
-                Direction::West         => (-1,  0),
+        // Slider targets come from the selected backend, then go through the same masks as the other pieces
+        let sliders = sliders::sliding_attacks();
+        let own = player_bitboard.occupancy() | player_bitboard.king;
+        let occupancy = own | enemy_bitboard.occupancy() | enemy_bitboard.king;
+        let sliding_moves = |result: &mut PieceMoves, piece_type: PieceType, from: Coord, movement_mask: Bitboard| {
+            let square = match from.index().and_then(Square::from_index) {
+                Some(square) => square,
+                None => return,
             };
-    
-            for distance in 1..(8 as i8) {
-                let to = from.offset(dx * distance, dy * distance);
-                if single_move_attack(&mut result, piece_type, from, to, movement_mask) {
-                    break;
-                }
+            let attacks = match piece_type {
+                PieceType::Rook => sliders.rook_attacks(square, occupancy),
+                PieceType::Bishop => sliders.bishop_attacks(square, occupancy),
+                _ => sliders.queen_attacks(square, occupancy),
+            };
+            for to in attacks & !own {
+                single_move_attack(result, piece_type, from, to.coord(), movement_mask);
             }
-            result
         };
         let pawn_moves = |mut result: PieceMoves, from: Coord| -> PieceMoves {
             let from_index = match from.index() {
@@ -1258,6 +1641,38 @@ impl GameState {
         king_moves();
         */
 
+        // Castling is generated up front, since it needs the player state that is borrowed below
+        let mut castles: ArrayVec<Move, 2> = ArrayVec::new();
+        {
+            let player = self.get_player(color);
+            let rank = Self::home_rank(color);
+            let from = player.king().1;
+            let occupancy = {
+                player_bitboard.occupancy() | player_bitboard.king |
+                enemy_bitboard.occupancy() | enemy_bitboard.king
+            };
+            let enemy_attacks = enemy_bitboard.attacks(occupancy, !color);
+
+            // The files between king and rook must be empty,
+            // and the king can not start, pass or end on an attacked file
+            let can_castle = |rook_file: u8, empty: &[u8], safe: &[u8]| -> bool {
+                let index = |file: u8| Coord::new(file, rank).index().unwrap();
+                player_bitboard.rooks.is_occupied(index(rook_file)) &&
+                empty.iter().all(|&file| board[index(file)].is_empty()) &&
+                safe.iter().all(|&file| !enemy_attacks.is_occupied(index(file)))
+            };
+
+            if let Some(from_index) = from.index().filter(|_| from == Coord::new(4, rank)) {
+                let from_index = from_index as u8;
+                if player.can_kingside_castle && can_castle(7, &[5, 6], &[4, 5, 6]) {
+                    castles.push(Move::new(MoveType::KingCastle, from_index, from_index + 2));
+                }
+                if player.can_queenside_castle && can_castle(0, &[1, 2, 3], &[4, 3, 2]) {
+                    castles.push(Move::new(MoveType::QueenCastle, from_index, from_index - 2));
+                }
+            }
+        }
+
         for (piece, coord, moves) in &mut self.get_player_mut(color).pieces {
             let coord = *coord;
             moves.clear();
@@ -1268,16 +1683,10 @@ impl GameState {
                     result = pawn_moves(result, coord);
                 }
                 PieceType::Rook => {
-                    result = sliding_moves(result, PieceType::Rook, coord, Direction::North, king_check_mask);
-                    result = sliding_moves(result, PieceType::Rook, coord, Direction::South, king_check_mask);
-                    result = sliding_moves(result, PieceType::Rook, coord, Direction::East, king_check_mask);
-                    result = sliding_moves(result, PieceType::Rook, coord, Direction::West, king_check_mask);
+                    sliding_moves(&mut result, PieceType::Rook, coord, king_check_mask);
                 }
                 PieceType::Bishop => {
-                    result = sliding_moves(result, PieceType::Bishop, coord, Direction::NorthEast, king_check_mask);
-                    result = sliding_moves(result, PieceType::Bishop, coord, Direction::NorthWest, king_check_mask);
-                    result = sliding_moves(result, PieceType::Bishop, coord, Direction::SouthEast, king_check_mask);
-                    result = sliding_moves(result, PieceType::Bishop, coord, Direction::SouthWest, king_check_mask);
+                    sliding_moves(&mut result, PieceType::Bishop, coord, king_check_mask);
                 }
                 PieceType::Knight => {
                     single_move_attack(&mut result, PieceType::Knight, coord, coord.offset( 2, 1), king_check_mask);
@@ -1290,14 +1699,7 @@ impl GameState {
                     single_move_attack(&mut result, PieceType::Knight, coord, coord.offset( -1, -2), king_check_mask);
                 }
                 PieceType::Queen => {
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::North, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::South, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::East, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::West, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::NorthEast, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::NorthWest, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::SouthEast, king_check_mask);
-                    result = sliding_moves(result, PieceType::Queen, coord, Direction::SouthWest, king_check_mask);
+                    sliding_moves(&mut result, PieceType::Queen, coord, king_check_mask);
                 }
                 PieceType::King => {
                     single_move_attack(&mut result, PieceType::King, coord, coord.offset( 1, 1), king_defence_mask);
@@ -1308,7 +1710,7 @@ impl GameState {
                     single_move_attack(&mut result, PieceType::King, coord, coord.offset( -1, 1), king_defence_mask);
                     single_move_attack(&mut result, PieceType::King, coord, coord.offset( -1, 0), king_defence_mask);
                     single_move_attack(&mut result, PieceType::King, coord, coord.offset( -1, -1), king_defence_mask);
-                    
+                    result.extend(castles.iter().copied());
                 }
                 _ => (),
             }
@@ -1394,31 +1796,60 @@ impl GameState {
         });
         move_count
     }
+    // The generated moves can still leave the king in check, so mates are decided on the legal moves
     pub fn is_king_stalemated(&self, color: Color) -> bool {
-        self.move_count(color) == 0 && !self.is_king_checked(color)
+        !self.has_legal_moves(color) && !self.is_king_checked(color)
     }
     pub fn is_king_checkmated(&self, color: Color) -> bool {
-        self.move_count(color) == 0 && self.is_king_checked(color)
+        !self.has_legal_moves(color) && self.is_king_checked(color)
+    }
+    fn has_legal_moves(&self, color: Color) -> bool {
+        let mut state = self.clone();
+        state.current = color;
+        !state.legal_moves().is_empty()
     }
     pub fn is_king_checked(&self, color: Color) -> bool {
         let player = self.get_player(color);
         let opponent = self.get_player(!color);
-        let difference = player.bitboard.king & opponent.bitboard.attacks(player.bitboard.occupancy(), !color);
+        let occlusion = player.bitboard.occupancy() | opponent.bitboard.king;
+        let difference = player.bitboard.king & opponent.bitboard.attacks(occlusion, !color);
         difference.0 != 0
     }
+    // The enemy pieces giving check to the king of the color
+    pub fn checkers(&self, color: Color) -> Bitboard {
+        let player = self.get_player(color).bitboard;
+        let enemy = self.get_player(!color).bitboard;
+        let occlusion = player.occupancy() | player.king | enemy.occupancy() | enemy.king;
+        let sliders = match player.king.lsb() {
+            Some(king) => {
+                let sliders = sliders::sliding_attacks();
+                (sliders.rook_attacks(king, occlusion) & (enemy.rooks | enemy.queens)) |
+                (sliders.bishop_attacks(king, occlusion) & (enemy.bishops | enemy.queens))
+            }
+            None => Bitboard(0),
+        };
+        sliders |
+        (PlayerBitboard::generate_knight_attacks(player.king) & enemy.knights) |
+        (PlayerBitboard::generate_pawn_attacks(player.king, color) & enemy.pawns)
+    }
 
 
+    // Adds a piece without checking the tile is free.
+    // The king always takes the first slot of the piece array, other pieces are appended after it.
     pub fn push_piece(&mut self, piece: PieceType, color: Color, coord: Coord) {
-        let index = match color {
-            Color::White => {
-                self.white.bitboard.set_bit(piece, coord);
-                self.white.pieces.push((piece, coord, ArrayVec::new()));
-                self.white.pieces.len() - 1
+        let player = self.get_player_mut(color);
+        if player.pieces.is_empty() {
+            player.pieces.push((PieceType::None, Coord::default(), ArrayVec::new()));
+        }
+        player.bitboard.set_bit(piece, coord);
+        let index = match piece {
+            PieceType::King => {
+                player.pieces[0] = (piece, coord, ArrayVec::new());
+                0
             }
-            Color::Black => {
-                self.black.bitboard.set_bit(piece, coord);
-                self.black.pieces.push((piece, coord, ArrayVec::new()));
-                self.black.pieces.len() - 1
+            _ => {
+                player.pieces.push((piece, coord, ArrayVec::new()));
+                player.pieces.len() - 1
             }
         };
         self.board[coord.index().unwrap()] = Tile::with_piece(index as u8, color);
@@ -1432,7 +1863,8 @@ impl GameState {
         // + 4 for castling rights
         // + 2 for en passant
         // + 3 for whitespace
-        const FEN_MAX_LENGTH: usize = (((8 * 8) + 7) + (1 + 4 + 2 )) + 3;
+        // + 2 * 11 for the move counters and their whitespace
+        const FEN_MAX_LENGTH: usize = (((8 * 8) + 7) + (1 + 4 + 2 )) + 3 + 2 * 11;
         let mut buffer: ArrayString<FEN_MAX_LENGTH> = ArrayString::new();
 
 
@@ -1452,6 +1884,9 @@ impl GameState {
                     }
                     iter.next();
                 }
+                // Empty squares at the end of the board
+                buffer.push(char::from_digit((64 - index) as u32, 10).unwrap());
+                continue 'outer;
             } else {
                 let symbol = if tile.is_empty() {
                     ' '
@@ -1491,8 +1926,8 @@ impl GameState {
 
 
         // castling rights
-        let white_rights = self.white.can_kingside_castle && self.white.can_queenside_castle;
-        let black_rights = self.black.can_kingside_castle && self.black.can_queenside_castle;
+        let white_rights = self.white.can_kingside_castle || self.white.can_queenside_castle;
+        let black_rights = self.black.can_kingside_castle || self.black.can_queenside_castle;
         if !white_rights && !black_rights {
             buffer.push('-')
         } else {
@@ -1505,7 +1940,17 @@ impl GameState {
 
 
         // en passant
-        buffer.push('-');
+        match self.en_passant {
+            Some(index) => {
+                // The square behind the pawn that just made a double move
+                let index = match self.current {
+                    Color::White => index - 8,
+                    Color::Black => index + 8,
+                };
+                buffer.push_str(&Coord::from(index as u8).to_string());
+            }
+            None => buffer.push('-'),
+        }
         buffer.push(' ');
 
 
@@ -1526,8 +1971,10 @@ impl GameState {
 
     pub fn from_fen(fen: &str) -> Option<Self> {
         let mut result = Self::default();
-        let fields: ArrayVec<&str, 6> = fen.split_whitespace().collect();
-    
+        let mut fields: ArrayVec<&str, 6> = ArrayVec::new();
+        for field in fen.split_whitespace() {
+            fields.try_push(field).ok()?;
+        }
         
         
         // Parse board positions
@@ -1535,65 +1982,69 @@ impl GameState {
         let mut black_king_coord = None;
         result.white.pieces.push((PieceType::King, Coord::default(), ArrayVec::new()));
         result.black.pieces.push((PieceType::King, Coord::default(), ArrayVec::new()));
-        if let Some(&field) = fields.get(0) {
-            let ranks: ArrayVec<&str, 8> = field.split("/").collect();
+        if let Some(&field) = fields.first() {
+            let mut ranks: ArrayVec<&str, 8> = ArrayVec::new();
+            for rank in field.split('/') {
+                ranks.try_push(rank).ok()?;
+            }
             if ranks.len() != 8 {
                 return None;
             }
 
             for (rank_index, rank) in ranks.iter().enumerate() {
                 let rank_index = rank_index as u8;
-                let mut file_index = 0;
+                let mut file_index: u8 = 0;
                 for file in rank.chars() {
-                    match file {
-                        'p' => result.push_piece(PieceType::Pawn, Color::Black, Coord::new(file_index, rank_index)),
-                        'r' => result.push_piece(PieceType::Rook, Color::Black, Coord::new(file_index, rank_index)),
-                        'n' => result.push_piece(PieceType::Knight, Color::Black, Coord::new(file_index, rank_index)),
-                        'b' => result.push_piece(PieceType::Bishop, Color::Black, Coord::new(file_index, rank_index)),
-                        'q' => result.push_piece(PieceType::Queen, Color::Black, Coord::new(file_index, rank_index)),
-                        'k' => match black_king_coord {
-                            None => {
-                                let coord = Coord::new(file_index, rank_index);
-                                result.board[coord.index()?] = Tile::with_piece(0, Color::Black);
-                                result.black.bitboard.set_bit(PieceType::King, coord);
-                                black_king_coord = Some(coord);
-                            }
-                            Some(_) => return None,
-                        },
+                    let (piece, color) = match file {
+                        'p' => (PieceType::Pawn, Color::Black),
+                        'r' => (PieceType::Rook, Color::Black),
+                        'n' => (PieceType::Knight, Color::Black),
+                        'b' => (PieceType::Bishop, Color::Black),
+                        'q' => (PieceType::Queen, Color::Black),
+                        'k' => (PieceType::King, Color::Black),
             
-                        'P' => result.push_piece(PieceType::Pawn, Color::White, Coord::new(file_index, rank_index)),
-                        'R' => result.push_piece(PieceType::Rook, Color::White, Coord::new(file_index, rank_index)),
-                        'N' => result.push_piece(PieceType::Knight, Color::White, Coord::new(file_index, rank_index)),
-                        'B' => result.push_piece(PieceType::Bishop, Color::White, Coord::new(file_index, rank_index)),
-                        'Q' => result.push_piece(PieceType::Queen, Color::White, Coord::new(file_index, rank_index)),
-                        'K' => match white_king_coord {
-                            None => {
-                                let coord = Coord::new(file_index, rank_index);
-                                result.board[coord.index()?] = Tile::with_piece(0, Color::White);
-                                result.white.bitboard.set_bit(PieceType::King, coord);
-                                white_king_coord = Some(coord)
-                            }
-                            Some(_) => return None,
-                        },
+                        'P' => (PieceType::Pawn, Color::White),
+                        'R' => (PieceType::Rook, Color::White),
+                        'N' => (PieceType::Knight, Color::White),
+                        'B' => (PieceType::Bishop, Color::White),
+                        'Q' => (PieceType::Queen, Color::White),
+                        'K' => (PieceType::King, Color::White),
             
-                        '1' => file_index += 1 - 1,
-                        '2' => file_index += 2 - 1,
-                        '3' => file_index += 3 - 1,
-                        '4' => file_index += 4 - 1,
-                        '5' => file_index += 5 - 1,
-                        '6' => file_index += 6 - 1,
-                        '7' => file_index += 7 - 1,
-                        '8' => file_index += 8 - 1,
+                        empty @ '1'..='8' => {
+                            file_index += empty as u8 - b'0';
+                            if file_index > 8 {
+                                return None;
+                            }
+                            continue;
+                        }
 
                         _ => return None,
+                    };
+
+                    let coord = Coord::new(file_index, rank_index);
+                    let index = coord.index()?;
+                    if piece == PieceType::King {
+                        let king_coord = match color {
+                            Color::White => &mut white_king_coord,
+                            Color::Black => &mut black_king_coord,
+                        };
+                        if king_coord.replace(coord).is_some() {
+                            return None;
+                        }
+                        result.board[index] = Tile::with_piece(0, color);
+                        result.get_player_mut(color).bitboard.set_bit(PieceType::King, coord);
+                    } else {
+                        if result.get_player(color).pieces.is_full() {
+                            return None;
+                        }
+                        result.push_piece(piece, color, coord);
                     }
 
                     file_index += 1;
                 }
-                // TODO: ensure length of each file is 8
-                /*if file_index != 8 {
+                if file_index != 8 {
                     return None;
-                }*/
+                }
             }
         }
         result.white.pieces[0].1 = white_king_coord?;
@@ -1661,12 +2112,14 @@ impl GameState {
                     'h' => 7,
                     _ => return None,
                 };
-                let rank = match *tile.get(1)? {
-                    '3' => 5,
-                    '6' => 2,
+                // The pawn that made the double move sits one rank further
+                let (rank, pawn_rank) = match (*tile.get(1)?, result.current) {
+                    ('3', Color::Black) => (5, 4),
+                    ('6', Color::White) => (2, 3),
                     _ => return None,
                 };
                 result.board[rank * 8 + file].set_en_passant(true);
+                result.en_passant = Some(pawn_rank * 8 + file);
             } else if field.len() != 1 {
                 return None;
             }
@@ -1677,13 +2130,20 @@ impl GameState {
         if let Some(&field) = fields.get(4) {
             result.halfmove_count = field.parse().ok()?;
         }
+        // The fullmove number starts at 1, even when the field is left out
+        result.fullmove_count = 1;
         if let Some(&field) = fields.get(5) {
             result.fullmove_count = field.parse().ok()?;
             if result.fullmove_count == 0 {
                 return None;
             }
         }
-    
+
+        result.zobrist = result.compute_hash();
+        // Anything else, like an en passant square without the pawn that moved, leaves the position inconsistent
+        if result.validate().is_err() {
+            return None;
+        }
         Some(result)
     }
 }
@@ -1692,7 +2152,7 @@ impl GameState {
 }
 impl Default for GameState {
     fn default() -> Self {
-        GameState {
+        let mut result = GameState {
             board: [Tile::empty(); 64],
             current: Color::White,
             black: PlayerState::default(),
@@ -1700,7 +2160,10 @@ impl Default for GameState {
             halfmove_count: 0,
             fullmove_count: 0,
             en_passant: None,
-        }
+            zobrist: 0,
+        };
+        result.zobrist = result.compute_hash();
+        result
     }
 }
 
@@ -1717,76 +2180,106 @@ pub fn init_logger(path: PathBuf) {
 
 
 
+// Tiles in board order, a8 is 0 and h1 is 63, the same as the board and bitboard indices
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
 pub enum Square {
-    A1 = 8 * 0 + 0,
-    B1 = 8 * 0 + 1,
-    C1 = 8 * 0 + 2,
-    D1 = 8 * 0 + 3,
-    E1 = 8 * 0 + 4,
-    F1 = 8 * 0 + 5,
-    G1 = 8 * 0 + 6,
-    H1 = 8 * 0 + 7,
-
-    A2 = 8 * 1 + 0,
-    B2 = 8 * 1 + 1,
-    C2 = 8 * 1 + 2,
-    D2 = 8 * 1 + 3,
-    E2 = 8 * 1 + 4,
-    F2 = 8 * 1 + 5,
-    G2 = 8 * 1 + 6,
-    H2 = 8 * 1 + 7,
-
-    A3 = 8 * 2 + 0,
-    B3 = 8 * 2 + 1,
-    C3 = 8 * 2 + 2,
-    D3 = 8 * 2 + 3,
-    E3 = 8 * 2 + 4,
-    F3 = 8 * 2 + 5,
-    G3 = 8 * 2 + 6,
-    H3 = 8 * 2 + 7,
-
-    A4 = 8 * 3 + 0,
-    B4 = 8 * 3 + 1,
-    C4 = 8 * 3 + 2,
-    D4 = 8 * 3 + 3,
-    E4 = 8 * 3 + 4,
-    F4 = 8 * 3 + 5,
-    G4 = 8 * 3 + 6,
-    H4 = 8 * 3 + 7,
-
-    A5 = 8 * 4 + 0,
-    B5 = 8 * 4 + 1,
-    C5 = 8 * 4 + 2,
-    D5 = 8 * 4 + 3,
-    E5 = 8 * 4 + 4,
-    F5 = 8 * 4 + 5,
-    G5 = 8 * 4 + 6,
-    H5 = 8 * 4 + 7,
-
-    A6 = 8 * 5 + 0,
-    B6 = 8 * 5 + 1,
-    C6 = 8 * 5 + 2,
-    D6 = 8 * 5 + 3,
-    E6 = 8 * 5 + 4,
-    F6 = 8 * 5 + 5,
-    G6 = 8 * 5 + 6,
-    H6 = 8 * 5 + 7,
-
-    A7 = 8 * 6 + 0,
-    B7 = 8 * 6 + 1,
-    C7 = 8 * 6 + 2,
-    D7 = 8 * 6 + 3,
-    E7 = 8 * 6 + 4,
-    F7 = 8 * 6 + 5,
-    G7 = 8 * 6 + 6,
-    H7 = 8 * 6 + 7,
-
-    A8 = 8 * 7 + 0,
-    B8 = 8 * 7 + 1,
-    C8 = 8 * 7 + 2,
-    D8 = 8 * 7 + 3,
-    E8 = 8 * 7 + 4,
-    F8 = 8 * 7 + 5,
-    G8 = 8 * 7 + 6,
-    H8 = 8 * 7 + 7,
+    A8 = 0,
+    B8 = 1,
+    C8 = 2,
+    D8 = 3,
+    E8 = 4,
+    F8 = 5,
+    G8 = 6,
+    H8 = 7,
+
+    A7 = 8,
+    B7 = 9,
+    C7 = 10,
+    D7 = 11,
+    E7 = 12,
+    F7 = 13,
+    G7 = 14,
+    H7 = 15,
+
+    A6 = 16,
+    B6 = 17,
+    C6 = 18,
+    D6 = 19,
+    E6 = 20,
+    F6 = 21,
+    G6 = 22,
+    H6 = 23,
+
+    A5 = 24,
+    B5 = 25,
+    C5 = 26,
+    D5 = 27,
+    E5 = 28,
+    F5 = 29,
+    G5 = 30,
+    H5 = 31,
+
+    A4 = 32,
+    B4 = 33,
+    C4 = 34,
+    D4 = 35,
+    E4 = 36,
+    F4 = 37,
+    G4 = 38,
+    H4 = 39,
+
+    A3 = 40,
+    B3 = 41,
+    C3 = 42,
+    D3 = 43,
+    E3 = 44,
+    F3 = 45,
+    G3 = 46,
+    H3 = 47,
+
+    A2 = 48,
+    B2 = 49,
+    C2 = 50,
+    D2 = 51,
+    E2 = 52,
+    F2 = 53,
+    G2 = 54,
+    H2 = 55,
+
+    A1 = 56,
+    B1 = 57,
+    C1 = 58,
+    D1 = 59,
+    E1 = 60,
+    F1 = 61,
+    G1 = 62,
+    H1 = 63,
+}
+impl Square {
+    pub const ALL: [Square; 64] = [
+        Self::A8, Self::B8, Self::C8, Self::D8, Self::E8, Self::F8, Self::G8, Self::H8,
+        Self::A7, Self::B7, Self::C7, Self::D7, Self::E7, Self::F7, Self::G7, Self::H7,
+        Self::A6, Self::B6, Self::C6, Self::D6, Self::E6, Self::F6, Self::G6, Self::H6,
+        Self::A5, Self::B5, Self::C5, Self::D5, Self::E5, Self::F5, Self::G5, Self::H5,
+        Self::A4, Self::B4, Self::C4, Self::D4, Self::E4, Self::F4, Self::G4, Self::H4,
+        Self::A3, Self::B3, Self::C3, Self::D3, Self::E3, Self::F3, Self::G3, Self::H3,
+        Self::A2, Self::B2, Self::C2, Self::D2, Self::E2, Self::F2, Self::G2, Self::H2,
+        Self::A1, Self::B1, Self::C1, Self::D1, Self::E1, Self::F1, Self::G1, Self::H1,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+    pub fn index(self) -> usize {
+        self as usize
+    }
+    pub fn coord(self) -> Coord {
+        Coord::from(self as u8)
+    }
+}
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.coord(), f)
+    }
 }
\ No newline at end of file
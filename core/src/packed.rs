@@ -0,0 +1,174 @@
+use crate::*;
+
+use nanoserde::{SerBin, DeBin, DeBinErr};
+
+
+// Fixed size encoding of a position, for opening books, training data and position databases.
+//  0..8:  occupancy, bit n set if board index n holds a piece, little endian
+//  8..24: a nibble per occupied tile in board order, low nibble first: color << 3 | piece type
+// 24:     bit 0 black to move, bits 1..5 castling rights: white kingside, white queenside, black kingside, black queenside
+// 25:     file of the en passant pawn plus one, 0 without en passant
+// 26..28: halfmove clock, little endian
+// 28..30: fullmove number, little endian
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct PackedPosition(pub [u8; PackedPosition::SIZE]);
+impl PackedPosition {
+    pub const SIZE: usize = 30;
+
+    const PIECES_OFFSET: usize = 8;
+    const FLAGS_OFFSET: usize = 24;
+    const EN_PASSANT_OFFSET: usize = 25;
+    const HALFMOVE_OFFSET: usize = 26;
+    const FULLMOVE_OFFSET: usize = 28;
+
+    // Clocks that do not fit in 16 bits are saturated
+    pub fn pack(state: &GameState) -> Self {
+        let mut bytes = [0; Self::SIZE];
+
+        let mut occupancy = 0u64;
+        let mut count = 0;
+        for index in 0..64 {
+            if let Some((piece, color)) = state.piece_at(index) {
+                occupancy |= 1 << index;
+                let piece: u8 = piece.into();
+                let color: u8 = color.into();
+                let nibble = color << 3 | piece;
+                bytes[Self::PIECES_OFFSET + count / 2] |= nibble << (4 * (count % 2));
+                count += 1;
+            }
+        }
+        bytes[..Self::PIECES_OFFSET].copy_from_slice(&occupancy.to_le_bytes());
+
+        let mut flags = (state.current == Color::Black) as u8;
+        for (index, right) in state.castling_rights().iter().enumerate() {
+            flags |= (*right as u8) << (index + 1);
+        }
+        bytes[Self::FLAGS_OFFSET] = flags;
+        bytes[Self::EN_PASSANT_OFFSET] = state.en_passant.map_or(0, |pawn| pawn as u8 % 8 + 1);
+
+        let halfmove = state.halfmove_count.min(u16::MAX as u32) as u16;
+        let fullmove = state.fullmove_count.min(u16::MAX as u32) as u16;
+        bytes[Self::HALFMOVE_OFFSET..Self::HALFMOVE_OFFSET + 2].copy_from_slice(&halfmove.to_le_bytes());
+        bytes[Self::FULLMOVE_OFFSET..Self::FULLMOVE_OFFSET + 2].copy_from_slice(&fullmove.to_le_bytes());
+        Self(bytes)
+    }
+
+    // None if the bytes do not describe a position with one king per side, at most 16 pieces each,
+    // no pawns on the back ranks and the pawn behind the en passant square
+    pub fn unpack(&self) -> Option<GameState> {
+        let bytes = &self.0;
+        let mut result = GameState::default();
+
+        let occupancy = u64::from_le_bytes(bytes[..Self::PIECES_OFFSET].try_into().unwrap());
+        if occupancy.count_ones() > 32 {
+            return None;
+        }
+        let mut kings = [None, None];
+        for (count, index) in (0..64).filter(|index| occupancy & (1 << index) != 0).enumerate() {
+            let nibble = (bytes[Self::PIECES_OFFSET + count / 2] >> (4 * (count % 2))) & 0b1111;
+            let color = Color::try_from(nibble >> 3).ok()?;
+            let piece = PieceType::try_from(nibble & 0b111).ok()?;
+            let coord = Coord::from(index as u8);
+            if piece == PieceType::Pawn && (coord.rank == 0 || coord.rank == 7) {
+                return None;
+            }
+
+            if piece == PieceType::King {
+                let king = &mut kings[color as usize];
+                if king.is_some() {
+                    return None;
+                }
+                *king = Some(coord);
+            } else if result.get_player(color).pieces.len() >= 16 {
+                return None;
+            }
+            result.push_piece(piece, color, coord);
+        }
+        if kings.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let flags = bytes[Self::FLAGS_OFFSET];
+        result.current = if flags & 1 == 0 { Color::White } else { Color::Black };
+        if result.is_king_checked(!result.current) {
+            return None;
+        }
+        result.set_castling_rights([
+            flags & 0b0010 != 0,
+            flags & 0b0100 != 0,
+            flags & 0b1000 != 0,
+            flags & 0b10000 != 0,
+        ]);
+
+        match bytes[Self::EN_PASSANT_OFFSET] {
+            0 => (),
+            file @ 1..=8 => {
+                let file = file as usize - 1;
+                // The pawn that made the double move sits one rank further than the skipped tile
+                let (rank, pawn_rank) = match result.current {
+                    Color::White => (2, 3),
+                    Color::Black => (5, 4),
+                };
+                if result.piece_at(pawn_rank * 8 + file) != Some((PieceType::Pawn, !result.current)) {
+                    return None;
+                }
+                result.board[rank * 8 + file].set_en_passant(true);
+                result.en_passant = Some(pawn_rank * 8 + file);
+            }
+            _ => return None,
+        }
+
+        result.halfmove_count = u16::from_le_bytes([bytes[Self::HALFMOVE_OFFSET], bytes[Self::HALFMOVE_OFFSET + 1]]) as u32;
+        result.fullmove_count = u16::from_le_bytes([bytes[Self::FULLMOVE_OFFSET], bytes[Self::FULLMOVE_OFFSET + 1]]) as u32;
+        result.zobrist = result.compute_hash();
+        // The bytes may come from anywhere, whatever the checks above miss leaves the position inconsistent
+        result.validate().ok()?;
+        Some(result)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.0
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self(bytes.try_into().ok()?))
+    }
+}
+impl SerBin for PackedPosition {
+    fn ser_bin(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.0);
+    }
+}
+impl DeBin for PackedPosition {
+    fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        let end = *offset + Self::SIZE;
+        let result = bytes.get(*offset..end)
+            .and_then(Self::from_bytes)
+            .ok_or(DeBinErr { o: *offset, l: Self::SIZE, s: bytes.len() })?;
+        *offset = end;
+        Ok(result)
+    }
+}
+
+
+impl GameState {
+    pub fn to_bytes(&self) -> [u8; PackedPosition::SIZE] {
+        PackedPosition::pack(self).to_bytes()
+    }
+    // The piece moves are not generated, call generate_moves when they are needed
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        PackedPosition::from_bytes(bytes)?.unpack()
+    }
+}
+impl SerBin for GameState {
+    fn ser_bin(&self, output: &mut Vec<u8>) {
+        PackedPosition::pack(self).ser_bin(output);
+    }
+}
+impl DeBin for GameState {
+    fn de_bin(offset: &mut usize, bytes: &[u8]) -> Result<Self, DeBinErr> {
+        let start = *offset;
+        PackedPosition::de_bin(offset, bytes)?
+            .unpack()
+            .ok_or(DeBinErr { o: start, l: PackedPosition::SIZE, s: bytes.len() })
+    }
+}
@@ -0,0 +1,140 @@
+use crate::*;
+
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+
+
+// Counts the leaf nodes of the legal move tree, https://www.chessprogramming.org/Perft
+pub fn perft(state: &mut GameState, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = pseudo_legal_moves(state);
+    // The moves of the last ply are only checked for legality, not searched
+    if depth == 1 {
+        return moves.into_iter().filter(|&movement| is_legal(state, movement)).count() as u64;
+    }
+    moves.into_iter()
+        .map(|movement| play_legal(state, movement, |state| perft(state, depth - 1)).unwrap_or(0))
+        .sum()
+}
+
+// The moves of the current player that may leave its king in check, generated on the state instead of a copy
+fn pseudo_legal_moves(state: &mut GameState) -> Vec<Move> {
+    state.generate_moves(state.current);
+    state.get_player(state.current).pieces.iter()
+        .flat_map(|(_, _, moves)| moves.iter().copied())
+        .collect()
+}
+
+// Plays the move and runs the search after it, or returns None if the move leaves the king of the mover in check
+fn play_legal<T>(state: &mut GameState, movement: Move, search: impl FnOnce(&mut GameState) -> T) -> Option<T> {
+    let color = state.current;
+    let undo = state.make_move(movement);
+    let result = (!state.is_king_checked(color)).then(|| search(state));
+    state.unmake_move(movement, &undo);
+    result
+}
+
+fn is_legal(state: &mut GameState, movement: Move) -> bool {
+    play_legal(state, movement, |_| ()).is_some()
+}
+
+// The perft of every root move, depth 0 has no root moves
+pub fn divide(state: &mut GameState, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    pseudo_legal_moves(state).into_iter()
+        .filter_map(|movement| play_legal(state, movement, |state| (movement, perft(state, depth - 1))))
+        .collect()
+}
+
+
+// A lockless transposition table for perft, shared between threads. Every entry keeps the hash xored with the data
+// next to the data, a torn write then fails the key check instead of returning the count of another position.
+pub struct PerftTable {
+    entries: Vec<[AtomicU64; 2]>,
+    probes: AtomicU64,
+    hits: AtomicU64,
+}
+impl PerftTable {
+    // The size is rounded down to a power of two entries, with at least one entry
+    pub fn new(megabytes: usize) -> Self {
+        let count = (megabytes * 1024 * 1024 / std::mem::size_of::<[AtomicU64; 2]>()).max(1);
+        let count = 1 << count.ilog2();
+        Self {
+            entries: (0..count).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    pub fn probes(&self) -> u64 {
+        self.probes.load(Ordering::Relaxed)
+    }
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    // The data holds the depth in the low byte and the node count above it
+    fn probe(&self, hash: u64, depth: usize) -> Option<u64> {
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        let [key, data] = &self.entries[hash as usize & (self.entries.len() - 1)];
+        let data = data.load(Ordering::Relaxed);
+        if key.load(Ordering::Relaxed) ^ data != hash || data & 0xff != depth as u64 {
+            return None;
+        }
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(data >> 8)
+    }
+    fn store(&self, hash: u64, depth: usize, nodes: u64) {
+        let [key, entry] = &self.entries[hash as usize & (self.entries.len() - 1)];
+        let data = nodes << 8 | depth as u64;
+        key.store(hash ^ data, Ordering::Relaxed);
+        entry.store(data, Ordering::Relaxed);
+    }
+}
+
+// Perft that looks up and stores the counts of every inner node, the leaves are still counted in bulk
+pub fn perft_hashed(state: &mut GameState, depth: usize, table: &PerftTable) -> u64 {
+    if depth <= 1 {
+        return perft(state, depth);
+    }
+    let hash = state.hash();
+    if let Some(nodes) = table.probe(hash, depth) {
+        return nodes;
+    }
+    let nodes = pseudo_legal_moves(state).into_iter()
+        .map(|movement| play_legal(state, movement, |state| perft_hashed(state, depth - 1, table)).unwrap_or(0))
+        .sum();
+    table.store(hash, depth, nodes);
+    nodes
+}
+
+// Divide with the root moves searched in parallel, every thread plays on its own copy of the state
+pub fn divide_parallel(state: &GameState, depth: usize, table: Option<&PerftTable>) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let mut root = state.clone();
+    pseudo_legal_moves(&mut root).into_par_iter()
+        .filter_map(|movement| {
+            let mut state = state.clone();
+            play_legal(&mut state, movement, |state| (movement, match table {
+                Some(table) => perft_hashed(state, depth - 1, table),
+                None => perft(state, depth - 1),
+            }))
+        })
+        .collect()
+}
+
+// Counts the same nodes as perft, with the root moves searched in parallel
+pub fn perft_parallel(state: &GameState, depth: usize, table: Option<&PerftTable>) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    divide_parallel(state, depth, table).into_iter().map(|(_, nodes)| nodes).sum()
+}
@@ -0,0 +1,618 @@
+use crate::*;
+
+use std::iter::{Enumerate, Peekable};
+use std::str::{Chars, Lines};
+
+
+// https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// Export format lines have to be shorter than 80 characters
+const LINE_LENGTH: usize = 79;
+
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PgnError {
+    // Index of the game in the input, starting at 0
+    pub game: usize,
+    // Line in the input the error was found on, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "game {}, line {}: {}", self.game + 1, self.line, self.message)
+    }
+}
+impl std::error::Error for PgnError {}
+
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub movement: Move,
+    // Numeric annotation glyphs, suffixes like "!?" are stored as their glyph
+    pub nags: Vec<u8>,
+    // Comments in front of the move, only used at the start of a line
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    // Alternatives to this move, each starting from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+impl PgnMove {
+    pub fn new(movement: Move) -> Self {
+        Self {
+            movement,
+            ..Default::default()
+        }
+    }
+}
+
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct PgnGame {
+    // Tag pairs in the order they were read
+    pub tags: Vec<(String, String)>,
+    // Comments of a game without any moves
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // The position the game starts from, taken from the FEN tag if there is one
+    pub fn start_position(&self) -> Option<GameState> {
+        let mut state = GameState::from_fen(self.tag("FEN").unwrap_or(GameState::FEN_CLASSIC))?;
+        if state.fullmove_count == 0 {
+            state.fullmove_count = 1;
+        }
+        state.generate_moves(state.current);
+        Some(state)
+    }
+
+    // Replays the mainline, returning the start position and the position after every move
+    pub fn replay(&self) -> Option<Vec<GameState>> {
+        let mut state = self.start_position()?;
+        let mut result = Vec::with_capacity(self.moves.len() + 1);
+        result.push(state.clone());
+        for movement in &self.moves {
+            if !state.play_move(movement.movement) {
+                return None;
+            }
+            result.push(state.clone());
+        }
+        Some(result)
+    }
+    pub fn final_position(&self) -> Option<GameState> {
+        self.replay()?.pop()
+    }
+
+
+    // Writes the game in export format, the moves are expected to be legal
+    pub fn to_pgn(&self) -> String {
+        let mut result = String::new();
+
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                _ => self.tag(name).unwrap_or(default),
+            };
+            write_tag(&mut result, name, value);
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.iter().any(|(roster, _)| roster == name) {
+                write_tag(&mut result, name, value);
+            }
+        }
+        result.push('\n');
+
+        let mut tokens = Vec::new();
+        for comment in &self.comments {
+            push_comment(&mut tokens, comment);
+        }
+        if let Some(state) = self.start_position() {
+            write_line(&mut tokens, &state, &self.moves);
+        }
+        tokens.push(self.result.as_str().to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            let attach = line.ends_with('(') || token == ")";
+            if !line.is_empty() && !attach && line.len() + 1 + token.len() > LINE_LENGTH {
+                result.push_str(&line);
+                result.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() && !attach {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        result.push_str(&line);
+        result.push('\n');
+        result
+    }
+}
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_pgn())
+    }
+}
+
+
+// Writes several games separated by an empty line
+pub fn write_games(games: &[PgnGame]) -> String {
+    games.iter()
+        .map(|game| game.to_pgn())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn write_tag(buffer: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    buffer.push_str(&format!("[{} \"{}\"]\n", name, value));
+}
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    // PGN has no escape for a closing brace inside a comment, so it is dropped
+    let comment = comment.replace('}', " ");
+    // Split into words so long comments can be wrapped
+    let words: Vec<&str> = comment.split_whitespace().collect();
+    match words.len() {
+        0 => tokens.push("{}".to_string()),
+        length => {
+            for (index, word) in words.iter().enumerate() {
+                let mut token = word.to_string();
+                if index == 0 {
+                    token.insert(0, '{');
+                }
+                if index == length - 1 {
+                    token.push('}');
+                }
+                tokens.push(token);
+            }
+        }
+    }
+}
+fn write_line(tokens: &mut Vec<String>, start: &GameState, moves: &[PgnMove]) {
+    let mut state = start.clone();
+    // Black moves need their number after anything that interrupts the movetext
+    let mut force_number = true;
+
+    for movement in moves {
+        for comment in &movement.comments_before {
+            push_comment(tokens, comment);
+            force_number = true;
+        }
+
+        // The move number is kept on the same line as its move
+        let san = state.move_to_san(movement.movement);
+        tokens.push(match state.current {
+            Color::White => format!("{}. {}", state.fullmove_count, san),
+            Color::Black if force_number => format!("{}... {}", state.fullmove_count, san),
+            Color::Black => san,
+        });
+        for nag in &movement.nags {
+            tokens.push(format!("${}", nag));
+        }
+        for comment in &movement.comments {
+            push_comment(tokens, comment);
+        }
+        force_number = !movement.comments.is_empty();
+
+        for variation in &movement.variations {
+            tokens.push("(".to_string());
+            write_line(tokens, &state, variation);
+            tokens.push(")".to_string());
+            force_number = true;
+        }
+
+        state.play_move(movement.movement);
+    }
+}
+
+
+
+
+// Reads games one at a time, so a bad game only costs that game
+pub struct PgnReader<'a> {
+    lines: Peekable<Enumerate<Lines<'a>>>,
+    game: usize,
+}
+impl<'a> PgnReader<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            lines: input.lines().enumerate().peekable(),
+            game: 0,
+        }
+    }
+}
+impl<'a> Iterator for PgnReader<'a> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Collect the lines of the next game.
+        // A game ends where a tag line follows movetext, or follows an empty line after the tags.
+        let mut chunk = String::new();
+        let mut first_line = None;
+        let mut in_comment = false;
+        let mut seen_movetext = false;
+        let mut seen_blank = false;
+
+        while let Some(&(index, line)) = self.lines.peek() {
+            let trimmed = line.trim();
+            let is_tag = !in_comment && trimmed.starts_with('[');
+            if is_tag && (seen_movetext || seen_blank) {
+                break;
+            }
+            self.lines.next();
+
+            if !in_comment && trimmed.starts_with('%') {
+                // Escape mechanism, the line is ignored
+                continue;
+            }
+            if trimmed.is_empty() {
+                seen_blank = first_line.is_some();
+            } else if !is_tag {
+                seen_movetext = true;
+                in_comment = scan_comment_state(line, in_comment);
+            }
+
+            if first_line.is_none() {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                first_line = Some(index + 1);
+            }
+            chunk.push_str(line);
+            chunk.push('\n');
+        }
+
+        let first_line = first_line?;
+        let game = self.game;
+        self.game += 1;
+        Some(parse_game(&chunk, first_line).map_err(|(line, message)| PgnError {
+            game,
+            line,
+            message,
+        }))
+    }
+}
+
+// Reads every game in the input, each game succeeds or fails on its own
+pub fn parse(input: &str) -> PgnReader<'_> {
+    PgnReader::new(input)
+}
+
+// Returns whether a brace comment is still open at the end of the line
+fn scan_comment_state(line: &str, mut in_comment: bool) -> bool {
+    for character in line.chars() {
+        match character {
+            '}' if in_comment => in_comment = false,
+            '{' if !in_comment => in_comment = true,
+            ';' if !in_comment => break,
+            _ => (),
+        }
+    }
+    in_comment
+}
+
+
+
+
+type ParseError = (usize, String);
+// Moves, comments without a move to attach to, and the game result if it was reached
+type ParsedLine = (Vec<PgnMove>, Vec<String>, Option<GameResult>);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    MoveNumber,
+    Result(GameResult),
+    Symbol(String),
+}
+
+struct Scanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+impl<'a> Scanner<'a> {
+    fn next_char(&mut self) -> Option<char> {
+        let character = self.chars.next()?;
+        if character == '\n' {
+            self.line += 1;
+        }
+        Some(character)
+    }
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|character| character.is_whitespace()) {
+            self.next_char();
+        }
+    }
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        Err((self.line, message))
+    }
+
+    fn read_tag(&mut self) -> Result<(String, String), ParseError> {
+        // Opening bracket
+        self.next_char();
+        self.skip_whitespace();
+
+        let mut name = String::new();
+        while let Some(&character) = self.chars.peek() {
+            if !(character.is_ascii_alphanumeric() || character == '_') {
+                break;
+            }
+            name.push(character);
+            self.next_char();
+        }
+        if name.is_empty() {
+            return self.error("missing tag name".to_string());
+        }
+
+        self.skip_whitespace();
+        if self.next_char() != Some('"') {
+            return self.error(format!("missing value for tag '{}'", name));
+        }
+        let mut value = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => break,
+                Some('\\') => match self.next_char() {
+                    Some(character) => value.push(character),
+                    None => return self.error(format!("unterminated value for tag '{}'", name)),
+                },
+                Some('\n') | None => return self.error(format!("unterminated value for tag '{}'", name)),
+                Some(character) => value.push(character),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.next_char() != Some(']') {
+            return self.error(format!("missing ']' after tag '{}'", name));
+        }
+        Ok((name, value))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        loop {
+            self.skip_whitespace();
+            let character = match self.chars.peek() {
+                Some(&character) => character,
+                None => return Ok(None),
+            };
+
+            let token = match character {
+                '{' => {
+                    self.next_char();
+                    let mut comment = String::new();
+                    loop {
+                        match self.next_char() {
+                            Some('}') => break,
+                            Some(character) => comment.push(character),
+                            None => return self.error("unterminated comment".to_string()),
+                        }
+                    }
+                    Token::Comment(comment.trim().to_string())
+                }
+                ';' => {
+                    let mut comment = String::new();
+                    self.next_char();
+                    while let Some(&character) = self.chars.peek() {
+                        if character == '\n' {
+                            break;
+                        }
+                        comment.push(character);
+                        self.next_char();
+                    }
+                    Token::Comment(comment.trim().to_string())
+                }
+                '(' => {
+                    self.next_char();
+                    Token::VariationStart
+                }
+                ')' => {
+                    self.next_char();
+                    Token::VariationEnd
+                }
+                '*' => {
+                    self.next_char();
+                    Token::Result(GameResult::Ongoing)
+                }
+                '.' => {
+                    // Stray periods, as in "1. ... e5"
+                    self.next_char();
+                    continue;
+                }
+                '$' => {
+                    self.next_char();
+                    let mut digits = String::new();
+                    while let Some(&character) = self.chars.peek() {
+                        if !character.is_ascii_digit() {
+                            break;
+                        }
+                        digits.push(character);
+                        self.next_char();
+                    }
+                    match digits.parse() {
+                        Ok(nag) => Token::Nag(nag),
+                        Err(_) => return self.error(format!("invalid annotation glyph '${}'", digits)),
+                    }
+                }
+                '!' | '?' => {
+                    let mut suffix = String::new();
+                    while let Some(&character) = self.chars.peek() {
+                        if character != '!' && character != '?' {
+                            break;
+                        }
+                        suffix.push(character);
+                        self.next_char();
+                    }
+                    match suffix.as_str() {
+                        "!" => Token::Nag(1),
+                        "?" => Token::Nag(2),
+                        "!!" => Token::Nag(3),
+                        "??" => Token::Nag(4),
+                        "!?" => Token::Nag(5),
+                        "?!" => Token::Nag(6),
+                        _ => return self.error(format!("invalid move suffix '{}'", suffix)),
+                    }
+                }
+                character if character.is_ascii_alphanumeric() => {
+                    let mut symbol = String::new();
+                    while let Some(&character) = self.chars.peek() {
+                        if !(character.is_ascii_alphanumeric() || "_+#=:-/".contains(character)) {
+                            break;
+                        }
+                        symbol.push(character);
+                        self.next_char();
+                    }
+
+                    if let Some(result) = GameResult::parse(&symbol) {
+                        Token::Result(result)
+                    } else if symbol.chars().all(|character| character.is_ascii_digit()) {
+                        // Move number, the periods are skipped with the stray ones
+                        Token::MoveNumber
+                    } else {
+                        Token::Symbol(symbol)
+                    }
+                }
+                character => {
+                    return self.error(format!("unexpected character '{}'", character));
+                }
+            };
+            return Ok(Some(token));
+        }
+    }
+}
+
+
+fn parse_game(text: &str, first_line: usize) -> Result<PgnGame, ParseError> {
+    let mut scanner = Scanner {
+        chars: text.chars().peekable(),
+        line: first_line,
+    };
+    let mut game = PgnGame::default();
+
+    // Tag pair section
+    loop {
+        scanner.skip_whitespace();
+        if scanner.chars.peek() != Some(&'[') {
+            break;
+        }
+        let (name, value) = scanner.read_tag()?;
+        game.tags.push((name, value));
+    }
+    if let Some(result) = game.tag("Result").and_then(GameResult::parse) {
+        game.result = result;
+    }
+
+    let start = match game.start_position() {
+        Some(start) => start,
+        None => return scanner.error("invalid FEN tag".to_string()),
+    };
+
+    // Movetext section
+    let (moves, comments, result) = parse_line(&mut scanner, &start, 0)?;
+    game.moves = moves;
+    game.comments = comments;
+    if let Some(result) = result {
+        game.result = result;
+    }
+
+    // Only comments may follow the game termination marker
+    while let Some(token) = scanner.next_token()? {
+        match token {
+            Token::Comment(comment) => match game.moves.last_mut() {
+                Some(last) => last.comments.push(comment),
+                None => game.comments.push(comment),
+            },
+            _ => return scanner.error("movetext after the game result".to_string()),
+        }
+    }
+    Ok(game)
+}
+
+// Parses moves until the end of the variation or game
+fn parse_line(scanner: &mut Scanner, start: &GameState, depth: usize) -> Result<ParsedLine, ParseError> {
+    let mut state = start.clone();
+    let mut previous = start.clone();
+    let mut moves: Vec<PgnMove> = Vec::new();
+    let mut pending_comments = Vec::new();
+
+    loop {
+        let token = match scanner.next_token()? {
+            Some(token) => token,
+            None if depth > 0 => return scanner.error("unterminated variation".to_string()),
+            None => return Ok((moves, pending_comments, None)),
+        };
+
+        match token {
+            Token::Comment(comment) => match moves.last_mut() {
+                Some(last) => last.comments.push(comment),
+                None => pending_comments.push(comment),
+            },
+            Token::Nag(nag) => match moves.last_mut() {
+                Some(last) => last.nags.push(nag),
+                None => return scanner.error("annotation before any move".to_string()),
+            },
+            Token::VariationStart => {
+                if moves.is_empty() {
+                    return scanner.error("variation before any move".to_string());
+                }
+                let (variation, _, _) = parse_line(scanner, &previous, depth + 1)?;
+                if variation.is_empty() {
+                    return scanner.error("empty variation".to_string());
+                }
+                moves.last_mut().unwrap().variations.push(variation);
+            }
+            Token::VariationEnd => {
+                if depth == 0 {
+                    return scanner.error("unbalanced ')'".to_string());
+                }
+                return Ok((moves, pending_comments, None));
+            }
+            Token::Result(result) => {
+                if depth > 0 {
+                    return scanner.error("game result inside a variation".to_string());
+                }
+                return Ok((moves, pending_comments, Some(result)));
+            }
+            Token::MoveNumber => (),
+            Token::Symbol(san) => {
+                let movement = match state.move_from_san(&san) {
+                    Some(movement) => movement,
+                    None => return scanner.error(format!("illegal or ambiguous move '{}' in '{}'", san, state.to_fen())),
+                };
+                previous = state.clone();
+                state.play_move(movement);
+
+                moves.push(PgnMove {
+                    movement,
+                    comments_before: std::mem::take(&mut pending_comments),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::*;
+
+
+// Standard algebraic notation, as used by PGN and EPD.
+// Both directions need the position the move is played from,
+// since SAN only names the target square and whatever is needed to make the move unique.
+impl GameState {
+    pub fn move_to_san(&self, movement: Move) -> String {
+        let mut result = String::with_capacity(8);
+        let from = movement.get_from() as usize;
+        let to = movement.get_target() as usize;
+        let (piece, _) = self.piece_at(from).unwrap_or((PieceType::Pawn, self.current));
+
+        match movement.get_type() {
+            MoveType::KingCastle => result.push_str("O-O"),
+            MoveType::QueenCastle => result.push_str("O-O-O"),
+            _ => {
+                if piece == PieceType::Pawn {
+                    if movement.is_capture() {
+                        result.push(file_symbol(Coord::from(from as u8).file));
+                    }
+                } else {
+                    result.push(piece_symbol(piece));
+                    result.push_str(&self.san_disambiguation(movement, piece));
+                }
+
+                if movement.is_capture() {
+                    result.push('x');
+                }
+                result.push_str(&Coord::from(to as u8).to_string());
+
+                if let Some(promotion) = promotion_type(movement.get_type()) {
+                    result.push('=');
+                    result.push(piece_symbol(promotion));
+                }
+            }
+        }
+
+        let mut state = self.clone();
+        state.play_move_unchecked(movement);
+        state.current = !state.current;
+        if state.is_king_checked(state.current) {
+            if state.legal_moves().is_empty() {
+                result.push('#');
+            } else {
+                result.push('+');
+            }
+        }
+        result
+    }
+
+    // Resolves a SAN move against the legal moves of the current player.
+    // Check, mate and annotation suffixes are ignored, "0-0" and a missing '=' before the promotion are accepted.
+    pub fn move_from_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        // Legality is only checked for the moves matching the notation, since that is the expensive part
+        let moves = self.pseudo_legal_moves();
+
+        let castle = match san {
+            "O-O" | "0-0" => Some(MoveType::KingCastle),
+            "O-O-O" | "0-0-0" => Some(MoveType::QueenCastle),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return moves.into_iter().find(|&movement| {
+                movement.get_type() == castle && !self.is_move_self_check(movement)
+            });
+        }
+
+        let mut chars: ArrayVec<char, 8> = ArrayVec::new();
+        for character in san.chars() {
+            chars.try_push(character).ok()?;
+        }
+
+        // Piece
+        let piece = match chars.first()? {
+            'K' => PieceType::King,
+            'Q' => PieceType::Queen,
+            'R' => PieceType::Rook,
+            'B' => PieceType::Bishop,
+            'N' => PieceType::Knight,
+            _ => PieceType::Pawn,
+        };
+        let mut rest = &chars[if piece == PieceType::Pawn { 0 } else { 1 }..];
+
+        // Promotion
+        let mut promotion = None;
+        if let Some(&last) = rest.last() {
+            if piece == PieceType::Pawn && last.is_ascii_uppercase() {
+                promotion = Some(match last {
+                    'Q' => PieceType::Queen,
+                    'R' => PieceType::Rook,
+                    'B' => PieceType::Bishop,
+                    'N' => PieceType::Knight,
+                    _ => return None,
+                });
+                rest = &rest[..rest.len() - 1];
+                if rest.last() == Some(&'=') {
+                    rest = &rest[..rest.len() - 1];
+                }
+            }
+        }
+
+        // Target square
+        if rest.len() < 2 {
+            return None;
+        }
+        let target: String = rest[rest.len() - 2..].iter().collect();
+        let target = Coord::parse(&target)?.index()? as u8;
+        rest = &rest[..rest.len() - 2];
+        if rest.last() == Some(&'x') {
+            rest = &rest[..rest.len() - 1];
+        }
+
+        // Disambiguation, a file, a rank or both
+        let mut file = None;
+        let mut rank = None;
+        for &character in rest {
+            match character {
+                'a'..='h' if file.is_none() => file = Some(character as u8 - b'a'),
+                '1'..='8' if rank.is_none() => rank = Some(b'8' - character as u8),
+                _ => return None,
+            }
+        }
+
+        let mut candidates = moves.into_iter().filter(|&movement| {
+            let from = Coord::from(movement.get_from());
+            movement.get_target() == target &&
+            !matches!(movement.get_type(), MoveType::KingCastle | MoveType::QueenCastle) &&
+            self.piece_at(movement.get_from() as usize).map(|(piece, _)| piece) == Some(piece) &&
+            promotion_type(movement.get_type()) == promotion &&
+            file.is_none_or(|file| from.file == file) &&
+            rank.is_none_or(|rank| from.rank == rank) &&
+            !self.is_move_self_check(movement)
+        });
+
+        let result = candidates.next()?;
+        if candidates.next().is_some() {
+            // Ambiguous
+            return None;
+        }
+        Some(result)
+    }
+
+    fn san_disambiguation(&self, movement: Move, piece: PieceType) -> String {
+        let from = Coord::from(movement.get_from());
+        let others: Vec<Coord> = self.legal_moves().into_iter()
+            .filter(|other| {
+                other.get_target() == movement.get_target() &&
+                other.get_from() != movement.get_from() &&
+                self.piece_at(other.get_from() as usize).map(|(piece, _)| piece) == Some(piece)
+            })
+            .map(|other| Coord::from(other.get_from()))
+            .collect();
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other.file != from.file) {
+            file_symbol(from.file).to_string()
+        } else if others.iter().all(|other| other.rank != from.rank) {
+            rank_symbol(from.rank).to_string()
+        } else {
+            from.to_string()
+        }
+    }
+}
+
+
+// Coordinate notation as used by UCI and perft divide output, like e2e4 or e7e8q.
+// Castling is written as the king move, which is how the move stores it.
+pub fn move_to_uci(movement: Move) -> String {
+    let mut result = String::with_capacity(5);
+    result.push_str(&Coord::from(movement.get_from()).to_string());
+    result.push_str(&Coord::from(movement.get_target()).to_string());
+    if let Some(promotion) = promotion_type(movement.get_type()) {
+        result.push(piece_symbol(promotion).to_ascii_lowercase());
+    }
+    result
+}
+
+pub fn promotion_type(move_type: MoveType) -> Option<PieceType> {
+    match move_type {
+        MoveType::PromotionQueen | MoveType::CapturePromotionQueen => Some(PieceType::Queen),
+        MoveType::PromotionRook | MoveType::CapturePromotionRook => Some(PieceType::Rook),
+        MoveType::PromotionBishop | MoveType::CapturePromotionBishop => Some(PieceType::Bishop),
+        MoveType::PromotionKnight | MoveType::CapturePromotionKnight => Some(PieceType::Knight),
+        _ => None,
+    }
+}
+
+pub fn piece_symbol(piece: PieceType) -> char {
+    match piece {
+        PieceType::None => ' ',
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+fn file_symbol(file: u8) -> char {
+    (b'a' + file) as char
+}
+fn rank_symbol(rank: u8) -> char {
+    (b'8' - rank) as char
+}
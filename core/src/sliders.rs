@@ -0,0 +1,347 @@
+use crate::*;
+use crate::bitboard::PlayerBitboard;
+
+use bitintr::Pext as _;
+use nanorand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+// Rook and bishop attacks of a single slider, including the first blocker in every direction.
+// The occupancy may contain the slider itself, it is ignored.
+pub trait SlidingAttacks: Send + Sync {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard;
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard;
+
+    fn queen_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.rook_attacks(square, occupancy) | self.bishop_attacks(square, occupancy)
+    }
+}
+
+
+// Runtime selection of a backend, the default is PEXT when the target has BMI2 and fancy magics otherwise.
+// Move generation and attack detection in core use the selected backend.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SlidingBackend {
+    KoggeStone,
+    PlainMagic,
+    FancyMagic,
+    Pext,
+    HyperbolaQuintessence,
+}
+impl SlidingBackend {
+    pub const ALL: [SlidingBackend; 5] = [
+        SlidingBackend::KoggeStone,
+        SlidingBackend::PlainMagic,
+        SlidingBackend::FancyMagic,
+        SlidingBackend::Pext,
+        SlidingBackend::HyperbolaQuintessence,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KoggeStone => "kogge-stone",
+            Self::PlainMagic => "plain magic",
+            Self::FancyMagic => "fancy magic",
+            Self::Pext => "pext",
+            Self::HyperbolaQuintessence => "hyperbola quintessence",
+        }
+    }
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.name() == name)
+    }
+
+    // Switches the backend for every thread, positions generated before keep their moves
+    pub fn select(self) {
+        SELECTED.store(self as usize, Ordering::Relaxed);
+    }
+    pub fn selected() -> Self {
+        Self::ALL.get(SELECTED.load(Ordering::Relaxed)).copied().unwrap_or_default()
+    }
+
+    // The shared instance of the backend, its tables are built on first use
+    pub fn get(self) -> &'static dyn SlidingAttacks {
+        match self {
+            Self::KoggeStone => &KoggeStone,
+            Self::PlainMagic => &*PLAIN_MAGIC,
+            Self::FancyMagic => &FancyMagic,
+            Self::Pext => &*PEXT,
+            Self::HyperbolaQuintessence => &*HYPERBOLA_QUINTESSENCE,
+        }
+    }
+}
+impl Default for SlidingBackend {
+    fn default() -> Self {
+        if cfg!(target_feature = "bmi2") {
+            Self::Pext
+        } else {
+            Self::FancyMagic
+        }
+    }
+}
+
+// Index into SlidingBackend::ALL, the default backend until one is selected
+static SELECTED: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+// The backend core generates slider attacks with
+pub fn sliding_attacks() -> &'static dyn SlidingAttacks {
+    SlidingBackend::selected().get()
+}
+
+// Random occupancies with every density, the same on every call, to check and time the backends on
+pub fn sample_occupancies(count: usize) -> Vec<Bitboard> {
+    let mut rng = nanorand::WyRand::new_seed(0);
+    (0..count)
+        .map(|index| {
+            // And-ing a few random boards together gives sparser boards, as seen later in a game
+            let mut occupancy: u64 = rng.generate();
+            for _ in 0..index % 4 {
+                occupancy &= rng.generate::<u64>();
+            }
+            Bitboard(occupancy)
+        })
+        .collect()
+}
+
+lazy_static::lazy_static!{
+    static ref PLAIN_MAGIC: PlainMagic = PlainMagic::new();
+    static ref PEXT: Pext = Pext::new();
+    static ref HYPERBOLA_QUINTESSENCE: HyperbolaQuintessence = HyperbolaQuintessence::new();
+}
+
+
+// Occluded fills over the whole board, no tables
+pub struct KoggeStone;
+impl SlidingAttacks for KoggeStone {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let slider = Bitboard::from_square(square);
+        PlayerBitboard::generate_rook_attacks(slider, occupancy) & !slider
+    }
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let slider = Bitboard::from_square(square);
+        PlayerBitboard::generate_bishop_attacks(slider, occupancy) & !slider
+    }
+}
+
+
+// Magic multiplication into a table of the same size for every square, 4096 entries for rooks and 512 for bishops.
+// The generated magics index by the top bits of the product, so they stay collision free with the wider shift.
+pub struct PlainMagic {
+    rooks: MagicTable,
+    bishops: MagicTable,
+}
+impl PlainMagic {
+    pub fn new() -> Self {
+        Self {
+            rooks: MagicTable::new(Slider::Rook, |_| 12),
+            bishops: MagicTable::new(Slider::Bishop, |_| 9),
+        }
+    }
+}
+impl Default for PlainMagic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SlidingAttacks for PlainMagic {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.rooks.attacks(square, occupancy)
+    }
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.bishops.attacks(square, occupancy)
+    }
+}
+
+
+// Magic multiplication into a table per square sized by its number of relevant occupancy bits.
+// Uses the searched magics of the wizard database, included as static tables so there is nothing to build.
+pub struct FancyMagic;
+impl SlidingAttacks for FancyMagic {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        Bitboard(generated::sliding_attacks(square.index(), occupancy.0))
+    }
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        Bitboard(generated::sliding_attacks(64 + square.index(), occupancy.0))
+    }
+}
+
+
+// The relevant occupancy bits are extracted with PEXT, which is a single instruction with BMI2 and emulated without it
+pub struct Pext {
+    rooks: PextTable,
+    bishops: PextTable,
+}
+impl Pext {
+    pub fn new() -> Self {
+        Self {
+            rooks: PextTable::new(Slider::Rook),
+            bishops: PextTable::new(Slider::Bishop),
+        }
+    }
+}
+impl Default for Pext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SlidingAttacks for Pext {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.rooks.attacks(square, occupancy)
+    }
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.bishops.attacks(square, occupancy)
+    }
+}
+
+
+// o ^ (o - 2r) along each line through the square, in both directions by reversing the bits.
+// Only needs the four line masks per square.
+pub struct HyperbolaQuintessence {
+    // File, rank, diagonal and anti diagonal through each square, without the square itself
+    lines: Vec<[u64; 4]>,
+}
+impl HyperbolaQuintessence {
+    pub fn new() -> Self {
+        let line = |square, first, second| (tables::ray(square, first) | tables::ray(square, second)).0;
+        let lines = Square::ALL.iter()
+            .map(|&square| [
+                line(square, Direction::North, Direction::South),
+                line(square, Direction::East, Direction::West),
+                line(square, Direction::NorthEast, Direction::SouthWest),
+                line(square, Direction::NorthWest, Direction::SouthEast),
+            ])
+            .collect();
+        Self { lines }
+    }
+
+    fn line_attacks(square: Square, occupancy: u64, line: u64) -> u64 {
+        let slider = 1u64 << square.index();
+        let occupancy = occupancy & line;
+        let forward = occupancy.wrapping_sub(slider.wrapping_mul(2));
+        let reverse = occupancy.reverse_bits().wrapping_sub(slider.reverse_bits().wrapping_mul(2)).reverse_bits();
+        (forward ^ reverse) & line
+    }
+}
+impl Default for HyperbolaQuintessence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SlidingAttacks for HyperbolaQuintessence {
+    fn rook_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let [file, rank, _, _] = self.lines[square.index()];
+        Bitboard(Self::line_attacks(square, occupancy.0, file) | Self::line_attacks(square, occupancy.0, rank))
+    }
+    fn bishop_attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let [_, _, diagonal, anti_diagonal] = self.lines[square.index()];
+        Bitboard(Self::line_attacks(square, occupancy.0, diagonal) | Self::line_attacks(square, occupancy.0, anti_diagonal))
+    }
+}
+
+
+#[derive(Clone, Copy)]
+enum Slider {
+    Rook,
+    Bishop,
+}
+impl Slider {
+    // Rooks come first in the generated magics
+    fn index(self, square: usize) -> usize {
+        match self {
+            Self::Rook => square,
+            Self::Bishop => 64 + square,
+        }
+    }
+    fn occlusion_mask(self, square: usize) -> u64 {
+        generated::MAGIC_MASKS[self.index(square)]
+    }
+    // The tables are filled from the occluded fills, which need no tables themselves
+    fn attack_mask(self, square: usize, occlusion: u64) -> u64 {
+        let square = Square::ALL[square];
+        match self {
+            Self::Rook => KoggeStone.rook_attacks(square, Bitboard(occlusion)).0,
+            Self::Bishop => KoggeStone.bishop_attacks(square, Bitboard(occlusion)).0,
+        }
+    }
+    fn magic(self, square: usize) -> u64 {
+        generated::MAGIC_VALUES[self.index(square)]
+    }
+
+    // Every subset of the occlusion mask of a square, with the attacks it leaves
+    fn occlusions(self, square: usize) -> impl Iterator<Item = (u64, u64)> {
+        let mask = self.occlusion_mask(square);
+        let mut next = Some(0u64);
+        std::iter::from_fn(move || {
+            let occlusion = next?;
+            next = Some(occlusion.wrapping_sub(mask) & mask).filter(|&next| next != 0);
+            Some((occlusion, self.attack_mask(square, occlusion)))
+        })
+    }
+}
+
+// Where the attacks of a square start in a magic table, and how its occupancy is turned into a key
+struct MagicSquare {
+    offset: usize,
+    shift: usize,
+    mask: u64,
+    value: u64,
+}
+impl MagicSquare {
+    fn key(&self, occlusion: u64) -> usize {
+        self.offset + ((occlusion & self.mask).wrapping_mul(self.value) >> self.shift) as usize
+    }
+}
+
+// One contiguous table for all squares of a slider, each square starting at its own offset
+struct MagicTable {
+    squares: Vec<MagicSquare>,
+    attacks: Vec<u64>,
+}
+impl MagicTable {
+    fn new(slider: Slider, bits: impl Fn(usize) -> usize) -> Self {
+        let mut squares = Vec::with_capacity(64);
+        let mut attacks = Vec::new();
+        for square in 0..64 {
+            let magic = MagicSquare {
+                offset: attacks.len(),
+                shift: 64 - bits(square),
+                mask: slider.occlusion_mask(square),
+                value: slider.magic(square),
+            };
+            attacks.resize(attacks.len() + (1 << bits(square)), 0);
+            for (occlusion, attack) in slider.occlusions(square) {
+                attacks[magic.key(occlusion)] = attack;
+            }
+            squares.push(magic);
+        }
+        Self { squares, attacks }
+    }
+    fn attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        Bitboard(self.attacks[self.squares[square.index()].key(occupancy.0)])
+    }
+}
+
+struct PextTable {
+    // Occlusion mask and table offset of each square
+    squares: Vec<(u64, usize)>,
+    attacks: Vec<u64>,
+}
+impl PextTable {
+    fn new(slider: Slider) -> Self {
+        let mut squares = Vec::with_capacity(64);
+        let mut attacks = Vec::new();
+        for square in 0..64 {
+            let mask = slider.occlusion_mask(square);
+            let offset = attacks.len();
+            attacks.resize(offset + (1 << mask.count_ones()), 0);
+            for (occlusion, attack) in slider.occlusions(square) {
+                attacks[offset + occlusion.pext(mask) as usize] = attack;
+            }
+            squares.push((mask, offset));
+        }
+        Self { squares, attacks }
+    }
+    fn attacks(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        let (mask, offset) = self.squares[square.index()];
+        Bitboard(self.attacks[offset + occupancy.0.pext(mask) as usize])
+    }
+}
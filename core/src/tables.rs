@@ -0,0 +1,77 @@
+use crate::*;
+
+
+// Square pair and ray lookups from the generated tables
+
+// The squares strictly between two squares on a shared rank, file or diagonal, empty otherwise
+pub fn between(from: Square, to: Square) -> Bitboard {
+    Bitboard(generated::BETWEEN[from.index() * 64 + to.index()])
+}
+// The whole line through two squares from edge to edge, empty if they do not share a rank, file or diagonal
+pub fn line(from: Square, to: Square) -> Bitboard {
+    Bitboard(generated::LINES[from.index() * 64 + to.index()])
+}
+// The squares from a square to the edge of the board, not including the square itself
+pub fn ray(square: Square, direction: Direction) -> Bitboard {
+    // The order of the directions in the generated table
+    let index = match direction {
+        Direction::North => 0,
+        Direction::South => 1,
+        Direction::East => 2,
+        Direction::West => 3,
+        Direction::NorthEast => 4,
+        Direction::NorthWest => 5,
+        Direction::SouthEast => 6,
+        Direction::SouthWest => 7,
+    };
+    Bitboard(generated::RAYS[square.index() * 8 + index])
+}
+// The number of king moves between two squares
+pub fn chebyshev_distance(from: Square, to: Square) -> u8 {
+    generated::CHEBYSHEV_DISTANCES[from.index() * 64 + to.index()]
+}
+// The number of rank and file steps between two squares
+pub fn manhattan_distance(from: Square, to: Square) -> u8 {
+    generated::MANHATTAN_DISTANCES[from.index() * 64 + to.index()]
+}
+
+
+// Evaluation masks from the generated tables, "in front" is towards the side of the board the color's pawns move to
+
+fn side(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+// The squares in front of a square on its file
+pub fn forward_file(color: Color, square: Square) -> Bitboard {
+    Bitboard(generated::FORWARD_FILES[square.index()][side(color)])
+}
+// The squares in front of a square on the adjacent files, every square a pawn could attack it from later on
+pub fn pawn_attack_span(color: Color, square: Square) -> Bitboard {
+    Bitboard(generated::PAWN_ATTACK_SPANS[square.index()][side(color)])
+}
+// A pawn of the color on the square is passed if there are no enemy pawns in its span
+pub fn passed_pawn_span(color: Color, square: Square) -> Bitboard {
+    Bitboard(generated::PASSED_PAWN_SPANS[square.index()][side(color)])
+}
+// The king, its neighbours and the three squares two ranks in front of it
+pub fn king_zone(color: Color, square: Square) -> Bitboard {
+    Bitboard(generated::KING_ZONES[square.index()][side(color)])
+}
+// The files next to the file of a square, a pawn without friendly pawns on them is isolated
+pub fn adjacent_files(square: Square) -> Bitboard {
+    Bitboard(generated::ADJACENT_FILES[square.index()])
+}
+// Ranks 4 to 6 from the side of the color
+pub fn outposts(color: Color) -> Bitboard {
+    Bitboard(generated::OUTPOSTS[side(color)])
+}
+pub fn light_squares() -> Bitboard {
+    Bitboard(generated::SQUARE_COLORS[0])
+}
+pub fn dark_squares() -> Bitboard {
+    Bitboard(generated::SQUARE_COLORS[1])
+}
@@ -0,0 +1,513 @@
+use crate::*;
+use crate::pgn::{PgnGame, PgnMove};
+
+
+// Colors of the arrows and highlighted squares, as used by the [%cal] and [%csl] comment commands
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarkColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+}
+impl MarkColor {
+    pub fn parse(symbol: char) -> Option<Self> {
+        match symbol {
+            'R' => Some(Self::Red),
+            'G' => Some(Self::Green),
+            'Y' => Some(Self::Yellow),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+    pub fn symbol(self) -> char {
+        match self {
+            Self::Red => 'R',
+            Self::Green => 'G',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Arrow {
+    pub from: Coord,
+    pub to: Coord,
+    pub color: MarkColor,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Highlight {
+    pub square: Coord,
+    pub color: MarkColor,
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Node {
+    parent: Option<NodeId>,
+    // The first child continues the line, the others are its variations
+    children: Vec<NodeId>,
+    // The move leading to this node, None for the root
+    movement: Option<Move>,
+    // Number of moves from the root
+    ply: usize,
+
+    pub nags: Vec<u8>,
+    // Comments in front of the move, only used at the start of a variation
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    pub arrows: Vec<Arrow>,
+    pub highlights: Vec<Highlight>,
+}
+impl Node {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+    pub fn movement(&self) -> Option<Move> {
+        self.movement
+    }
+}
+
+
+// A game with variations for analysis.
+// Nodes are kept in an arena, deleted nodes leave a hole so the ids of the others stay valid.
+#[derive(Clone, Debug)]
+pub struct VariationTree {
+    start: GameState,
+    nodes: Vec<Option<Node>>,
+    current: NodeId,
+    position: GameState,
+    // The nodes from the root to the current node, and the undo info of their moves,
+    // so walking to another node only takes back and plays the moves in between
+    line: Vec<NodeId>,
+    history: Vec<UndoInfo>,
+
+    // Tag pairs as in PGN, in insertion order
+    pub tags: Vec<(String, String)>,
+    pub result: GameResult,
+}
+impl VariationTree {
+    pub const ROOT: NodeId = NodeId(0);
+
+    pub fn new(mut start: GameState) -> Self {
+        start.generate_moves(start.current);
+        Self {
+            position: start.clone(),
+            start,
+            nodes: vec![Some(Node::default())],
+            current: Self::ROOT,
+            line: vec![Self::ROOT],
+            history: Vec::new(),
+            tags: Vec::new(),
+            result: GameResult::Ongoing,
+        }
+    }
+
+
+    pub fn start_position(&self) -> &GameState {
+        &self.start
+    }
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0)?.as_mut()
+    }
+    // All nodes that have not been deleted, the root first
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_some())
+            .map(|(index, _)| NodeId(index))
+    }
+
+    // The nodes from the root down to the given node, both included
+    pub fn path(&self, id: NodeId) -> Option<Vec<NodeId>> {
+        let mut result = vec![id];
+        let mut node = self.node(id)?;
+        while let Some(parent) = node.parent {
+            result.push(parent);
+            node = self.node(parent)?;
+        }
+        result.reverse();
+        Some(result)
+    }
+    // The moves leading from the start position to the given node
+    pub fn moves_to(&self, id: NodeId) -> Option<Vec<Move>> {
+        let path = self.path(id)?;
+        Some(path.into_iter().filter_map(|id| self.node(id)?.movement).collect())
+    }
+    // The nodes following the first child from the given node, not including it
+    pub fn mainline(&self, id: NodeId) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut node = self.node(id);
+        while let Some(&child) = node.and_then(|node| node.children.first()) {
+            result.push(child);
+            node = self.node(child);
+        }
+        result
+    }
+    // Walks from the current position instead of replaying from the start
+    pub fn position_at(&self, id: NodeId) -> Option<GameState> {
+        let (shared, down) = self.route(id)?;
+        let mut state = self.position.clone();
+        for ply in (shared..self.line.len()).rev() {
+            state.unmake_move(self.node(self.line[ply])?.movement?, &self.history[ply - 1]);
+        }
+        for id in down {
+            state.make_move(self.node(id)?.movement?);
+        }
+        state.generate_moves(state.current);
+        Some(state)
+    }
+    // The number of nodes the line to the current node shares with the path to the given node, and the nodes after them
+    fn route(&self, id: NodeId) -> Option<(usize, Vec<NodeId>)> {
+        let mut down = Vec::new();
+        let mut id = id;
+        loop {
+            let node = self.node(id)?;
+            if self.line.get(node.ply) == Some(&id) {
+                down.reverse();
+                return Some((node.ply + 1, down));
+            }
+            down.push(id);
+            id = node.parent?;
+        }
+    }
+
+
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+    // The position at the current node
+    pub fn position(&self) -> &GameState {
+        &self.position
+    }
+    pub fn go_to(&mut self, id: NodeId) -> bool {
+        let Some((shared, down)) = self.route(id) else {
+            return false;
+        };
+        while self.line.len() > shared {
+            let id = self.line.pop().unwrap();
+            let undo = self.history.pop().unwrap();
+            self.position.unmake_move(self.nodes[id.0].as_ref().unwrap().movement.unwrap(), &undo);
+        }
+        for id in down {
+            let undo = self.position.make_move(self.nodes[id.0].as_ref().unwrap().movement.unwrap());
+            self.line.push(id);
+            self.history.push(undo);
+        }
+        self.position.generate_moves(self.position.current);
+        self.current = id;
+        true
+    }
+    pub fn go_back(&mut self) -> bool {
+        match self.node(self.current).and_then(|node| node.parent) {
+            Some(parent) => self.go_to(parent),
+            None => false,
+        }
+    }
+    // Follows the mainline from the current node
+    pub fn go_forward(&mut self) -> bool {
+        match self.node(self.current).and_then(|node| node.children.first().copied()) {
+            Some(child) => self.go_to(child),
+            None => false,
+        }
+    }
+
+
+    // Adds a legal move after the given node, the first move becomes the mainline and later ones variations.
+    // Returns the existing node if the move was already there.
+    pub fn add_move(&mut self, parent: NodeId, movement: Move) -> Option<NodeId> {
+        let node = self.node(parent)?;
+        if let Some(&existing) = node.children.iter().find(|&&child| {
+            self.node(child).and_then(|child| child.movement) == Some(movement)
+        }) {
+            return Some(existing);
+        }
+        if !self.position_at(parent)?.is_legal_move(movement) {
+            return None;
+        }
+
+        let id = NodeId(self.nodes.len());
+        let ply = self.node(parent)?.ply + 1;
+        self.nodes.push(Some(Node {
+            parent: Some(parent),
+            movement: Some(movement),
+            ply,
+            ..Default::default()
+        }));
+        self.node_mut(parent)?.children.push(id);
+        Some(id)
+    }
+    // Adds the move after the current node and walks to it
+    pub fn play(&mut self, movement: Move) -> Option<NodeId> {
+        let id = self.add_move(self.current, movement)?;
+        self.go_to(id);
+        Some(id)
+    }
+    pub fn play_san(&mut self, san: &str) -> Option<NodeId> {
+        let movement = self.position.move_from_san(san)?;
+        self.play(movement)
+    }
+
+    // Moves a variation one place up among its siblings, the first place being the mainline
+    pub fn promote_variation(&mut self, id: NodeId) -> bool {
+        let parent = match self.node(id).and_then(|node| node.parent) {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let siblings = &mut self.node_mut(parent).unwrap().children;
+        let index = siblings.iter().position(|&sibling| sibling == id).unwrap();
+        if index == 0 {
+            return false;
+        }
+        siblings.swap(index, index - 1);
+        true
+    }
+    // Makes the line through the given node the mainline of the whole tree
+    pub fn make_mainline(&mut self, id: NodeId) -> bool {
+        let path = match self.path(id) {
+            Some(path) => path,
+            None => return false,
+        };
+        for pair in path.windows(2) {
+            let siblings = &mut self.node_mut(pair[0]).unwrap().children;
+            let index = siblings.iter().position(|&sibling| sibling == pair[1]).unwrap();
+            let child = siblings.remove(index);
+            siblings.insert(0, child);
+        }
+        true
+    }
+    // Deletes a node with everything after it, the root can not be deleted.
+    // If the current node is deleted the parent becomes the current node.
+    pub fn delete(&mut self, id: NodeId) -> bool {
+        let parent = match self.node(id).and_then(|node| node.parent) {
+            Some(parent) => parent,
+            None => return false,
+        };
+        // The moves of the deleted nodes are still needed to walk back from them
+        if self.line.contains(&id) {
+            self.go_to(parent);
+        }
+
+        self.node_mut(parent).unwrap().children.retain(|&child| child != id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                stack.extend(node.children);
+            }
+        }
+        true
+    }
+
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+
+    // Comments in front of the first move are stored on the root, as are the comments of a game without moves.
+    // Arrows and highlights are read from [%cal] and [%csl] commands in the comments.
+    pub fn from_pgn(pgn: &PgnGame) -> Option<Self> {
+        let mut result = Self::new(pgn.start_position()?);
+        result.tags = pgn.tags.clone();
+        result.result = pgn.result;
+
+        let mut root_comments = pgn.comments.clone();
+        if let Some(first) = pgn.moves.first() {
+            root_comments.extend(first.comments_before.iter().cloned());
+        }
+        let root = result.node_mut(Self::ROOT)?;
+        set_comments(root, &root_comments, false);
+
+        result.add_pgn_line(Self::ROOT, &pgn.moves, true)?;
+        result.go_to(Self::ROOT);
+        Some(result)
+    }
+    // Walks along while adding, so every move is checked in a position one move away
+    fn add_pgn_line(&mut self, mut parent: NodeId, moves: &[PgnMove], is_mainline: bool) -> Option<()> {
+        for (index, movement) in moves.iter().enumerate() {
+            self.go_to(parent).then_some(())?;
+            let id = self.add_move(parent, movement.movement)?;
+            let node = self.node_mut(id)?;
+            node.nags = movement.nags.clone();
+            set_comments(node, &movement.comments, false);
+            if !(is_mainline && index == 0) {
+                set_comments(node, &movement.comments_before, true);
+            }
+
+            for variation in &movement.variations {
+                self.add_pgn_line(parent, variation, false)?;
+            }
+            parent = id;
+        }
+        Some(())
+    }
+
+    pub fn to_pgn(&self) -> PgnGame {
+        let root = self.node(Self::ROOT).unwrap();
+        let mut result = PgnGame {
+            tags: self.tags.clone(),
+            comments: write_comments(&root.comments, &root.arrows, &root.highlights),
+            moves: self.pgn_line(Self::ROOT, None),
+            result: self.result,
+        };
+        result.set_tag("Result", self.result.as_str());
+        let fen = self.start.to_fen();
+        if fen != GameState::FEN_CLASSIC {
+            result.set_tag("SetUp", "1");
+            result.set_tag("FEN", &fen);
+        }
+        result
+    }
+    // The line after a node, starting with the given child or the mainline child
+    fn pgn_line(&self, parent: NodeId, first: Option<NodeId>) -> Vec<PgnMove> {
+        let mut result = Vec::new();
+        let mut parent = parent;
+        let mut next = first.or_else(|| self.node(parent)?.children.first().copied());
+
+        while let Some(id) = next {
+            let node = self.node(id).unwrap();
+            let variations = match first {
+                // The siblings of the first move of a variation are written by the line containing it
+                Some(first) if first == id => Vec::new(),
+                _ => self.node(parent).unwrap().children.iter()
+                    .skip(1)
+                    .map(|&sibling| self.pgn_line(parent, Some(sibling)))
+                    .collect(),
+            };
+            result.push(PgnMove {
+                movement: node.movement.unwrap(),
+                nags: node.nags.clone(),
+                comments_before: node.comments_before.clone(),
+                comments: write_comments(&node.comments, &node.arrows, &node.highlights),
+                variations,
+            });
+            parent = id;
+            next = node.children.first().copied();
+        }
+        result
+    }
+}
+impl Default for VariationTree {
+    // An empty tree from the standard start position
+    fn default() -> Self {
+        Self::new(GameState::from_fen(GameState::FEN_CLASSIC).unwrap())
+    }
+}
+
+
+// Stores comments on a node with the [%cal] and [%csl] commands taken out of them
+fn set_comments(node: &mut Node, comments: &[String], before: bool) {
+    for comment in comments {
+        let text = read_marks(comment, &mut node.arrows, &mut node.highlights);
+        if text.is_empty() && comment.contains("[%") {
+            // Nothing but marks
+            continue;
+        }
+        if before {
+            node.comments_before.push(text);
+        } else {
+            node.comments.push(text);
+        }
+    }
+}
+// Parses "[%cal Ge2e4,Rd1d8]" and "[%csl Gd4]" out of a comment, returning the remaining text
+fn read_marks(comment: &str, arrows: &mut Vec<Arrow>, highlights: &mut Vec<Highlight>) -> String {
+    let mut text = String::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let command = &rest[start + 2..end];
+        let (name, arguments) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        // A command is only taken out when every one of its marks parses, otherwise it stays in the text as it is
+        let is_read = match name {
+            "cal" => match arguments.split(',').map(|argument| parse_arrow(argument.trim())).collect::<Option<Vec<Arrow>>>() {
+                Some(parsed) => {
+                    arrows.extend(parsed);
+                    true
+                }
+                None => false,
+            },
+            "csl" => match arguments.split(',').map(|argument| parse_highlight(argument.trim())).collect::<Option<Vec<Highlight>>>() {
+                Some(parsed) => {
+                    highlights.extend(parsed);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+        // Other commands, like clock times, stay in the text
+        text.push_str(&rest[..start]);
+        if !is_read {
+            text.push_str(&rest[start..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+fn parse_arrow(argument: &str) -> Option<Arrow> {
+    let color = MarkColor::parse(argument.chars().next()?)?;
+    Some(Arrow {
+        from: Coord::parse(argument.get(1..3)?)?,
+        to: Coord::parse(argument.get(3..5)?)?,
+        color,
+    }).filter(|_| argument.len() == 5)
+}
+fn parse_highlight(argument: &str) -> Option<Highlight> {
+    let color = MarkColor::parse(argument.chars().next()?)?;
+    Some(Highlight {
+        square: Coord::parse(argument.get(1..3)?)?,
+        color,
+    }).filter(|_| argument.len() == 3)
+}
+// The comments of a node with its marks put back in front of the first one
+fn write_comments(comments: &[String], arrows: &[Arrow], highlights: &[Highlight]) -> Vec<String> {
+    let mut marks = Vec::new();
+    if !highlights.is_empty() {
+        let highlights: Vec<String> = highlights.iter()
+            .map(|highlight| format!("{}{}", highlight.color.symbol(), highlight.square))
+            .collect();
+        marks.push(format!("[%csl {}]", highlights.join(",")));
+    }
+    if !arrows.is_empty() {
+        let arrows: Vec<String> = arrows.iter()
+            .map(|arrow| format!("{}{}{}", arrow.color.symbol(), arrow.from, arrow.to))
+            .collect();
+        marks.push(format!("[%cal {}]", arrows.join(",")));
+    }
+
+    let mut result = comments.to_vec();
+    if !marks.is_empty() {
+        let marks = marks.join("");
+        match result.first_mut() {
+            Some(first) if !first.is_empty() => *first = format!("{} {}", marks, first),
+            Some(first) => *first = marks,
+            None => result.push(marks),
+        }
+    }
+    result
+}
@@ -0,0 +1,168 @@
+use crate::*;
+
+
+// A GameState keeps every piece three times, on the board, in the piece arrays and in the bitboards.
+// These are the ways the copies can disagree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Inconsistency {
+    // The tile points at a piece slot that is empty, out of range or somewhere else
+    TileMismatch { coord: Coord, color: Color, index: u8 },
+    // The piece is not on the tile that points back at it
+    PieceMismatch { color: Color, index: u8 },
+    // A bitboard bit without the piece, or a piece without its bit
+    StrayBit { color: Color, piece: PieceType, coord: Coord },
+    MissingBit { color: Color, piece: PieceType, coord: Coord },
+    // Two bitboards claim the same tile
+    OverlappingBitboards(Coord),
+    // The king is not in the first slot of the piece array, or another slot holds a king
+    WrongKingSlot(Color),
+    // The en passant square is not a pawn that just double moved, or the wrong tiles carry the flag
+    StaleEnPassant(Coord),
+    // The hash kept by make and unmake is not the hash computed from scratch
+    StaleHash { recorded: u64, actual: u64 },
+}
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TileMismatch { coord, color, index } => write!(f, "tile {} points at {:?} piece {} which is not there", coord, color, index),
+            Self::PieceMismatch { color, index } => write!(f, "{:?} piece {} is not on the tile pointing at it", color, index),
+            Self::StrayBit { color, piece, coord } => write!(f, "{:?} {:?} bitboard has {} set without the piece", color, piece, coord),
+            Self::MissingBit { color, piece, coord } => write!(f, "{:?} {:?} bitboard misses the piece on {}", color, piece, coord),
+            Self::OverlappingBitboards(coord) => write!(f, "more than one bitboard has {} set", coord),
+            Self::WrongKingSlot(color) => write!(f, "{:?} king is not alone in the first piece slot", color),
+            Self::StaleEnPassant(coord) => write!(f, "en passant flag on {} does not follow the last double pawn move", coord),
+            Self::StaleHash { recorded, actual } => write!(f, "recorded hash {:016x} is not the position hash {:016x}", recorded, actual),
+        }
+    }
+}
+impl std::error::Error for Inconsistency {}
+
+
+impl GameState {
+    // Returns every way the board, the piece arrays and the bitboards disagree, and a kept hash that went stale
+    pub fn validate(&self) -> Result<(), Vec<Inconsistency>> {
+        let mut errors = Vec::new();
+        self.validate_tiles(&mut errors);
+        for color in [Color::White, Color::Black] {
+            self.validate_pieces(color, &mut errors);
+            self.validate_bitboards(color, &mut errors);
+        }
+        self.validate_overlap(&mut errors);
+        self.validate_en_passant(&mut errors);
+        self.validate_hash(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_tiles(&self, errors: &mut Vec<Inconsistency>) {
+        for (index, tile) in self.board.iter().enumerate() {
+            if tile.is_empty() {
+                continue;
+            }
+            let coord = Coord::from(index as u8);
+            let color = tile.get_color();
+            let is_valid = self.get_player(color).pieces
+                .get(tile.get_index() as usize)
+                .is_some_and(|&(piece, piece_coord, _)| piece != PieceType::None && piece_coord == coord);
+            if !is_valid {
+                errors.push(Inconsistency::TileMismatch { coord, color, index: tile.get_index() });
+            }
+        }
+    }
+    fn validate_pieces(&self, color: Color, errors: &mut Vec<Inconsistency>) {
+        let pieces = &self.get_player(color).pieces;
+        let has_king_slot = pieces.first().is_some_and(|(piece, _, _)| *piece == PieceType::King);
+        let has_other_king = pieces.iter().skip(1).any(|(piece, _, _)| *piece == PieceType::King);
+        if !has_king_slot || has_other_king {
+            errors.push(Inconsistency::WrongKingSlot(color));
+        }
+
+        for (index, &(piece, coord, _)) in pieces.iter().enumerate() {
+            if piece == PieceType::None {
+                continue;
+            }
+            let is_valid = coord.index().is_some_and(|tile| {
+                let tile = self.board[tile];
+                tile.is_occupied() && tile.get_color() == color && tile.get_index() as usize == index
+            });
+            if !is_valid {
+                errors.push(Inconsistency::PieceMismatch { color, index: index as u8 });
+            }
+        }
+    }
+    fn validate_bitboards(&self, color: Color, errors: &mut Vec<Inconsistency>) {
+        let player = self.get_player(color);
+        for (piece, bitboard) in Self::bitboards(&player.bitboard) {
+            let mut expected = Bitboard(0);
+            for &(_, coord, _) in player.pieces.iter().filter(|(other, _, _)| *other == piece) {
+                expected.set_bit(coord);
+            }
+            for square in bitboard & !expected {
+                errors.push(Inconsistency::StrayBit { color, piece, coord: square.coord() });
+            }
+            for square in expected & !bitboard {
+                errors.push(Inconsistency::MissingBit { color, piece, coord: square.coord() });
+            }
+        }
+    }
+    fn validate_overlap(&self, errors: &mut Vec<Inconsistency>) {
+        let mut seen = Bitboard(0);
+        let mut overlap = Bitboard(0);
+        for color in [Color::White, Color::Black] {
+            for (_, bitboard) in Self::bitboards(&self.get_player(color).bitboard) {
+                overlap |= seen & bitboard;
+                seen |= bitboard;
+            }
+        }
+        errors.extend(overlap.iter().map(|square| Inconsistency::OverlappingBitboards(square.coord())));
+    }
+    fn validate_en_passant(&self, errors: &mut Vec<Inconsistency>) {
+        // The pawn of the side that just moved, on the rank a double move lands on
+        let flagged = self.en_passant.filter(|&pawn| {
+            let landing_rank = match !self.current {
+                Color::White => 4,
+                Color::Black => 3,
+            };
+            pawn / 8 == landing_rank && self.piece_at(pawn) == Some((PieceType::Pawn, !self.current))
+        });
+        if let Some(pawn) = self.en_passant.filter(|_| flagged.is_none()) {
+            errors.push(Inconsistency::StaleEnPassant(Coord::from(pawn as u8)));
+        }
+
+        let expected = flagged.map(Self::en_passant_tile);
+        for (index, tile) in self.board.iter().enumerate() {
+            if tile.is_en_passant() != (expected == Some(index)) {
+                errors.push(Inconsistency::StaleEnPassant(Coord::from(index as u8)));
+            }
+        }
+    }
+    fn validate_hash(&self, errors: &mut Vec<Inconsistency>) {
+        let actual = self.compute_hash();
+        if self.zobrist != actual {
+            errors.push(Inconsistency::StaleHash { recorded: self.zobrist, actual });
+        }
+    }
+
+    fn bitboards(bitboard: &PlayerBitboard) -> [(PieceType, Bitboard); 6] {
+        [
+            (PieceType::Pawn, bitboard.pawns),
+            (PieceType::Knight, bitboard.knights),
+            (PieceType::Bishop, bitboard.bishops),
+            (PieceType::Rook, bitboard.rooks),
+            (PieceType::Queen, bitboard.queens),
+            (PieceType::King, bitboard.king),
+        ]
+    }
+
+    // Panics with every inconsistency, make and unmake call this in debug builds
+    pub(crate) fn debug_validate(&self, context: &str) {
+        if let Err(errors) = self.validate() {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            panic!("{} left the position inconsistent:\n{}", context, errors.join("\n"));
+        }
+    }
+}
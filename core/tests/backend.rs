@@ -0,0 +1,14 @@
+use core::*;
+use core::perft::perft;
+use core::sliders::SlidingBackend;
+
+
+#[test]
+fn move_generation_uses_the_selected_backend() {
+    let mut state = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").expect("Fen parsing failed. Use fen tests");
+    for backend in SlidingBackend::ALL {
+        backend.select();
+        assert_eq!(SlidingBackend::selected(), backend);
+        assert_eq!(perft(&mut state, 2), 2039, "{}", backend.name());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::*;
+use core::bitboard::{
+    Bitboard,
+    PlayerBitboard,
+};
+
+
+// A bitboard with the squares set
+fn squares(squares: &[Square]) -> Bitboard {
+    squares.iter().fold(Bitboard(0), |result, &square| result | Bitboard::from_square(square))
+}
+
+
+#[test]
+fn fill_reaches_the_edge() {
+    use Square::*;
+    let e4 = Bitboard::from_square(E4);
+    assert_eq!(e4.fill_north(), squares(&[E4, E5, E6, E7, E8]));
+    assert_eq!(e4.fill_south(), squares(&[E4, E3, E2, E1]));
+    assert_eq!(e4.fill_east(), squares(&[E4, F4, G4, H4]));
+    assert_eq!(e4.fill_west(), squares(&[E4, D4, C4, B4, A4]));
+    assert_eq!(e4.fill_north_east(), squares(&[E4, F5, G6, H7]));
+    assert_eq!(e4.fill_north_west(), squares(&[E4, D5, C6, B7, A8]));
+    assert_eq!(e4.fill_south_east(), squares(&[E4, F3, G2, H1]));
+    assert_eq!(e4.fill_south_west(), squares(&[E4, D3, C2, B1]));
+}
+
+#[test]
+fn fill_does_not_wrap_around_the_board() {
+    use Square::*;
+    assert_eq!(Bitboard::from_square(H4).fill_east(), Bitboard::from_square(H4));
+    assert_eq!(Bitboard::from_square(A4).fill_west(), Bitboard::from_square(A4));
+    assert_eq!(Bitboard::from_square(H1).fill_north_east(), Bitboard::from_square(H1));
+    assert_eq!(Bitboard::from_square(A1).fill_north_west(), Bitboard::from_square(A1));
+    assert_eq!(Bitboard::from_square(H8).fill_south_east(), Bitboard::from_square(H8));
+    assert_eq!(Bitboard::from_square(A8).fill_south_west(), Bitboard::from_square(A8));
+    assert_eq!(Bitboard::from_square(A1).fill_north_east(), squares(&[A1, B2, C3, D4, E5, F6, G7, H8]));
+}
+
+#[test]
+fn occluded_fill_stops_at_and_includes_the_blocker() {
+    use Square::*;
+    let e1 = Bitboard::from_square(E1);
+    let blockers = squares(&[E5, B4, H4]);
+    assert_eq!(e1.fill_north_occluded(blockers), squares(&[E1, E2, E3, E4, E5]));
+    assert_eq!(e1.fill_north_west_occluded(blockers), squares(&[E1, D2, C3, B4]));
+    assert_eq!(e1.fill_north_east_occluded(blockers), squares(&[E1, F2, G3, H4]));
+    assert_eq!(e1.fill_south_occluded(blockers), e1);
+
+    let d4 = Bitboard::from_square(D4);
+    let blockers = squares(&[B4, D2, F6, B2]);
+    assert_eq!(d4.fill_west_occluded(blockers), squares(&[D4, C4, B4]));
+    assert_eq!(d4.fill_east_occluded(blockers), squares(&[D4, E4, F4, G4, H4]));
+    assert_eq!(d4.fill_south_occluded(blockers), squares(&[D4, D3, D2]));
+    assert_eq!(d4.fill_north_east_occluded(blockers), squares(&[D4, E5, F6]));
+    assert_eq!(d4.fill_south_west_occluded(blockers), squares(&[D4, C3, B2]));
+    assert_eq!(d4.fill_south_east_occluded(blockers), squares(&[D4, E3, F2, G1]));
+}
+
+#[test]
+fn shifts_drop_bits_over_the_edge() {
+    use Square::*;
+    assert_eq!(Bitboard::from_square(A8).shift_north(), Bitboard(0));
+    assert_eq!(Bitboard::from_square(H1).shift_south(), Bitboard(0));
+    assert_eq!(Bitboard::from_square(H4).shift_east(), Bitboard(0));
+    assert_eq!(Bitboard::from_square(A4).shift_west(), Bitboard(0));
+    assert_eq!(Bitboard::from_square(E4).shift_north_east(), Bitboard::from_square(F5));
+    assert_eq!(Bitboard::from_square(E4).shift_south_west(), Bitboard::from_square(D3));
+}
+
+#[test]
+fn rotations_move_the_corners() {
+    use Square::*;
+    let turn = |rotate: fn(Bitboard) -> Bitboard, square| rotate(Bitboard::from_square(square));
+    for (from, clockwise, counter_clockwise, half) in [
+        (A1, A8, H1, H8),
+        (A8, H8, A1, H1),
+        (H8, H1, A8, A1),
+        (H1, A1, H8, A8),
+    ] {
+        assert_eq!(turn(Bitboard::rotate_clockwise, from), Bitboard::from_square(clockwise), "{} clockwise", from);
+        assert_eq!(turn(Bitboard::rotate_counter_clockwise, from), Bitboard::from_square(counter_clockwise), "{} counter clockwise", from);
+        assert_eq!(turn(Bitboard::rotate_180, from), Bitboard::from_square(half), "{} half turn", from);
+    }
+    let board = squares(&[A1, B3, E4, G8]);
+    assert_eq!(board.rotate_clockwise().rotate_counter_clockwise(), board);
+    assert_eq!(board.rotate_clockwise().rotate_clockwise(), board.rotate_180());
+}
+
+#[test]
+fn bit_iteration_visits_every_square_once() {
+    use Square::*;
+    let board = squares(&[A8, E4, H1]);
+    assert_eq!(board.count(), 3);
+    assert_eq!(board.lsb(), Some(A8));
+    assert_eq!(board.msb(), Some(H1));
+    assert_eq!(board.iter().collect::<Vec<_>>(), vec![A8, E4, H1]);
+
+    let mut remaining = board;
+    assert_eq!(remaining.pop_lsb(), Some(A8));
+    assert_eq!(remaining, squares(&[E4, H1]));
+    assert!(Bitboard(0).lsb().is_none());
+}
+
+#[test]
+fn attacks_of_the_pieces() {
+    use Square::*;
+    let knight = PlayerBitboard::generate_knight_attacks(Bitboard::from_square(A1));
+    assert_eq!(knight, squares(&[B3, C2]));
+    let king = PlayerBitboard::generate_king_attacks(Bitboard::from_square(H8));
+    assert_eq!(king, squares(&[G8, G7, H7]));
+    let pawn = PlayerBitboard::generate_pawn_attacks(Bitboard::from_square(A2), Color::White);
+    assert_eq!(pawn, squares(&[B3]));
+    let pawn = PlayerBitboard::generate_pawn_attacks(Bitboard::from_square(E7), Color::Black);
+    assert_eq!(pawn, squares(&[D6, F6]));
+
+    // The slider fills keep the square they start from
+    let rook = PlayerBitboard::generate_rook_attacks(Bitboard::from_square(A1), squares(&[A3, C1]));
+    assert_eq!(rook, squares(&[A1, A2, A3, B1, C1]));
+    let bishop = PlayerBitboard::generate_bishop_attacks(Bitboard::from_square(C1), squares(&[E3]));
+    assert_eq!(bishop, squares(&[C1, B2, A3, D2, E3]));
+}
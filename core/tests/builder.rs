@@ -0,0 +1,134 @@
+use crate::*;
+
+use core::builder::{PositionBuilder, SetupError};
+
+
+fn coord(name: &str) -> Coord {
+    Coord::parse(name).unwrap()
+}
+
+// Both kings on their starting squares, white to move
+fn kings() -> PositionBuilder {
+    let mut builder = PositionBuilder::new();
+    builder
+        .place(coord("e1"), PieceType::King, Color::White)
+        .place(coord("e8"), PieceType::King, Color::Black);
+    builder
+}
+
+fn errors(builder: &PositionBuilder) -> Vec<SetupError> {
+    builder.build().err().unwrap_or_default()
+}
+
+
+#[test]
+fn valid_positions_build() {
+    let state = kings()
+        .place(coord("h1"), PieceType::Rook, Color::White)
+        .castling(Color::White, true, true)
+        .build()
+        .unwrap();
+    assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert_eq!(state.hash(), state.compute_hash());
+
+    let start = GameState::from_fen(GameState::FEN_CLASSIC).unwrap();
+    assert_eq!(PositionBuilder::from_state(&start).build().map(|state| state.to_fen()), Ok(GameState::FEN_CLASSIC.to_string()));
+}
+
+#[test]
+fn missing_and_extra_kings() {
+    let mut builder = kings();
+    builder.remove(coord("e8"));
+    assert_eq!(errors(&builder), vec![SetupError::MissingKing(Color::Black)]);
+    builder.place(coord("a8"), PieceType::King, Color::Black).place(coord("h8"), PieceType::King, Color::Black);
+    assert_eq!(errors(&builder), vec![SetupError::TooManyKings(Color::Black)]);
+}
+
+#[test]
+fn pawns_on_the_back_rank() {
+    let mut builder = kings();
+    builder.place(coord("a1"), PieceType::Pawn, Color::White).place(coord("h8"), PieceType::Pawn, Color::Black);
+    assert_eq!(errors(&builder), vec![SetupError::PawnOnBackRank(coord("h8")), SetupError::PawnOnBackRank(coord("a1"))]);
+}
+
+#[test]
+fn side_not_to_move_in_check() {
+    let mut builder = kings();
+    builder.place(coord("e2"), PieceType::Rook, Color::White).side_to_move(Color::White);
+    assert_eq!(errors(&builder), vec![SetupError::OpponentInCheck(Color::Black)]);
+    assert!(builder.side_to_move(Color::Black).build().is_ok());
+}
+
+#[test]
+fn castling_needs_king_and_rook() {
+    let mut builder = kings();
+    builder.castling(Color::White, true, true).castling(Color::Black, false, true);
+    assert_eq!(errors(&builder), vec![
+        SetupError::CastlingWithoutRook { color: Color::White, kingside: true },
+        SetupError::CastlingWithoutRook { color: Color::Black, kingside: false },
+    ]);
+
+    let mut builder = kings();
+    builder.remove(coord("e1"));
+    builder
+        .place(coord("d1"), PieceType::King, Color::White)
+        .place(coord("a1"), PieceType::Rook, Color::White)
+        .castling(Color::White, false, true);
+    assert_eq!(errors(&builder), vec![SetupError::CastlingWithoutKing(Color::White)]);
+}
+
+#[test]
+fn en_passant_needs_a_double_push() {
+    let mut builder = kings();
+    builder.place(coord("e4"), PieceType::Pawn, Color::White).side_to_move(Color::Black);
+    assert!(builder.en_passant(Some(coord("e3"))).build().is_ok());
+    assert_eq!(errors(builder.en_passant(Some(coord("d3")))), vec![SetupError::InvalidEnPassant(coord("d3"))]);
+    assert_eq!(errors(builder.en_passant(Some(coord("e6")))), vec![SetupError::InvalidEnPassant(coord("e6"))]);
+    // The pawn can not have come from e2 if something stands there
+    builder.en_passant(Some(coord("e3"))).place(coord("e2"), PieceType::Knight, Color::White);
+    assert_eq!(errors(&builder), vec![SetupError::InvalidEnPassant(coord("e3"))]);
+}
+
+#[test]
+fn piece_counts() {
+    let mut builder = kings();
+    for file in 0..8 {
+        builder.place(Coord::new(file, 6), PieceType::Pawn, Color::White);
+        builder.place(Coord::new(file, 5), PieceType::Pawn, Color::White);
+    }
+    builder.place(coord("a5"), PieceType::Pawn, Color::White);
+    assert_eq!(errors(&builder), vec![SetupError::TooManyPieces(Color::White), SetupError::TooManyPawns(Color::White)]);
+
+    let mut builder = kings();
+    for file in 0..8 {
+        builder.place(Coord::new(file, 4), PieceType::Pawn, Color::White);
+    }
+    builder.place(coord("a1"), PieceType::Queen, Color::White).place(coord("b1"), PieceType::Queen, Color::White);
+    assert_eq!(errors(&builder), vec![SetupError::TooManyPromotions(Color::White)]);
+}
+
+#[test]
+fn fullmove_number_starts_at_one() {
+    assert_eq!(errors(kings().fullmove_count(0)), vec![SetupError::InvalidFullmoveCount]);
+}
+
+#[test]
+fn every_violation_is_reported() {
+    let mut builder = kings();
+    builder.remove(coord("e8"));
+    builder
+        .place(coord("c8"), PieceType::Pawn, Color::White)
+        .castling(Color::Black, true, true)
+        .fullmove_count(0);
+    let errors = errors(&builder);
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+    for error in [
+        SetupError::MissingKing(Color::Black),
+        SetupError::CastlingWithoutKing(Color::Black),
+        SetupError::CastlingWithoutRook { color: Color::Black, kingside: true },
+        SetupError::PawnOnBackRank(coord("c8")),
+        SetupError::InvalidFullmoveCount,
+    ] {
+        assert!(errors.contains(&error), "{} is missing", error);
+    }
+}
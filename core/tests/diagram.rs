@@ -0,0 +1,65 @@
+use crate::*;
+
+
+const FENS: &[&str] = &[
+    GameState::FEN_CLASSIC,
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 12",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 40",
+];
+
+
+#[test]
+fn diagram_round_trip() {
+    for fen in FENS {
+        let state = state(fen);
+        for unicode in [false, true] {
+            let diagram = state.to_diagram(unicode);
+            let parsed = GameState::from_diagram(&diagram).unwrap_or_else(|| panic!("did not parse:\n{}", diagram));
+            assert_eq!(parsed.to_fen(), *fen, "from\n{}", diagram);
+        }
+        assert_eq!(format!("{}", state), state.to_diagram(false));
+        assert_eq!(format!("{:#}", state), state.to_diagram(true));
+    }
+}
+
+#[test]
+fn diagram_layout() {
+    let diagram = state("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").to_diagram(false);
+    let lines: Vec<&str> = diagram.lines().collect();
+    assert_eq!(lines[1], "8 | r n b q k b n r |");
+    assert_eq!(lines[5], "4 | . . . . P . . . |");
+    assert_eq!(lines[10], "    a b c d e f g h");
+    assert_eq!(lines[11], "Black to move, castling KQkq, en passant e3");
+}
+
+#[test]
+fn book_diagrams_parse() {
+    // No borders or labels, dashes for empty squares and no FEN line
+    let diagram = "\
+        - - - - k - - -\n\
+        - - - - - - - -\n\
+        - - - - - - - -\n\
+        - - - - - - - -\n\
+        - - - - - - - -\n\
+        - - - - - - - -\n\
+        - - - - P - - -\n\
+        R - - - K - - -\n\
+        White to move, castling Q";
+    let state = GameState::from_diagram(diagram).unwrap();
+    assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1");
+}
+
+#[test]
+fn bad_diagrams_are_rejected() {
+    let diagram = state(GameState::FEN_CLASSIC).to_diagram(false);
+    // A rank missing
+    let short: String = diagram.lines().filter(|line| !line.starts_with("5 |")).collect::<Vec<&str>>().join("\n");
+    assert!(GameState::from_diagram(&short).is_none());
+    // An unknown piece
+    assert!(GameState::from_diagram(&diagram.replace("r n b q", "r x b q")).is_none());
+    // Castling the position does not allow
+    let no_rook = diagram.replace("R N B Q K B N R", "R N B Q K B N .");
+    assert!(GameState::from_diagram(&no_rook).is_none());
+    assert!(GameState::from_diagram(&no_rook.replace("castling KQkq", "castling Qkq").replace(" KQkq ", " Qkq ")).is_some());
+}
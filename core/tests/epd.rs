@@ -0,0 +1,64 @@
+use crate::*;
+
+use core::epd::Epd;
+
+
+fn parse(line: &str) -> Epd {
+    Epd::parse(line).unwrap_or_else(|| panic!("'{}' did not parse", line))
+}
+
+
+#[test]
+fn best_and_avoid_moves_resolve_against_the_position() {
+    let epd = parse("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am Ng5; id \"open.001\";");
+    let best: Vec<String> = epd.best_moves().unwrap().into_iter().map(san::move_to_uci).collect();
+    assert_eq!(best, vec!["f1b5", "f1c4"]);
+    let avoid: Vec<String> = epd.avoid_moves().unwrap().into_iter().map(san::move_to_uci).collect();
+    assert_eq!(avoid, vec!["f3g5"]);
+    assert_eq!(epd.id(), Some("open.001"));
+}
+
+#[test]
+fn unresolvable_moves_are_none() {
+    // Nf6 is not a legal move for white, Nd2 is ambiguous without the file of the knight and there is no bm
+    assert!(parse("4k3/8/8/8/8/8/8/4K1N1 w - - bm Nf6;").best_moves().is_none());
+    assert!(parse("4k3/8/8/8/8/8/8/1N2KN2 w - - am Nd2;").avoid_moves().is_none());
+    assert!(parse("4k3/8/8/8/8/8/8/4K3 w - - id \"none\";").best_moves().is_none());
+}
+
+#[test]
+fn checks_and_captures_resolve() {
+    let epd = parse("4k3/8/8/3p4/4P3/8/8/R3K3 w Q - bm exd5 Ra8+ O-O-O;");
+    let best: Vec<String> = epd.best_moves().unwrap().into_iter().map(san::move_to_uci).collect();
+    assert_eq!(best, vec!["e4d5", "a1a8", "e1c1"]);
+}
+
+#[test]
+fn set_best_moves_writes_san() {
+    let mut epd = parse("4k3/8/8/8/8/8/4P3/4K3 w - -");
+    let movement = find_move(&epd.position, "e2e4");
+    epd.set_best_moves(&[movement]);
+    assert_eq!(epd.operation("bm"), Some(&["e4".to_string()][..]));
+    assert_eq!(epd.best_moves(), Some(vec![movement]));
+}
+
+#[test]
+fn operations_round_trip() {
+    let line = "rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq - bm e6; id \"WAC; 1\"; c0 \"a comment\"; acd 12; hmvc 3; fmvn 9;";
+    let epd = parse(line);
+    assert_eq!(epd.id(), Some("WAC; 1"));
+    assert_eq!(epd.comment(0), Some("a comment"));
+    assert_eq!(epd.depth(), Some(12));
+    assert_eq!(epd.position.halfmove_count, 3);
+    assert_eq!(epd.position.fullmove_count, 9);
+    assert_eq!(epd.to_epd(), line);
+    assert_eq!(parse(&epd.to_epd()).to_epd(), line);
+}
+
+#[test]
+fn malformed_records_are_rejected() {
+    assert!(Epd::parse("").is_none());
+    assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w -").is_none());
+    assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open").is_none());
+    assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - hmvc x;").is_none());
+}
@@ -0,0 +1,83 @@
+use crate::*;
+
+
+#[test]
+fn fen_round_trips() {
+    let fens = [
+        GameState::FEN_CLASSIC,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 3",
+        "4k3/8/8/8/8/8/8/4K3 b - - 99 120",
+    ];
+    for fen in fens {
+        let state = GameState::from_fen(fen).unwrap_or_else(|| panic!("'{}' did not parse", fen));
+        assert_eq!(state.to_fen(), fen);
+    }
+}
+
+#[test]
+fn fen_fields() {
+    let state = GameState::from_fen("rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 3").unwrap();
+    assert_eq!(state.current, Color::Black);
+    assert_eq!(state.castling_rights(), [true, false, false, true]);
+    assert_eq!(state.en_passant, Some(Square::D4.index()));
+    assert!(state.board[Square::D3.index()].is_en_passant());
+    assert_eq!(state.halfmove_count, 0);
+    assert_eq!(state.fullmove_count, 3);
+    assert_eq!(state.piece_at(Square::E4.index()), Some((PieceType::Pawn, Color::Black)));
+    assert_eq!(state.piece_at(Square::E1.index()), Some((PieceType::King, Color::White)));
+    assert_eq!(state.piece_at(Square::E3.index()), None);
+}
+
+#[test]
+fn fen_kings_take_the_first_slot() {
+    let state = GameState::from_fen(GameState::FEN_CLASSIC).unwrap();
+    for color in [Color::White, Color::Black] {
+        let player = state.get_player(color);
+        assert_eq!(player.pieces.len(), 16);
+        assert_eq!(player.king().0, PieceType::King);
+        let (_, coord, _) = player.king();
+        assert_eq!(state.board[coord.index().unwrap()].get_index(), 0);
+    }
+}
+
+#[test]
+fn fen_rejects_malformed_input() {
+    let fens = [
+        "",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkqK - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+    ];
+    for fen in fens {
+        assert!(GameState::from_fen(fen).is_none(), "'{}' parsed", fen);
+    }
+}
+
+#[test]
+fn fen_rejects_impossible_kings() {
+    // Missing, doubled, and the side not to move in check
+    assert!(GameState::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+    assert!(GameState::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").is_none());
+    assert!(GameState::from_fen("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_none());
+    assert!(GameState::from_fen("4kk2/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+    assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4RK2 b - - 0 1").is_some());
+    assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4RK2 w - - 0 1").is_none());
+}
+
+#[test]
+fn fen_of_the_default_board_is_classic() {
+    let state = GameState::from_fen(GameState::FEN_CLASSIC).unwrap();
+    assert_eq!(state.to_fen(), GameState::FEN_CLASSIC);
+    assert_eq!(state.hash(), GameState::from_fen(&state.to_fen()).unwrap().hash());
+}
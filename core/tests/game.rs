@@ -0,0 +1,125 @@
+use crate::*;
+
+use core::game::Game;
+use nanoserde::{SerBin, DeBin, SerJson, DeJson};
+
+
+fn play(game: &mut Game, moves: &str) {
+    for san in moves.split_whitespace() {
+        assert!(game.play_san(san), "{} is not legal in '{}'", san, game.position().to_fen());
+    }
+}
+
+
+#[test]
+fn undo_redo_and_jump() {
+    let mut game = Game::default();
+    play(&mut game, "e4 e5 Nf3 Nc6 Bb5");
+    let end = game.position().to_fen();
+    assert_eq!((game.ply(), game.len()), (5, 5));
+
+    assert_eq!(game.undo().map(san::move_to_uci).as_deref(), Some("f1b5"));
+    assert_eq!(game.undo().map(san::move_to_uci).as_deref(), Some("b8c6"));
+    assert_eq!((game.ply(), game.len()), (3, 5));
+    assert_eq!(game.redo().map(san::move_to_uci).as_deref(), Some("b8c6"));
+
+    assert!(game.go_to(0));
+    assert_eq!(game.position().to_fen(), GameState::FEN_CLASSIC);
+    assert_eq!(game.hashes(), &[game.start_position().hash()]);
+    assert!(game.undo().is_none());
+    assert!(game.go_to(5));
+    assert_eq!(game.position().to_fen(), end);
+    assert!(game.redo().is_none());
+    assert!(!game.go_to(6));
+}
+
+#[test]
+fn a_different_move_drops_the_redo_moves() {
+    let mut game = Game::default();
+    play(&mut game, "e4 e5 Nf3");
+    game.go_to(1);
+    // Playing the next move again keeps the rest of the line
+    play(&mut game, "e5");
+    assert_eq!(game.len(), 3);
+    play(&mut game, "Bc4");
+    assert_eq!((game.ply(), game.len()), (3, 3));
+    assert!(game.redo().is_none());
+    assert!(!game.play_san("Ke3"));
+}
+
+#[test]
+fn hashes_follow_undo_and_redo() {
+    let mut game = Game::default();
+    play(&mut game, "e4 d5 exd5 c5 dxc6 Qd6 cxb7 Kd8 bxa8=Q Nf6 Nf3 e6 Be2 Bd7 O-O");
+    let hashes = game.hashes().to_vec();
+    assert_eq!(hashes.last(), Some(&game.position().compute_hash()));
+    game.go_to(0);
+    game.go_to(hashes.len() - 1);
+    assert_eq!(game.hashes(), &hashes[..]);
+    assert_eq!(game.validate(), Ok(()));
+}
+
+#[test]
+fn knight_shuffles_repeat_the_position() {
+    let mut game = Game::default();
+    play(&mut game, "Nf3 Nf6 Ng1 Ng8");
+    assert_eq!(game.repetition_count(), 2);
+    play(&mut game, "Nf3 Nf6 Ng1 Ng8");
+    assert_eq!(game.repetition_count(), 3);
+    assert_eq!(game.end_term(), GameStateEndTerm::ThreeFoldRepetition);
+    assert_eq!(game.outcome(), GameResult::Draw);
+}
+
+#[test]
+fn en_passant_square_without_a_capture_still_repeats() {
+    // The position after 1.e4 has an en passant square nobody can use, it comes back twice
+    let mut game = Game::default();
+    play(&mut game, "e4 Nf6 Nf3 Ng8 Ng1 Nf6 Nf3 Ng8 Ng1");
+    assert_eq!(game.repetition_count(), 3);
+    assert_eq!(game.end_term(), GameStateEndTerm::ThreeFoldRepetition);
+}
+
+#[test]
+fn en_passant_square_with_a_capture_does_not_repeat() {
+    // After 1...d5 white can take en passant, so the later positions with the same pieces differ
+    let mut game = Game::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 w - - 0 1").unwrap();
+    play(&mut game, "Kd1 d5 Ke1 Kd8 Kd1 Ke8 Ke1 Kd8 Kd1 Ke8");
+    game.go_to(2);
+    let with_capture = game.position().hash();
+    game.go_to(6);
+    assert_eq!(game.position().to_fen().split(' ').next(), Some("4k3/8/8/3pP3/8/8/8/3K4"));
+    assert_ne!(game.position().hash(), with_capture);
+    game.go_to(10);
+    assert_eq!(game.repetition_count(), 2);
+}
+
+#[test]
+fn mate_ends_the_game() {
+    let mut game = Game::default();
+    play(&mut game, "f3 e5 g4 Qh4#");
+    assert_eq!(game.end_term(), GameStateEndTerm::Checkmate);
+    assert_eq!(game.outcome(), GameResult::BlackWins);
+}
+
+#[test]
+fn pgn_and_serialization_round_trip() {
+    let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    game.set_tag("White", "Someone");
+    play(&mut game, "O-O O-O-O Rae1 Rd2");
+    game.undo();
+
+    let pgn = game.to_pgn();
+    assert_eq!(pgn.tag("FEN"), Some("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+    let from_pgn = Game::from_pgn(&pgn).unwrap();
+    assert_eq!(from_pgn.moves(), game.moves());
+    assert_eq!(from_pgn.tag("White"), Some("Someone"));
+
+    let json = Game::deserialize_json(&game.serialize_json()).unwrap();
+    let binary = Game::deserialize_bin(&game.serialize_bin()).unwrap();
+    for other in [json, binary] {
+        assert_eq!(other.ply(), game.ply());
+        assert_eq!(other.len(), game.len());
+        assert_eq!(other.hashes(), game.hashes());
+        assert_eq!(other.position().to_fen(), game.position().to_fen());
+    }
+}
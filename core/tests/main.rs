@@ -0,0 +1,33 @@
+use core::*;
+
+mod bitboard;
+mod builder;
+mod diagram;
+mod epd;
+mod fen;
+mod game;
+mod moves;
+mod packed;
+mod perft;
+mod pgn;
+mod play;
+mod properties;
+mod sliders;
+mod tables;
+mod tree;
+mod validate;
+
+
+// Parses a FEN the test relies on, with the moves of the side to move generated
+fn state(fen: &str) -> GameState {
+    let mut state = GameState::from_fen(fen).expect("Fen parsing failed. Use fen tests");
+    state.generate_moves(state.current);
+    state
+}
+
+// The legal move of the current player in UCI notation
+fn find_move(state: &GameState, uci: &str) -> Move {
+    state.legal_moves().into_iter()
+        .find(|&movement| san::move_to_uci(movement) == uci)
+        .unwrap_or_else(|| panic!("{} is not legal in '{}'", uci, state.to_fen()))
+}
@@ -0,0 +1,114 @@
+use crate::*;
+
+
+const MOVE_TYPES: [MoveType; 14] = [
+    MoveType::Quiet,
+    MoveType::PawnDoubleMove,
+    MoveType::KingCastle,
+    MoveType::QueenCastle,
+    MoveType::Capture,
+    MoveType::CaptureEnPassant,
+    MoveType::PromotionQueen,
+    MoveType::PromotionKnight,
+    MoveType::PromotionRook,
+    MoveType::PromotionBishop,
+    MoveType::CapturePromotionQueen,
+    MoveType::CapturePromotionKnight,
+    MoveType::CapturePromotionRook,
+    MoveType::CapturePromotionBishop,
+];
+
+
+#[test]
+fn move_round_trips_every_field() {
+    for move_type in MOVE_TYPES {
+        for from in 0..64 {
+            for target in 0..64 {
+                let movement = Move::new(move_type, from, target);
+                assert_eq!(movement.get_type(), move_type);
+                assert_eq!(movement.get_from(), from);
+                assert_eq!(movement.get_target(), target);
+            }
+        }
+    }
+}
+
+#[test]
+fn move_flags_follow_the_type() {
+    for move_type in MOVE_TYPES {
+        let movement = Move::new(move_type, 12, 4);
+        let is_capture = matches!(move_type,
+            MoveType::Capture | MoveType::CaptureEnPassant |
+            MoveType::CapturePromotionQueen | MoveType::CapturePromotionKnight |
+            MoveType::CapturePromotionRook | MoveType::CapturePromotionBishop
+        );
+        assert_eq!(movement.is_capture(), is_capture, "{:?}", move_type);
+        assert_eq!(movement.is_promotion(), san::promotion_type(move_type).is_some(), "{:?}", move_type);
+    }
+}
+
+#[test]
+fn move_type_round_trips_through_u8() {
+    for move_type in MOVE_TYPES {
+        let bits: u8 = move_type.into();
+        assert_eq!(MoveType::try_from(bits), Ok(move_type));
+    }
+}
+
+#[test]
+fn move_default_is_quiet_a8_a8() {
+    assert_eq!(Move::default(), Move::new(MoveType::Quiet, 0, 0));
+}
+
+
+#[test]
+fn tile_round_trips_every_field() {
+    for index in 0..16 {
+        for color in [Color::White, Color::Black] {
+            let mut tile = Tile::with_piece(index, color);
+            assert!(tile.is_occupied());
+            assert!(!tile.is_empty());
+            assert!(!tile.is_en_passant());
+            assert_eq!(tile.get_index(), index);
+            assert_eq!(tile.get_color(), color);
+            assert!(tile.is_color(color));
+            assert!(!tile.is_color(!color));
+
+            tile.set_en_passant(true);
+            assert!(tile.is_en_passant());
+            assert_eq!(tile.get_index(), index);
+            assert_eq!(tile.get_color(), color);
+
+            tile.set_en_passant(false);
+            assert!(!tile.is_en_passant());
+            assert!(tile.is_occupied());
+        }
+    }
+}
+
+#[test]
+fn tile_empty_only_holds_the_en_passant_flag() {
+    let mut tile = Tile::empty();
+    assert!(tile.is_empty());
+    assert!(!tile.is_en_passant());
+    tile.set_en_passant(true);
+    assert!(tile.is_empty());
+    assert!(tile.is_en_passant());
+}
+
+
+#[test]
+fn coord_round_trips_index_and_name() {
+    for index in 0..64 {
+        let coord = Coord::from(index);
+        assert_eq!(coord.index(), Some(index as usize));
+        assert_eq!(Coord::parse(&coord.to_string()), Some(coord));
+        assert_eq!(Square::from_index(index as usize).map(Square::coord), Some(coord));
+    }
+    assert_eq!(Coord::parse("a8"), Some(Coord::new(0, 0)));
+    assert_eq!(Coord::parse("h1"), Some(Coord::new(7, 7)));
+    assert_eq!(Coord::parse("i1"), None);
+    assert_eq!(Coord::parse("a9"), None);
+    assert_eq!(Coord::parse("a1a"), None);
+    assert_eq!(Coord::new(8, 0).index(), None);
+}
@@ -0,0 +1,109 @@
+use crate::*;
+
+use core::packed::PackedPosition;
+use nanoserde::{SerBin, DeBin};
+
+
+const FENS: &[&str] = &[
+    GameState::FEN_CLASSIC,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 13 47",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3",
+    "4k3/8/8/8/8/8/8/4K2R w K - 99 300",
+];
+
+
+#[test]
+fn pack_unpack_round_trip() {
+    for fen in FENS {
+        let state = state(fen);
+        let packed = PackedPosition::pack(&state);
+        let unpacked = packed.unpack().unwrap_or_else(|| panic!("'{}' did not unpack", fen));
+        assert_eq!(unpacked.to_fen(), *fen);
+        assert_eq!(unpacked.hash(), state.hash());
+        assert_eq!(unpacked.validate(), Ok(()));
+
+        assert_eq!(GameState::from_bytes(&state.to_bytes()).map(|state| state.to_fen()).as_deref(), Some(*fen));
+        assert_eq!(PackedPosition::from_bytes(&packed.to_bytes()), Some(packed));
+    }
+}
+
+#[test]
+fn positions_along_a_game_round_trip() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    for uci in ["e2e4", "c7c5", "g1f3", "d7d6", "d2d4", "c5d4", "f3d4", "g8f6", "b1c3", "a7a6", "f1e2", "e7e5", "e1g1"] {
+        state.make_move(find_move(&state, uci));
+        state.generate_moves(state.current);
+        let unpacked = PackedPosition::pack(&state).unpack().unwrap();
+        assert_eq!(unpacked.to_fen(), state.to_fen(), "after {}", uci);
+    }
+}
+
+#[test]
+fn clocks_saturate() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    state.halfmove_count = 70000;
+    let unpacked = PackedPosition::pack(&state).unpack().unwrap();
+    assert_eq!(unpacked.halfmove_count, u16::MAX as u32);
+}
+
+#[test]
+fn serbin_round_trip() {
+    let states: Vec<GameState> = FENS.iter().map(|fen| state(fen)).collect();
+    let mut bytes = Vec::new();
+    for state in &states {
+        state.ser_bin(&mut bytes);
+    }
+    assert_eq!(bytes.len(), states.len() * PackedPosition::SIZE);
+
+    let mut offset = 0;
+    for state in &states {
+        let read = GameState::de_bin(&mut offset, &bytes).unwrap();
+        assert_eq!(read.to_fen(), state.to_fen());
+    }
+    assert!(GameState::de_bin(&mut offset, &bytes).is_err());
+}
+
+#[test]
+fn invalid_bytes_are_rejected() {
+    assert!(PackedPosition::from_bytes(&[0; 29]).is_none());
+    // No kings
+    assert!(PackedPosition([0; PackedPosition::SIZE]).unpack().is_none());
+
+    let packed = PackedPosition::pack(&state(GameState::FEN_CLASSIC));
+    // An occupied tile holding piece type 7
+    let mut bytes = packed.to_bytes();
+    bytes[8] |= 0b0111;
+    assert!(PackedPosition(bytes).unpack().is_none());
+    // A ninth en passant file
+    let mut bytes = packed.to_bytes();
+    bytes[25] = 9;
+    assert!(PackedPosition(bytes).unpack().is_none());
+    // More than 32 pieces
+    let mut bytes = packed.to_bytes();
+    bytes[3] = 0xff;
+    assert!(PackedPosition(bytes).unpack().is_none());
+    // A white pawn instead of the rook on a1, the second piece after the black king
+    let mut bytes = PackedPosition::pack(&state("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")).to_bytes();
+    bytes[8] = (bytes[8] & 0x0f) | (PieceType::Pawn as u8) << 4;
+    assert!(PackedPosition(bytes).unpack().is_none());
+}
+
+#[test]
+fn en_passant_without_the_pawn_is_rejected() {
+    let mut bytes = PackedPosition::pack(&state("4k3/8/8/8/8/8/8/4K3 w - - 0 1")).to_bytes();
+    bytes[25] = 5;
+    let packed = PackedPosition(bytes);
+    assert!(packed.unpack().is_none());
+    assert!(GameState::from_bytes(&bytes).is_none());
+    assert!(GameState::de_bin(&mut 0, &bytes).is_err());
+
+    // With the black pawn on e5 the byte is fine
+    let mut bytes = PackedPosition::pack(&state("4k3/8/8/4p3/8/8/8/4K3 w - - 0 1")).to_bytes();
+    bytes[25] = 5;
+    let state = PackedPosition(bytes).unpack().unwrap();
+    assert_eq!(state.to_fen(), "4k3/8/8/4p3/8/8/8/4K3 w - e6 0 1");
+    assert_eq!(state.validate(), Ok(()));
+}
@@ -0,0 +1,53 @@
+use crate::*;
+
+
+// https://www.chessprogramming.org/Perft_Results
+// Depths kept small enough for a debug build
+const PERFT: &[(&str, &[u64])] = &[
+    (GameState::FEN_CLASSIC, &[1, 20, 400, 8902]),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[1, 48, 2039]),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[1, 14, 191, 2812]),
+    ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[1, 6, 264, 9467]),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[1, 44, 1486]),
+    ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[1, 46, 2079]),
+];
+
+
+#[test]
+fn perft_small_depths() {
+    for &(fen, counts) in PERFT {
+        let mut state = GameState::from_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(core::perft::perft(&mut state, depth), count, "'{}' at depth {}", fen, depth);
+        }
+        assert_eq!(state.to_fen(), fen, "perft did not restore the position");
+    }
+}
+
+#[test]
+fn perft_of_the_color_flip_is_the_same() {
+    for &(fen, counts) in PERFT {
+        let mut state = GameState::from_fen(fen).unwrap().color_flipped();
+        let depth = counts.len().min(3) - 1;
+        assert_eq!(core::perft::perft(&mut state, depth), counts[depth], "flip of '{}' at depth {}", fen, depth);
+    }
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    for &(fen, counts) in PERFT {
+        let mut state = GameState::from_fen(fen).unwrap();
+        let divide = core::perft::divide(&mut state, 2);
+        assert_eq!(divide.len() as u64, counts[1]);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), counts[2]);
+    }
+}
+
+#[test]
+fn perft_hashed_matches_perft() {
+    let table = core::perft::PerftTable::new(1);
+    for &(fen, counts) in PERFT {
+        let mut state = GameState::from_fen(fen).unwrap();
+        assert_eq!(core::perft::perft_hashed(&mut state, 2, &table), counts[2], "'{}'", fen);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::*;
+
+use core::pgn::*;
+
+
+const GAME: &str = r#"[Event "Casual"]
+[Site "?"]
+[Date "2021.11.03"]
+[Round "1"]
+[White "White"]
+[Black "Black"]
+[Result "1-0"]
+[Annotator "Someone"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $1 Nc6 (2... d6 {Philidor} 3. d4) 3. Bb5 a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23.
+Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5
+29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7
+g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42.
+g4 Bd3 43. Re6 1-0
+"#;
+
+fn read(input: &str) -> PgnGame {
+    let mut games: Vec<PgnGame> = parse(input).collect::<Result<_, _>>().unwrap_or_else(|error| panic!("{}", error));
+    assert_eq!(games.len(), 1);
+    games.remove(0)
+}
+
+
+#[test]
+fn read_write_read_keeps_the_game() {
+    let game = read(GAME);
+    assert_eq!(game.moves.len(), 85);
+    assert_eq!(game.result, GameResult::WhiteWins);
+    assert_eq!(game.tag("Annotator"), Some("Someone"));
+    assert_eq!(game.moves[0].comments_before, vec!["Opening comment".to_string()]);
+    assert_eq!(game.moves[2].nags, vec![1]);
+    assert_eq!(game.moves[3].variations.len(), 1);
+
+    let written = game.to_pgn();
+    assert!(written.lines().all(|line| line.len() < 80), "{}", written);
+    let reread = read(&written);
+    assert_eq!(reread, game);
+    assert_eq!(reread.to_pgn(), written);
+    assert_eq!(reread.final_position().map(|state| state.to_fen()), game.final_position().map(|state| state.to_fen()));
+}
+
+#[test]
+fn comments_with_a_closing_brace_read_back() {
+    let mut game = read("1. e4 e5 *");
+    game.moves[0].comments.push("evil } comment".to_string());
+    game.comments.push("}".to_string());
+
+    let written = game.to_pgn();
+    let reread = read(&written);
+    assert_eq!(reread.moves.len(), 2);
+    assert_eq!(reread.moves[0].comments, vec!["evil comment".to_string()]);
+    assert_eq!(reread.to_pgn(), written);
+}
+
+#[test]
+fn tags_with_quotes_read_back() {
+    let mut game = read("1. d4 *");
+    game.set_tag("Event", r#"The "quoted" \ event"#);
+    let reread = read(&game.to_pgn());
+    assert_eq!(reread.tag("Event"), Some(r#"The "quoted" \ event"#));
+}
+
+#[test]
+fn games_start_from_the_fen_tag() {
+    let game = read("[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 Kd7 *");
+    assert_eq!(game.final_position().map(|state| state.to_fen()), Some("8/3k4/8/8/4P3/8/8/4K3 w - - 1 2".to_string()));
+    // The export adds the seven tag roster in front of the FEN tag
+    let reread = read(&game.to_pgn());
+    assert_eq!(reread.moves, game.moves);
+    assert_eq!(reread.tag("FEN"), game.tag("FEN"));
+    assert_eq!(reread.tag("Event"), Some("?"));
+}
+
+#[test]
+fn a_bad_game_does_not_stop_the_others() {
+    let input = "[Event \"1\"]\n\n1. e4 e5 *\n\n[Event \"2\"]\n\n1. e5 *\n\n[Event \"3\"]\n\n1. d4 d5 *\n";
+    let games: Vec<_> = parse(input).collect();
+    assert_eq!(games.len(), 3);
+    assert!(games[0].is_ok());
+    assert_eq!(games[1].as_ref().map_err(|error| error.game), Err(1));
+    assert_eq!(games[2].as_ref().map(|game| game.moves.len()), Ok(2));
+}
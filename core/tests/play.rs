@@ -0,0 +1,207 @@
+use crate::*;
+
+
+#[test]
+fn double_push_sets_en_passant() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    state.play_move_unchecked(Move::new(MoveType::PawnDoubleMove, Square::E2 as u8, Square::E4 as u8));
+    assert_eq!(state.en_passant, Some(Square::E4.index()));
+    assert!(state.board[Square::E3.index()].is_en_passant());
+    assert_eq!(state.piece_at(Square::E4.index()), Some((PieceType::Pawn, Color::White)));
+    assert_eq!(state.piece_at(Square::E2.index()), None);
+    assert!(state.white.bitboard.pawns.contains(Square::E4));
+    assert!(!state.white.bitboard.pawns.contains(Square::E2));
+}
+
+#[test]
+fn black_double_push_sets_en_passant() {
+    let mut state = state("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    state.play_move_unchecked(Move::new(MoveType::PawnDoubleMove, Square::D7 as u8, Square::D5 as u8));
+    assert_eq!(state.en_passant, Some(Square::D5.index()));
+    assert!(state.board[Square::D6.index()].is_en_passant());
+    assert!(!state.board[Square::E3.index()].is_en_passant());
+}
+
+#[test]
+fn en_passant_removes_the_passed_pawn() {
+    let mut state = state("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    state.play_move_unchecked(Move::new(MoveType::CaptureEnPassant, Square::E5 as u8, Square::D6 as u8));
+    assert_eq!(state.piece_at(Square::D6.index()), Some((PieceType::Pawn, Color::White)));
+    assert_eq!(state.piece_at(Square::D5.index()), None);
+    assert_eq!(state.piece_at(Square::E5.index()), None);
+    assert!(state.black.bitboard.pawns.is_empty());
+    assert_eq!(state.en_passant, None);
+}
+
+#[test]
+fn capture_clears_the_captured_piece() {
+    let mut state = state("4k3/8/8/3p4/4N3/8/8/4K3 w - - 0 1");
+    state.play_move_unchecked(Move::new(MoveType::Capture, Square::E4 as u8, Square::D5 as u8));
+    assert_eq!(state.piece_at(Square::D5.index()), Some((PieceType::Knight, Color::White)));
+    assert!(state.black.bitboard.occupancy().is_empty());
+    assert!(state.black.pieces.iter().skip(1).all(|(piece, _, _)| *piece == PieceType::None));
+}
+
+#[test]
+fn castling_moves_the_rook_and_clears_the_rights() {
+    let mut state = state("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    state.play_move_unchecked(Move::new(MoveType::KingCastle, Square::E1 as u8, Square::G1 as u8));
+    assert_eq!(state.piece_at(Square::G1.index()), Some((PieceType::King, Color::White)));
+    assert_eq!(state.piece_at(Square::F1.index()), Some((PieceType::Rook, Color::White)));
+    assert_eq!(state.piece_at(Square::H1.index()), None);
+    assert_eq!(state.castling_rights(), [false, false, true, true]);
+
+    let mut state = self::state("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+    state.play_move_unchecked(Move::new(MoveType::QueenCastle, Square::E8 as u8, Square::C8 as u8));
+    assert_eq!(state.piece_at(Square::C8.index()), Some((PieceType::King, Color::Black)));
+    assert_eq!(state.piece_at(Square::D8.index()), Some((PieceType::Rook, Color::Black)));
+    assert_eq!(state.piece_at(Square::A8.index()), None);
+    assert_eq!(state.castling_rights(), [true, true, false, false]);
+}
+
+#[test]
+fn rook_moves_and_captures_clear_one_right() {
+    let mut state = state("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    state.play_move_unchecked(Move::new(MoveType::Capture, Square::H1 as u8, Square::H8 as u8));
+    assert_eq!(state.castling_rights(), [false, true, false, true]);
+    state.play_move_unchecked(Move::new(MoveType::Quiet, Square::A1 as u8, Square::A2 as u8));
+    assert_eq!(state.castling_rights(), [false, false, false, true]);
+}
+
+#[test]
+fn promotion_replaces_the_pawn() {
+    for (move_type, piece) in [
+        (MoveType::PromotionQueen, PieceType::Queen),
+        (MoveType::PromotionRook, PieceType::Rook),
+        (MoveType::PromotionBishop, PieceType::Bishop),
+        (MoveType::PromotionKnight, PieceType::Knight),
+    ] {
+        let mut state = state("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        state.play_move_unchecked(Move::new(move_type, Square::A7 as u8, Square::A8 as u8));
+        assert_eq!(state.piece_at(Square::A8.index()), Some((piece, Color::White)));
+        assert!(state.white.bitboard.pawns.is_empty());
+        // The occupancy leaves out the king
+        assert_eq!(state.white.bitboard.occupancy().count(), 1);
+    }
+}
+
+#[test]
+fn make_and_unmake_restore_the_position() {
+    let fens = [
+        GameState::FEN_CLASSIC,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+    for fen in fens {
+        let mut state = state(fen);
+        let hash = state.hash();
+        for movement in state.legal_moves() {
+            let undo = state.make_move(movement);
+            assert_ne!(state.hash(), hash, "{} did not change the hash of '{}'", san::move_to_uci(movement), fen);
+            state.unmake_move(movement, &undo);
+            assert_eq!(state.to_fen(), fen, "{} was not taken back", san::move_to_uci(movement));
+            assert_eq!(state.hash(), hash);
+        }
+    }
+}
+
+#[test]
+fn make_move_updates_the_counters() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    for (uci, halfmove, fullmove) in [("g1f3", 1, 1), ("g8f6", 2, 2), ("e2e4", 0, 2), ("f6e4", 0, 3)] {
+        let movement = find_move(&state, uci);
+        state.make_move(movement);
+        assert_eq!((state.halfmove_count, state.fullmove_count), (halfmove, fullmove), "after {}", uci);
+    }
+    assert_eq!(state.current, Color::White);
+}
+
+#[test]
+fn play_move_rejects_illegal_moves() {
+    let mut state = state("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+    let fen = state.to_fen();
+    // The king may not step along the rook's rank, nor move like a rook
+    assert!(!state.play_move(Move::new(MoveType::Quiet, Square::E1 as u8, Square::D2 as u8)));
+    assert!(!state.play_move(Move::new(MoveType::Quiet, Square::E1 as u8, Square::F2 as u8)));
+    assert!(!state.play_move(Move::new(MoveType::Quiet, Square::E1 as u8, Square::A1 as u8)));
+    assert_eq!(state.to_fen(), fen);
+    assert!(state.play_move(Move::new(MoveType::Capture, Square::E1 as u8, Square::E2 as u8)));
+    assert_eq!(state.current, Color::Black);
+}
+
+
+#[test]
+fn check_is_detected() {
+    let state = state("4k3/8/8/8/8/8/8/4K2r w - - 0 1");
+    assert!(state.is_king_checked(Color::White));
+    assert!(!state.is_king_checked(Color::Black));
+    assert_eq!(state.checkers(Color::White), core::bitboard::Bitboard::from_square(Square::H1));
+
+    let state = self::state("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1");
+    assert!(state.is_king_checked(Color::White));
+    let state = self::state("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1");
+    assert!(state.is_king_checked(Color::White));
+    let state = self::state("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1");
+    assert!(!state.is_king_checked(Color::White));
+    // A blocked slider does not give check
+    let state = self::state("4k3/8/8/8/8/8/8/4KN1r w - - 0 1");
+    assert!(!state.is_king_checked(Color::White));
+}
+
+#[test]
+fn king_masks_in_check() {
+    use Square::*;
+    let squares = |squares: &[Square]| squares.iter()
+        .fold(core::bitboard::Bitboard(0), |result, &square| result | core::bitboard::Bitboard::from_square(square));
+
+    // The rook on a4 does not check, so a1 and e4 do not answer the check on the first rank
+    let state = state("4k3/4r3/8/8/r7/8/4N3/4K2r w - - 0 1");
+    let (check, _, pin) = state.generate_king_masks(Color::White);
+    assert_eq!(check, squares(&[F1, G1, H1]));
+    assert_eq!(pin, squares(&[E2, E3, E4, E5, E6, E7]));
+
+    // Not in check, nothing is masked out
+    let state = self::state(GameState::FEN_CLASSIC);
+    assert_eq!(state.generate_king_masks(Color::White).0, core::bitboard::Bitboard(!0));
+}
+
+#[test]
+fn checkmate_is_detected() {
+    let fens = [
+        // Fool's mate
+        "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        // Back rank
+        "3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1",
+        // Smothered
+        "6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1",
+    ];
+    for fen in fens {
+        let state = state(fen);
+        assert!(state.legal_moves().is_empty(), "'{}' has moves", fen);
+        assert!(state.is_king_checkmated(state.current), "'{}' is not mate", fen);
+        assert!(!state.is_king_stalemated(state.current), "'{}' is stalemate", fen);
+    }
+}
+
+#[test]
+fn stalemate_is_detected() {
+    let fens = [
+        "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        "k7/P7/K7/8/8/8/8/8 b - - 0 1",
+        "5bnr/4p1pq/4Qpkr/7p/7P/4P3/PPPP1PP1/RNB1KBNR b KQ - 2 10",
+    ];
+    for fen in fens {
+        let state = state(fen);
+        assert!(state.legal_moves().is_empty(), "'{}' has moves", fen);
+        assert!(state.is_king_stalemated(state.current), "'{}' is not stalemate", fen);
+        assert!(!state.is_king_checkmated(state.current), "'{}' is mate", fen);
+    }
+}
+
+#[test]
+fn positions_with_moves_are_neither_mate_nor_stalemate() {
+    let state = state("4k3/8/8/8/8/8/8/4K2r w - - 0 1");
+    assert!(!state.is_king_checkmated(Color::White));
+    assert!(!state.is_king_stalemated(Color::White));
+}
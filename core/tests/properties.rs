@@ -0,0 +1,200 @@
+use crate::*;
+
+use nanorand::{
+    Rng,
+    WyRand,
+};
+
+
+// Walks start from these, the perft positions and the king safety positions from the frontend
+const STARTS: &[&str] = &[
+    GameState::FEN_CLASSIC,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2bqkbnr/p1pppppp/Pr6/2p4n/3P4/2N5/4PPPP/R1BQKBNR b KQkq - 0 1",
+    "4k3/8/4K3/8/8/8/1R6/8 b - - 0 1",
+    "8/8/8/4k3/8/2K5/4n3/4R3 w - - 0 1",
+    "8/5k2/8/1R6/8/8/1K6/8 b - - 0 1",
+    "8/1R3k2/8/8/8/3r4/1K6/8 b - - 0 1",
+];
+const WALKS: usize = 4;
+const PLIES: usize = 40;
+
+
+// Whether a piece of the color attacks the tile, found by looking outwards from the tile.
+// It shares nothing with the bitboard attacks the move generator uses.
+fn is_attacked(state: &GameState, index: usize, color: Color) -> bool {
+    let coord = Coord::from(index as u8);
+    let piece_at = |file: i8, rank: i8| -> Option<(PieceType, Color)> {
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+            return None;
+        }
+        state.piece_at(rank as usize * 8 + file as usize)
+    };
+    let (file, rank) = (coord.file as i8, coord.rank as i8);
+
+    let leapers = |steps: &[(i8, i8)], piece: PieceType| steps.iter()
+        .any(|&(dx, dy)| piece_at(file + dx, rank + dy) == Some((piece, color)));
+    let knight = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+    let king = [(1, 1), (1, 0), (1, -1), (0, 1), (0, -1), (-1, 1), (-1, 0), (-1, -1)];
+    // White pawns move towards rank 0, so they attack from the rank below
+    let pawn = match color {
+        Color::White => [(1, 1), (-1, 1)],
+        Color::Black => [(1, -1), (-1, -1)],
+    };
+    if leapers(&knight, PieceType::Knight) || leapers(&king, PieceType::King) || leapers(&pawn, PieceType::Pawn) {
+        return true;
+    }
+
+    let slider = |dx: i8, dy: i8, piece: PieceType| -> bool {
+        let (mut x, mut y) = (file + dx, rank + dy);
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            match piece_at(x, y) {
+                None => (),
+                Some((other, other_color)) => return other_color == color && (other == piece || other == PieceType::Queen),
+            }
+            x += dx;
+            y += dy;
+        }
+        false
+    };
+    [(0, 1), (0, -1), (1, 0), (-1, 0)].iter().any(|&(dx, dy)| slider(dx, dy, PieceType::Rook)) ||
+    [(1, 1), (1, -1), (-1, 1), (-1, -1)].iter().any(|&(dx, dy)| slider(dx, dy, PieceType::Bishop))
+}
+
+fn is_in_check(state: &GameState, color: Color) -> bool {
+    let (_, king, _) = state.get_player(color).king();
+    is_attacked(state, king.index().unwrap(), !color)
+}
+
+
+// Every property that has to hold for a position reached in a game
+fn check_position(state: &mut GameState) {
+    let fen = state.to_fen();
+    let hash = state.hash();
+    assert_eq!(hash, state.compute_hash(), "kept hash of '{}'", fen);
+    let mover = state.current;
+    assert_eq!(state.is_king_checked(mover), is_in_check(state, mover), "check in '{}'", fen);
+
+    // A pseudo legal move is legal exactly when it does not leave the king attacked
+    let legal = state.legal_moves();
+    for movement in state.pseudo_legal_moves() {
+        let undo = state.make_move(movement);
+        let is_legal = !is_in_check(state, mover);
+        state.unmake_move(movement, &undo);
+        assert_eq!(legal.contains(&movement), is_legal, "{} in '{}'", san::move_to_uci(movement), fen);
+
+        assert_eq!(state.to_fen(), fen, "{} was not taken back", san::move_to_uci(movement));
+        assert_eq!(state.hash(), hash, "{} was not taken back", san::move_to_uci(movement));
+    }
+
+    let parsed = GameState::from_fen(&fen).unwrap_or_else(|| panic!("'{}' did not parse", fen));
+    assert_eq!(parsed.to_fen(), fen);
+    assert_eq!(parsed.hash(), hash);
+
+    let flipped = state.color_flipped();
+    assert_eq!(flipped.validate(), Ok(()), "flip of '{}'", fen);
+    assert_eq!(flipped.legal_moves().len(), legal.len(), "flip of '{}'", fen);
+    assert_eq!(flipped.is_king_checked(!mover), state.is_king_checked(mover), "flip of '{}'", fen);
+    assert_eq!(flipped.color_flipped().to_fen(), fen);
+}
+
+
+#[test]
+fn random_games_keep_every_property() {
+    let mut rng = WyRand::new_seed(0x5eed);
+    for start in STARTS {
+        for _ in 0..WALKS {
+            let mut state = state(start);
+            for _ in 0..PLIES {
+                check_position(&mut state);
+                let moves = state.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                state.make_move(moves[rng.generate_range(0..moves.len())]);
+            }
+        }
+    }
+}
+
+#[test]
+fn random_games_have_the_perft_of_their_flip() {
+    let mut rng = WyRand::new_seed(0xf11b);
+    for start in STARTS {
+        let mut state = state(start);
+        for ply in 0..PLIES {
+            if ply % 8 == 0 {
+                let mut flipped = state.color_flipped();
+                assert_eq!(core::perft::perft(&mut flipped, 2), core::perft::perft(&mut state, 2), "flip of '{}'", state.to_fen());
+            }
+            let moves = state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            state.make_move(moves[rng.generate_range(0..moves.len())]);
+        }
+    }
+}
+
+
+// Anything from_fen accepts has to be a consistent position that survives a round trip
+fn check_fen(fen: &str) {
+    if let Some(state) = GameState::from_fen(fen) {
+        assert_eq!(state.validate(), Ok(()), "'{}'", fen);
+        let written = state.to_fen();
+        assert_eq!(GameState::from_fen(&written).map(|state| state.to_fen()), Some(written.clone()), "'{}' from '{}'", written, fen);
+    }
+}
+
+#[test]
+fn fen_parsing_never_panics() {
+    let fens = [
+        "9/8/8/8/8/8/8/8 w - - 0 1",
+        "44/8/8/8/8/8/8/4K2k w - - 0 1",
+        "8p/8/8/8/8/8/8/4K2k w - - 0 1",
+        "pppppppppp/8/8/8/8/8/8/4K2k w - - 0 1",
+        "8/8/8/8/8/8/8/8/4K2k w - - 0 1",
+        "88888888888888888888888888888888888/8/8/8/8/8/8/4K2k w - - 0 1",
+        "pppppppp/pppppppp/pppppppp/8/8/8/8/4K2k w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra",
+        "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w - - 99999999999 1",
+        "4k3/8/8/8/8/8/8/4K3",
+        "4k3/8/8/8/8/8/8/4K3 w",
+        "/////// w - - 0 1",
+        "k/8/8/8/8/8/8/K w - - 0 1",
+        "\u{e9}\u{e9}\u{e9}\u{e9}/8/8/8/8/8/8/4K2k w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w \u{e9} - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w - \u{e9} 0 1",
+    ];
+    for fen in fens {
+        check_fen(fen);
+    }
+
+    // Random mutations of valid FENs, and random strings of FEN characters
+    const ALPHABET: &[u8] = b"pnbrqkPNBRQK0123456789/ -wabcdefgh";
+    let mut rng = WyRand::new_seed(0xfe7);
+    for _ in 0..20000 {
+        let mut bytes = STARTS[rng.generate_range(0..STARTS.len())].as_bytes().to_vec();
+        for _ in 0..rng.generate_range(1..=4usize) {
+            let index = rng.generate_range(0..bytes.len());
+            match rng.generate_range(0..3u8) {
+                0 => bytes[index] = ALPHABET[rng.generate_range(0..ALPHABET.len())],
+                1 => { bytes.remove(index); }
+                _ => bytes.insert(index, ALPHABET[rng.generate_range(0..ALPHABET.len())]),
+            }
+            if bytes.is_empty() {
+                break;
+            }
+        }
+        check_fen(&String::from_utf8_lossy(&bytes));
+
+        let length = rng.generate_range(0..80usize);
+        let random: Vec<u8> = (0..length).map(|_| ALPHABET[rng.generate_range(0..ALPHABET.len())]).collect();
+        check_fen(&String::from_utf8_lossy(&random));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::*;
+use core::bitboard::Bitboard;
+use core::sliders::{sample_occupancies, SlidingBackend};
+
+
+// Walks every direction a square at a time up to and including the first blocker
+fn reference(square: Square, occupancy: Bitboard, directions: &[(i8, i8)]) -> Bitboard {
+    let mut result = Bitboard(0);
+    for &(dx, dy) in directions {
+        let mut coord = square.coord();
+        while let Some(next) = coord.offset(dx, dy).index().and_then(Square::from_index) {
+            result |= Bitboard::from_square(next);
+            if occupancy.contains(next) {
+                break;
+            }
+            coord = next.coord();
+        }
+    }
+    result
+}
+
+
+#[test]
+fn backends_agree_with_the_reference() {
+    let straight = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+    let diagonal = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+    let occupancies = sample_occupancies(200);
+    for backend in SlidingBackend::ALL {
+        let sliders = backend.get();
+        for square in Square::ALL {
+            for &occupancy in &occupancies {
+                assert_eq!(
+                    sliders.rook_attacks(square, occupancy), reference(square, occupancy, &straight),
+                    "{} rook on {} with occupancy {:#x}", backend.name(), square, occupancy.0,
+                );
+                assert_eq!(
+                    sliders.bishop_attacks(square, occupancy), reference(square, occupancy, &diagonal),
+                    "{} bishop on {} with occupancy {:#x}", backend.name(), square, occupancy.0,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn backends_round_trip_their_names() {
+    for backend in SlidingBackend::ALL {
+        assert_eq!(SlidingBackend::parse(backend.name()), Some(backend));
+    }
+    assert_eq!(SlidingBackend::parse("rotated"), None);
+}
@@ -0,0 +1,89 @@
+use crate::*;
+use core::bitboard::Bitboard;
+use core::tables;
+
+
+// A bitboard with the squares set
+fn squares(squares: &[Square]) -> Bitboard {
+    squares.iter().fold(Bitboard(0), |result, &square| result | Bitboard::from_square(square))
+}
+
+
+#[test]
+fn between_and_line_need_a_shared_line() {
+    use Square::*;
+    assert_eq!(tables::between(A1, A4), squares(&[A2, A3]));
+    assert_eq!(tables::between(C1, F4), squares(&[D2, E3]));
+    assert_eq!(tables::between(E4, E5), Bitboard(0));
+    assert_eq!(tables::between(A1, B3), Bitboard(0));
+    assert_eq!(tables::line(C1, F4), squares(&[C1, D2, E3, F4, G5, H6]));
+    assert_eq!(tables::line(D4, B2), squares(&[A1, B2, C3, D4, E5, F6, G7, H8]));
+    assert_eq!(tables::line(A1, B3), Bitboard(0));
+
+    for from in Square::ALL {
+        for to in Square::ALL {
+            assert_eq!(tables::between(from, to), tables::between(to, from));
+            let between = tables::between(from, to);
+            assert_eq!(between & tables::line(from, to), between, "{} {}", from, to);
+        }
+    }
+}
+
+#[test]
+fn rays_and_distances() {
+    use Square::*;
+    assert_eq!(tables::ray(E4, Direction::North), squares(&[E5, E6, E7, E8]));
+    assert_eq!(tables::ray(E4, Direction::West), squares(&[D4, C4, B4, A4]));
+    assert_eq!(tables::ray(E4, Direction::SouthEast), squares(&[F3, G2, H1]));
+    assert_eq!(tables::ray(H8, Direction::NorthEast), Bitboard(0));
+
+    assert_eq!(tables::chebyshev_distance(A1, H8), 7);
+    assert_eq!(tables::chebyshev_distance(E4, F6), 2);
+    assert_eq!(tables::manhattan_distance(A1, H8), 14);
+    assert_eq!(tables::manhattan_distance(E4, F6), 3);
+    assert_eq!(tables::manhattan_distance(E4, E4), 0);
+}
+
+#[test]
+fn pawn_spans_look_ahead_of_the_pawn() {
+    use Square::*;
+    assert_eq!(tables::forward_file(Color::White, E5), squares(&[E6, E7, E8]));
+    assert_eq!(tables::forward_file(Color::Black, E5), squares(&[E4, E3, E2, E1]));
+    assert_eq!(tables::forward_file(Color::White, A8), Bitboard(0));
+
+    assert_eq!(tables::pawn_attack_span(Color::White, E5), squares(&[D6, D7, D8, F6, F7, F8]));
+    assert_eq!(tables::pawn_attack_span(Color::Black, A3), squares(&[B2, B1]));
+
+    for color in [Color::White, Color::Black] {
+        for square in Square::ALL {
+            let span = tables::forward_file(color, square) | tables::pawn_attack_span(color, square);
+            assert_eq!(tables::passed_pawn_span(color, square), span, "{:?} pawn on {}", color, square);
+        }
+    }
+}
+
+#[test]
+fn king_zone_and_adjacent_files() {
+    use Square::*;
+    assert_eq!(tables::king_zone(Color::White, G1), squares(&[G1, F1, H1, F2, G2, H2, F3, G3, H3]));
+    assert_eq!(tables::king_zone(Color::Black, G8), squares(&[G8, F8, H8, F7, G7, H7, F6, G6, H6]));
+    assert_eq!(tables::king_zone(Color::White, A4), squares(&[A4, B4, A3, B3, A5, B5, A6, B6]));
+
+    assert_eq!(tables::adjacent_files(A5), Bitboard(0x0202020202020202));
+    assert_eq!(tables::adjacent_files(E2), Bitboard(0x2828282828282828));
+}
+
+#[test]
+fn outposts_and_square_colors() {
+    use Square::*;
+    let white = tables::outposts(Color::White);
+    let black = tables::outposts(Color::Black);
+    assert_eq!(white.count(), 24);
+    assert!(white.contains(A4) && white.contains(H6) && !white.contains(A3) && !white.contains(A7));
+    assert!(black.contains(A5) && black.contains(H3) && !black.contains(A6) && !black.contains(A2));
+
+    assert!(tables::light_squares().contains(A8) && tables::light_squares().contains(H1));
+    assert!(tables::dark_squares().contains(A1) && tables::dark_squares().contains(H8));
+    assert_eq!(tables::light_squares() | tables::dark_squares(), Bitboard(!0));
+    assert_eq!(tables::light_squares() & tables::dark_squares(), Bitboard(0));
+}
@@ -0,0 +1,117 @@
+use crate::*;
+
+use core::pgn::{self, PgnGame};
+use core::tree::*;
+
+
+fn read(input: &str) -> PgnGame {
+    pgn::parse(input).next().unwrap().unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn tree(input: &str) -> VariationTree {
+    VariationTree::from_pgn(&read(input)).unwrap_or_else(|| panic!("'{}' has illegal moves", input))
+}
+
+// The tree written as PGN and read back, which has to give the same PGN again
+fn round_trip(tree: &VariationTree) -> String {
+    let written = tree.to_pgn().to_pgn();
+    let reread = VariationTree::from_pgn(&read(&written)).unwrap();
+    assert_eq!(reread.to_pgn().to_pgn(), written);
+    written
+}
+
+fn movetext(written: &str) -> &str {
+    written.split("\n\n").nth(1).unwrap().trim()
+}
+
+
+#[test]
+fn variations_round_trip() {
+    let tree = tree("1. e4 e5 (1... c5 2. Nf3 (2. Nc3) d6) 2. Nf3 $1 {good} 2... Nc6 *");
+    let written = round_trip(&tree);
+    assert_eq!(movetext(&written), "1. e4 e5 (1... c5 2. Nf3 (2. Nc3) 2... d6) 2. Nf3 $1 {good} 2... Nc6 *");
+}
+
+#[test]
+fn promote_and_make_mainline() {
+    let mut tree = tree("1. e4 e5 (1... c5) (1... e6) 2. Nf3 *");
+    let e4 = tree.node(VariationTree::ROOT).unwrap().children()[0];
+    let e6 = tree.node(e4).unwrap().children()[2];
+
+    assert!(tree.promote_variation(e6));
+    assert_eq!(movetext(&round_trip(&tree)), "1. e4 e5 (1... e6) (1... c5) 2. Nf3 *");
+    assert!(tree.promote_variation(e6));
+    assert_eq!(movetext(&round_trip(&tree)), "1. e4 e6 (1... e5 2. Nf3) (1... c5) *");
+    assert!(!tree.promote_variation(e6));
+    assert!(!tree.promote_variation(VariationTree::ROOT));
+
+    let c5 = tree.node(e4).unwrap().children()[2];
+    tree.go_to(c5);
+    let nf3 = tree.play_san("Nf3").unwrap();
+    assert!(tree.make_mainline(nf3));
+    assert_eq!(movetext(&round_trip(&tree)), "1. e4 c5 (1... e6) (1... e5 2. Nf3) 2. Nf3 *");
+}
+
+#[test]
+fn delete_removes_the_subtree() {
+    let mut tree = tree("1. e4 e5 (1... c5 2. Nf3 d6) 2. Nf3 *");
+    let e4 = tree.node(VariationTree::ROOT).unwrap().children()[0];
+    let c5 = tree.node(e4).unwrap().children()[1];
+    let d6 = tree.mainline(c5)[1];
+    tree.go_to(d6);
+
+    assert!(tree.delete(c5));
+    assert_eq!(tree.current(), e4);
+    assert!(tree.node(c5).is_none());
+    assert!(tree.node(d6).is_none());
+    assert_eq!(tree.node_ids().count(), 4);
+    assert_eq!(movetext(&round_trip(&tree)), "1. e4 e5 2. Nf3 *");
+    assert!(!tree.delete(c5));
+    assert!(!tree.delete(VariationTree::ROOT));
+}
+
+#[test]
+fn walking_between_variations_keeps_the_position() {
+    let mut tree = tree("1. e4 e5 (1... c5 2. Nf3 (2. c3 d5) d6 3. d4) 2. Nf3 Nc6 3. Bb5 *");
+    // Every node against its moves replayed from the start, walking there from the node before
+    for id in tree.node_ids().collect::<Vec<_>>() {
+        let mut replayed = tree.start_position().clone();
+        for movement in tree.moves_to(id).unwrap() {
+            replayed.make_move(movement);
+        }
+        assert_eq!(tree.position_at(id).unwrap().to_fen(), replayed.to_fen());
+        assert!(tree.go_to(id));
+        assert_eq!(tree.position().to_fen(), replayed.to_fen());
+        assert_eq!(tree.position().hash(), replayed.compute_hash());
+    }
+    assert!(tree.go_to(VariationTree::ROOT));
+    assert_eq!(tree.position().to_fen(), GameState::FEN_CLASSIC);
+}
+
+#[test]
+fn marks_round_trip() {
+    let tree = tree("{[%csl Rd4] start} 1. e4 {[%cal Ge2e4,Rd1h5] [%clk 0:03:00] fine} *");
+    let e4 = tree.node(VariationTree::ROOT).unwrap().children()[0];
+    let node = tree.node(e4).unwrap();
+    assert_eq!(node.arrows.len(), 2);
+    assert_eq!(node.arrows[1], Arrow { from: Coord::parse("d1").unwrap(), to: Coord::parse("h5").unwrap(), color: MarkColor::Red });
+    assert_eq!(node.comments, vec!["[%clk 0:03:00] fine".to_string()]);
+    assert_eq!(tree.node(VariationTree::ROOT).unwrap().highlights.len(), 1);
+
+    let written = round_trip(&tree);
+    assert_eq!(movetext(&written), "{[%csl Rd4] start} 1. e4 {[%cal Ge2e4,Rd1h5] [%clk 0:03:00] fine} *");
+}
+
+#[test]
+fn partly_invalid_marks_stay_in_the_text() {
+    let tree = tree("1. e4 {[%cal Ge2e4,Rz9z9] hello} *");
+    let e4 = tree.node(VariationTree::ROOT).unwrap().children()[0];
+    let node = tree.node(e4).unwrap();
+    assert!(node.arrows.is_empty());
+    assert_eq!(node.comments, vec!["[%cal Ge2e4,Rz9z9] hello".to_string()]);
+
+    // Nothing is added on the way through
+    let written = round_trip(&tree);
+    assert_eq!(movetext(&written), "1. e4 {[%cal Ge2e4,Rz9z9] hello} *");
+    assert_eq!(movetext(&round_trip(&VariationTree::from_pgn(&read(&written)).unwrap())), movetext(&written));
+}
@@ -0,0 +1,115 @@
+use crate::*;
+use core::validate::Inconsistency;
+use core::bitboard::Bitboard;
+use core::game::Game;
+
+
+#[test]
+fn parsed_positions_are_consistent() {
+    let fens = [
+        GameState::FEN_CLASSIC,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 3",
+    ];
+    for fen in fens {
+        let state = state(fen);
+        assert_eq!(state.validate(), Ok(()), "'{}'", fen);
+        assert_eq!(state.color_flipped().validate(), Ok(()), "flip of '{}'", fen);
+    }
+}
+
+#[test]
+fn every_move_keeps_the_position_consistent() {
+    let mut state = state("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+    for movement in state.legal_moves() {
+        let undo = state.make_move(movement);
+        assert_eq!(state.validate(), Ok(()), "after {}", san::move_to_uci(movement));
+        state.unmake_move(movement, &undo);
+        assert_eq!(state.validate(), Ok(()), "after taking back {}", san::move_to_uci(movement));
+    }
+}
+
+#[test]
+fn tile_without_its_piece() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    let tile = state.board[Square::B1.index()];
+    state.board[Square::B3.index()] = tile;
+    let errors = state.validate().unwrap_err();
+    assert!(errors.contains(&Inconsistency::TileMismatch { coord: Square::B3.coord(), color: Color::White, index: tile.get_index() }));
+}
+
+#[test]
+fn piece_without_its_tile() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    let index = state.board[Square::G1.index()].get_index();
+    state.board[Square::G1.index()] = Tile::empty();
+    let errors = state.validate().unwrap_err();
+    assert!(errors.contains(&Inconsistency::PieceMismatch { color: Color::White, index }));
+}
+
+#[test]
+fn bitboards_out_of_sync() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    state.black.bitboard.knights.0 |= Bitboard::from_square(Square::E5).0;
+    state.white.bitboard.pawns.0 &= !Bitboard::from_square(Square::E2).0;
+    let errors = state.validate().unwrap_err();
+    assert_eq!(errors, vec![
+        Inconsistency::MissingBit { color: Color::White, piece: PieceType::Pawn, coord: Square::E2.coord() },
+        Inconsistency::StrayBit { color: Color::Black, piece: PieceType::Knight, coord: Square::E5.coord() },
+    ]);
+}
+
+#[test]
+fn overlapping_bitboards() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    state.white.bitboard.queens.0 |= Bitboard::from_square(Square::E8).0;
+    let errors = state.validate().unwrap_err();
+    assert!(errors.contains(&Inconsistency::OverlappingBitboards(Square::E8.coord())));
+}
+
+#[test]
+fn king_out_of_its_slot() {
+    let mut state = state("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+    state.white.pieces.swap(0, 1);
+    let errors = state.validate().unwrap_err();
+    assert!(errors.contains(&Inconsistency::WrongKingSlot(Color::White)));
+}
+
+#[test]
+fn stale_en_passant() {
+    // The flag outlived the move after the double push
+    let mut state = state("rnbqkbnr/ppp1pppp/8/3p4/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2");
+    state.board[Square::D6.index()].set_en_passant(true);
+    let errors = state.validate().unwrap_err();
+    assert_eq!(errors, vec![Inconsistency::StaleEnPassant(Square::D6.coord())]);
+
+    // The square does not hold the pawn that moved
+    let mut state = self::state("rnbqkbnr/ppp1pppp/8/3p4/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2");
+    state.en_passant = Some(Square::E5.index());
+    let errors = state.validate().unwrap_err();
+    assert_eq!(errors, vec![Inconsistency::StaleEnPassant(Square::E5.coord())]);
+}
+
+#[test]
+fn stale_hash() {
+    let mut state = state(GameState::FEN_CLASSIC);
+    let actual = state.hash();
+    state.zobrist ^= 1;
+    let errors = state.validate().unwrap_err();
+    assert_eq!(errors, vec![Inconsistency::StaleHash { recorded: actual ^ 1, actual }]);
+
+    // Handing the turn over without make_move leaves the kept hash behind
+    let mut state = self::state(GameState::FEN_CLASSIC);
+    state.current = !state.current;
+    let errors = state.validate().unwrap_err();
+    assert_eq!(errors, vec![Inconsistency::StaleHash { recorded: actual, actual: state.compute_hash() }]);
+}
+
+#[test]
+fn game_keeps_its_hashes_current() {
+    let mut game = Game::from_fen(GameState::FEN_CLASSIC).unwrap();
+    assert!(game.play_san("e4"));
+    assert_eq!(game.validate(), Ok(()));
+    assert_eq!(game.hashes().len(), 2);
+}
@@ -370,15 +370,15 @@ pub fn render_bitboard(position: Bitboard, attacks: Bitboard, blockers: Bitboard
             let mut back = style::Color::Rgb{r: 0x85, g: 0x5E, b: 0x42};
             let mut symbol = " ";
 
-            if (1 << index) & blockers.0 != 0 {
+            if blockers.is_occupied(index) {
                 front = style::Color::Rgb{r: 0xa6, g: 0x48, b: 0x31};
                 symbol = "#"
             }
-            if (1 << index) & position.0 != 0 {
+            if position.is_occupied(index) {
                 front = style::Color::Rgb{r: 0x21, g: 0x88, b: 0x96};
                 symbol = "#"
             }
-            if (1 << index) & attacks.0 != 0 {
+            if attacks.is_occupied(index) {
                 back = style::Color::Rgb{r: 0x5b, g: 0x3a, b: 0x21}
             }
 
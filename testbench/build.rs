@@ -0,0 +1,18 @@
+// Builds qperft, the C reference move generator at the repository root, into a static library for the differential perft.
+// Only with the qperft feature, without it there is nothing to build.
+#[cfg(feature = "qperft")]
+fn main() {
+    println!("cargo:rerun-if-changed=reference/qperft.c");
+    println!("cargo:rerun-if-changed=../c.c");
+
+    cc::Build::new()
+        .file("reference/qperft.c")
+        .opt_level(3)
+        // qperft is older C with unused variables everywhere, and it reads its Zobrist keys through casted char pointers
+        .warnings(false)
+        .flag_if_supported("-fno-strict-aliasing")
+        .compile("qperft");
+}
+
+#[cfg(not(feature = "qperft"))]
+fn main() {}
@@ -0,0 +1,157 @@
+/* Entry points into qperft (c.c at the repository root), the reference move generator of the differential perft.
+   The reference is included unchanged, only its main and the globals that clash with libc are renamed. */
+#include <string.h>
+
+#define main qperft_main
+#define accept qperft_accept
+#include "../../c.c"
+#undef main
+#undef accept
+
+
+/* Loads a position into the global state of qperft, returns the color to move or a negative ReadFEN error */
+static int qperft_setup(const char *fen)
+{
+    int color;
+
+    msp = 0;
+    epSqr = 0;
+    HashFlag = 0;
+    noUnder = 0;
+    Split = 0;
+
+    delta_init();
+    piece_init();
+    board_init(board);
+    color = ReadFEN((char *) fen);
+    if(color < 0) return color;
+    setup();
+    return color;
+}
+
+/* The last move of the root as qperft's main makes it, but without a contact check it points at our own King instead
+   of square 0. qperft reads the piece code of that square, and the guard there indexes past the end of the piece list. */
+static int qperft_lastply(int color)
+{
+    int x = checker(color);
+    return ((epSqr^16)<<24) + (x ? x : pos[color-WHITE]);
+}
+
+/* The nodes below the side to move */
+static unsigned long long int qperft_count(int color, int lastply, int depth)
+{
+    count = 0;
+    if(depth == 0) return 1;
+    if(depth == 1) leaf_perft(color, lastply, depth, 1);
+    else perft(color, lastply, depth, 1);
+    return count;
+}
+
+/* Perft of the FEN, returns -1 if qperft cannot read it */
+long long int qperft_perft(const char *fen, int depth)
+{
+    int color = qperft_setup(fen);
+    if(color < 0) return -1;
+    return qperft_count(color, qperft_lastply(color), depth);
+}
+
+/* The legal root moves with the perft below each of them, in the order qperft generates them.
+   A move is stored as from | to << 8 | promotion << 16, with 0x88 squares and the qperft piece kind of the
+   promotion, 0 if there is none. Returns the number of moves, or a negative ReadFEN error.
+   The make and unmake are those of perft, without the hashing and the bulk counting. */
+int qperft_divide(const char *fen, int depth, int *moves, unsigned long long int *counts, int capacity)
+{
+    int color = qperft_setup(fen), lastply, first_move, last_move, n = 0, i;
+    int from, to, capt, mode, piece, oldpiece = 0, victim, j = 0, h = 0, SavRights;
+
+    if(color < 0) return color;
+    if(depth == 0) return 0;
+    lastply = qperft_lastply(color);
+
+    first_move = msp;
+    move_gen(color, lastply, 1);
+    last_move = msp;
+
+    for(i = first_move; i < last_move; i++)
+    {
+        from = (stack[i]>>8)&0xFF;
+        to = capt = stack[i]&0xFF;
+        mode = (unsigned int)stack[i]>>24;
+        piece = board[from];
+        SavRights = CasRights;
+
+        if(mode >= 0xA0)
+        {   if(mode == 0xA0) capt ^= 0x10; else
+            if(mode == 0xA1)
+            {   /* promotion, to a Queen first */
+                oldpiece = piece; pos[piece-WHITE] = 0;
+                piece = --FirstSlider[color]+WHITE;
+                kind[piece-WHITE] = QUEEN;
+                code[piece-WHITE] = C_QUEEN;
+                Zob[piece-WHITE]  = Keys + 128*QUEEN + (color&BLACK)/8 - 0x22;
+                pos[piece-WHITE]  = from;
+            } else
+            {   /* castling, the king may not pass an attacked square */
+                j = mode - 0xB0 + from;
+                h = from+to >> 1;
+                if(capturable(color^COLOR, h)) continue;
+                board[h] = board[j];
+                board[j] = DUMMY;
+                pos[board[h]-WHITE] = h;
+            }
+        }
+
+        victim = board[capt];
+        CasRights |= cstl[piece-WHITE] | cstl[victim-WHITE];
+
+minor:
+        board[capt] = board[from] = DUMMY;
+        board[to] = piece;
+        pos[piece-WHITE] = to;
+        pos[victim-WHITE] = 0;
+
+        if((piece != color && mode != 0xA0) || !capturable(color^COLOR, pos[color-WHITE]))
+        {   if(n < capacity)
+            {   moves[n] = from | to << 8 | (mode == 0xA1 ? kind[piece-WHITE] : 0) << 16;
+                counts[n] = qperft_count(COLOR-color, stack[i], depth-1);
+            }
+            n++;
+        }
+
+        pos[piece-WHITE] = from;
+        pos[victim-WHITE] = capt;
+        board[to] = DUMMY;
+        board[capt] = victim;
+        board[from] = piece;
+
+        if(mode == 0xA1)
+        {   if(--kind[piece-WHITE] >= KNIGHT)
+            {   /* the under promotions */
+                if(kind[piece-WHITE] == KNIGHT)
+                {   FirstSlider[color]++;
+                    piece = ++LastKnight[color]+WHITE;
+                    pos[piece-WHITE]  = from;
+                    kind[piece-WHITE] = KNIGHT;
+                    Zob[piece-WHITE]  = Keys + 128*KNIGHT + (color&BLACK)/8 - 0x22;
+                }
+                code[piece-WHITE] = capts[kind[piece-WHITE]];
+                goto minor;
+            } else
+            {   kind[piece-WHITE] = QUEEN;
+                piece = oldpiece; LastKnight[color]--;
+                pos[piece-WHITE] = from;
+                board[from] = piece;
+            }
+        } else
+        if(mode > 0xA1)
+        {   board[j] = board[h];
+            board[h] = DUMMY;
+            pos[board[j]-WHITE] = j;
+        }
+
+        CasRights = SavRights;
+    }
+
+    msp = first_move;
+    return n;
+}
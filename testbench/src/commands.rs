@@ -0,0 +1,174 @@
+use crate::*;
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+
+pub const USAGE: &str = "\
+Usage: testbench perft <fen|startpos> <depth> [--divide] [--parallel] [--hash MB] [--compare]
+        Counts the leaf nodes of the legal move tree, with --divide the count below every root move as well.
+        --parallel searches the root moves on all cores, --hash caches subtree counts in a table of MB megabytes
+        and --compare reruns the plain serial count, which has to match, and reports the speedup
+       testbench perft-tables [--max-nodes N]
+        Checks every field of the perft statistics tables, for the entries of up to N nodes if given
+       testbench differential [--positions N] [--depth D] [--seed S]
+        Compares the perft of core against qperft (c.c) on the curated positions and N random ones, 2000 by default,
+        at every depth up to D, 3 by default. The first difference is followed down to the moves that differ.
+        Needs qperft built in, with cargo run -p testbench --features qperft
+
+Any other arguments run the benchmarks, see cargo bench -- --help. The move generation benchmarks report moves per second
+for every corpus position, per category (opening, middlegame, endgame, check, promotion) and in total. Compare them
+against a saved run with cargo bench -- movegen --save-baseline NAME, then cargo bench -- movegen --baseline NAME
+Exits with 1 if a check fails and 2 on bad arguments.
+";
+
+pub fn perft(arguments: &[String]) -> ExitCode {
+    let mut positional = Vec::new();
+    let mut is_divide = false;
+    let mut is_parallel = false;
+    let mut is_compare = false;
+    let mut hash_megabytes = None;
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--divide" => is_divide = true,
+            "--parallel" => is_parallel = true,
+            "--compare" => is_compare = true,
+            "--hash" => match arguments.next().map(|value| value.parse::<usize>()) {
+                Some(Ok(megabytes)) if megabytes > 0 => hash_megabytes = Some(megabytes),
+                _ => return usage_error("--hash needs a size in megabytes"),
+            },
+            flag if flag.starts_with("--") => return usage_error(&format!("unknown option '{}'", flag)),
+            _ => positional.push(argument),
+        }
+    }
+    let [fen, depth] = positional[..] else {
+        return usage_error("perft needs a FEN and a depth");
+    };
+
+    let fen = if fen == "startpos" { GameState::FEN_CLASSIC } else { fen.as_str() };
+    let Some(mut state) = GameState::from_fen(fen) else {
+        return usage_error(&format!("invalid FEN '{}'", fen));
+    };
+    let Ok(depth) = depth.parse::<usize>() else {
+        return usage_error(&format!("invalid depth '{}'", depth));
+    };
+
+    let table = hash_megabytes.map(core::perft::PerftTable::new);
+    let start = Instant::now();
+    let moves = if depth == 0 {
+        Vec::new()
+    } else if is_parallel {
+        core::perft::divide_parallel(&state, depth, table.as_ref())
+    } else {
+        state.legal_moves().into_iter()
+            .map(|movement| {
+                let undo = state.make_move(movement);
+                let nodes = match &table {
+                    Some(table) => core::perft::perft_hashed(&mut state, depth - 1, table),
+                    None => core::perft::perft(&mut state, depth - 1),
+                };
+                state.unmake_move(movement, &undo);
+                (movement, nodes)
+            })
+            .collect()
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+
+    // Sorted so the output can be diffed against other engines
+    let mut moves: Vec<(String, u64)> = moves.into_iter()
+        .map(|(movement, nodes)| (san::move_to_uci(movement), nodes))
+        .collect();
+    moves.sort();
+    if is_divide {
+        for (movement, nodes) in &moves {
+            println!("{}: {}", movement, nodes);
+        }
+        println!();
+    }
+    let nodes = if depth == 0 { 1 } else { moves.iter().map(|(_, nodes)| nodes).sum() };
+
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {:.3} s, {:.0} nps", elapsed, nodes as f64 / elapsed.max(f64::EPSILON));
+    if let Some(table) = &table {
+        println!("Hash: {} hits of {} probes, {:.1}%", table.hits(), table.probes(), 100.0 * table.hits() as f64 / table.probes().max(1) as f64);
+    }
+
+    // The plain serial count is the reference the other modes have to match exactly
+    if is_compare {
+        let start = Instant::now();
+        let serial = core::perft::divide(&mut state, depth);
+        let serial_elapsed = start.elapsed().as_secs_f64();
+        let mut serial: Vec<(String, u64)> = serial.into_iter()
+            .map(|(movement, nodes)| (san::move_to_uci(movement), nodes))
+            .collect();
+        serial.sort();
+
+        println!("Serial: {:.3} s, speedup {:.2}x", serial_elapsed, serial_elapsed / elapsed.max(f64::EPSILON));
+        if serial != moves {
+            for (movement, nodes) in &serial {
+                match moves.iter().find(|(other, _)| other == movement) {
+                    Some((_, found)) if found == nodes => (),
+                    Some((_, found)) => println!("{}: serial {}, found {}", movement, nodes, found),
+                    None => println!("{}: serial {}, missing", movement, nodes),
+                }
+            }
+            println!("Mismatch against the serial count");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn perft_tables(arguments: &[String]) -> ExitCode {
+    let max_nodes = match arguments {
+        [] => usize::MAX,
+        [flag, value] if flag == "--max-nodes" => match value.parse() {
+            Ok(max_nodes) => max_nodes,
+            Err(_) => return usage_error(&format!("invalid value '{}' for --max-nodes", value)),
+        },
+        _ => return usage_error("perft-tables only takes --max-nodes"),
+    };
+    if crate::perft::check_perft_tables(max_nodes) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(feature = "qperft")]
+pub fn differential(arguments: &[String]) -> ExitCode {
+    let mut positions = 2000;
+    let mut depth = 3;
+    let mut seed = 0;
+    for pair in arguments.chunks(2) {
+        let [flag, value] = pair else {
+            return usage_error(&format!("missing value for '{}'", pair[0]));
+        };
+        let is_valid = match flag.as_str() {
+            "--positions" => value.parse().map(|value| positions = value).is_ok(),
+            "--depth" => value.parse().map(|value| depth = value).is_ok(),
+            "--seed" => value.parse().map(|value| seed = value).is_ok(),
+            _ => return usage_error(&format!("unknown option '{}'", flag)),
+        };
+        if !is_valid {
+            return usage_error(&format!("invalid value '{}' for {}", value, flag));
+        }
+    }
+
+    if crate::reference::differential(positions, depth, seed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(not(feature = "qperft"))]
+pub fn differential(_arguments: &[String]) -> ExitCode {
+    usage_error("differential needs qperft, rebuild with --features qperft")
+}
+
+fn usage_error(error: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", error, USAGE);
+    ExitCode::from(2)
+}
@@ -1,8 +1,7 @@
-pub use core::*;
+use core::*;
 pub use criterion::{
     Criterion,
     criterion_group, 
-    criterion_main, 
     black_box, 
 };
 /*
@@ -12,9 +11,35 @@ pub use pretty_assertions::{
 };
 */
 
+mod commands;
 mod fen;
+mod movegen;
 mod perft;
+#[cfg(feature = "qperft")]
+mod reference;
+mod sliders;
 
 
-criterion_main!(perft::benches);
-//criterion_main!(fen::benches);
+// The commands take the first argument, anything else goes to criterion like criterion_main! would
+fn main() -> std::process::ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments.first().map(String::as_str) {
+        Some("perft") => return commands::perft(&arguments[1..]),
+        Some("perft-tables") => return commands::perft_tables(&arguments[1..]),
+        Some("differential") => return commands::differential(&arguments[1..]),
+        Some("help") => {
+            print!("{}", commands::USAGE);
+            return std::process::ExitCode::SUCCESS;
+        }
+        _ => (),
+    }
+
+    perft::benches();
+    sliders::benches();
+    movegen::benches();
+    //fen::benches();
+    Criterion::default()
+        .configure_from_args()
+        .final_summary();
+    std::process::ExitCode::SUCCESS
+}
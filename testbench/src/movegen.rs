@@ -0,0 +1,206 @@
+use crate::*;
+
+use criterion::{
+    BenchmarkId,
+    Throughput,
+};
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Category {
+    Opening,
+    // Middlegames with most of the sliders still on the board
+    Middlegame,
+    Endgame,
+    // The side to move is in check
+    Check,
+    // The side to move can promote
+    Promotion,
+}
+impl Category {
+    const ALL: [Self; 5] = [
+        Self::Opening,
+        Self::Middlegame,
+        Self::Endgame,
+        Self::Check,
+        Self::Promotion,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Opening => "opening",
+            Self::Middlegame => "middlegame",
+            Self::Endgame => "endgame",
+            Self::Check => "check",
+            Self::Promotion => "promotion",
+        }
+    }
+}
+
+
+// The per position benchmarks are reported under the label, so their results stay comparable when the corpus changes
+const CORPUS: &[(Category, &str, &str)] = &[
+    (Category::Opening, "start", GameState::FEN_CLASSIC),
+    (Category::Opening, "open-game", "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"),
+    (Category::Opening, "sicilian", "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+    (Category::Opening, "ruy-lopez", "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"),
+    (Category::Opening, "queens-gambit", "rnbqkb1r/ppp2ppp/4pn2/3p4/2PP4/2N5/PP2PPPP/R1BQKBNR w KQkq - 2 4"),
+    (Category::Opening, "kings-indian", "rnbq1rk1/ppp1ppbp/3p1np1/8/2PPP3/2N2N2/PP3PPP/R1BQKB1R w KQ - 0 6"),
+    (Category::Opening, "french", "rnbqkbnr/ppp2ppp/4p3/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 0 3"),
+    (Category::Opening, "caro-kann", "rnbqkbnr/pp2pppp/2p5/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 0 3"),
+    (Category::Opening, "italian", "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"),
+    (Category::Opening, "english", "rnbqkbnr/pppp1ppp/8/4p3/2P5/2N5/PP1PPPPP/R1BQKBNR b KQkq - 1 2"),
+    (Category::Opening, "en-passant", "rnbqkbnr/pp1ppppp/8/2pP4/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 3"),
+
+    (Category::Middlegame, "kiwipete", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+    (Category::Middlegame, "mirrored-pins", "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"),
+    (Category::Middlegame, "queens-gambit-declined", "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 3 9"),
+    (Category::Middlegame, "sicilian-bb7", "r2q1rk1/1b1nbppp/p2ppn2/1p6/3NP3/1BN1B3/PPPQ1PPP/R4RK1 w - - 0 11"),
+    (Category::Middlegame, "symmetric-fianchetto", "2rq1rk1/pb1nbppp/1p2pn2/2pp4/2PP4/1PNBPN2/PB1Q1PPP/2R2RK1 w - - 0 12"),
+    (Category::Middlegame, "sicilian-qc7", "r1b2rk1/2q1bppp/p1nppn2/1p6/3NP3/1BN1B3/PPP1QPPP/R4RK1 w - - 0 11"),
+    (Category::Middlegame, "symmetric", "3r1rk1/pp1q1ppp/2nbbn2/3p4/3P4/2NBBN2/PP1Q1PPP/3R1RK1 w - - 0 14"),
+    (Category::Middlegame, "isolated-pawn", "r4rk1/pp1bqppp/2n1pn2/2bp4/2B5/2NPBN2/PPQ2PPP/R4RK1 w - - 0 12"),
+    (Category::Middlegame, "closed-ruy", "1r2r1k1/2qbbppp/p2p1n2/1p1Pp3/4P3/2P2N1P/PPB2PP1/R1BQR1K1 w - - 1 17"),
+    (Category::Middlegame, "opposite-castling", "2kr3r/pbpq1ppp/1p2pn2/3p4/1bPP4/2NBPN2/PPQ2PPP/R1B2RK1 w - - 4 10"),
+    (Category::Middlegame, "benoni", "r3r1k1/1bq2pbp/p2p1np1/1pnP4/4P3/2N1BB1P/PPQ2PP1/R3R1K1 w - - 6 17"),
+
+    (Category::Endgame, "rook-and-pawns", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"),
+    (Category::Endgame, "bishop-vs-pawn", "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1"),
+    (Category::Endgame, "king-and-pawn", "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1"),
+    (Category::Endgame, "king-and-rook", "8/8/8/4k3/8/8/8/R5K1 w - - 0 1"),
+    (Category::Endgame, "rook-on-the-seventh", "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1"),
+    (Category::Endgame, "rook-behind", "4k3/8/8/3KP3/8/8/r7/7R b - - 0 1"),
+    (Category::Endgame, "queen-vs-rook", "8/8/8/3k4/8/3r4/8/3QK3 w - - 0 1"),
+    (Category::Endgame, "bishops", "8/5k2/3b4/8/3B4/4K3/8/8 w - - 0 1"),
+    (Category::Endgame, "knights", "8/5k2/3n4/8/3N4/4K3/5P2/8 w - - 0 1"),
+    (Category::Endgame, "pawn-race", "8/p7/8/8/8/8/7P/k6K w - - 0 1"),
+    (Category::Endgame, "blocked-pawns", "8/k7/3p4/p2P1p2/P2P1P2/8/8/K7 w - - 0 1"),
+    (Category::Endgame, "rook-kingside-pawns", "8/5pk1/6p1/7p/7P/6P1/r4PK1/R7 w - - 0 1"),
+
+    (Category::Check, "bishop", "rnbqkbnr/ppp2ppp/8/1B1pp3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 3"),
+    (Category::Check, "rook-contact", "4k3/8/8/8/8/8/4r3/4K3 w - - 0 1"),
+    (Category::Check, "kiwipete", "r3k2r/p1pp1pb1/bn2Qnp1/2qPN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQkq - 3 2"),
+    (Category::Check, "knight", "rnbqkb1r/pppppppp/8/8/8/3n4/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+    (Category::Check, "double-check", "4k3/8/8/8/8/5n2/8/4K2r w - - 0 1"),
+    (Category::Check, "pawn", "4k3/8/8/8/8/8/3p4/4K3 w - - 0 1"),
+    (Category::Check, "en-passant-evasion", "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1"),
+    (Category::Check, "bishop-b4", "rnbqk1nr/pppp1ppp/8/4p3/1b1P4/8/PPP1PPPP/RNBQKBNR w KQkq - 1 3"),
+    (Category::Check, "queen-file", "rnb1kbnr/pppp1ppp/4q3/8/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 4"),
+    (Category::Check, "queen-contact", "7k/8/8/8/8/8/1q6/K7 w - - 0 1"),
+
+    (Category::Promotion, "capture-or-push", "2r1k3/1P6/8/8/8/8/p7/4K2R w K - 0 1"),
+    (Category::Promotion, "black-to-promote", "4k2r/P7/8/8/8/8/6p1/R3K3 b Qk - 0 1"),
+    (Category::Promotion, "capture-on-c8", "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"),
+    (Category::Promotion, "underpromotions-black", "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+    (Category::Promotion, "underpromotions-white", "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1"),
+    (Category::Promotion, "three-pawns-white", "8/PPP4k/8/8/8/8/4Kppp/8 w - - 0 1"),
+    (Category::Promotion, "three-pawns-black", "8/PPP4k/8/8/8/8/4Kppp/8 b - - 0 1"),
+    (Category::Promotion, "capture-the-rook", "r3k3/1P6/8/8/8/8/8/4K3 w q - 0 1"),
+    (Category::Promotion, "capture-the-queen", "3qk3/2P5/8/8/8/8/8/4K3 w - - 0 1"),
+    (Category::Promotion, "capture-on-h1", "4k3/1P6/8/8/8/8/6p1/4K2R b K - 0 1"),
+    (Category::Promotion, "both-sides", "1r2k3/P1P5/8/8/8/8/6pp/4K1R1 w - - 0 1"),
+];
+
+
+// The labelled positions of a category, or of the whole corpus, checked to belong to their category
+fn positions(category: Option<Category>) -> Vec<(&'static str, GameState)> {
+    CORPUS.iter()
+        .filter(|(other, _, _)| category.is_none_or(|category| category == *other))
+        .map(|&(category, label, fen)| {
+            let state = GameState::from_fen(fen).expect("Fen parsing failed. Use fen tests");
+            let moves = state.legal_moves();
+            assert!(!moves.is_empty(), "{} position '{}' has no moves", category.name(), fen);
+            match category {
+                Category::Check => assert!(state.is_king_checked(state.current), "'{}' is not in check", fen),
+                Category::Promotion => assert!(moves.iter().any(|movement| movement.is_promotion()), "'{}' can not promote", fen),
+                _ => (),
+            }
+            (label, state)
+        })
+        .collect()
+}
+
+// The categories followed by the whole corpus, with the names their totals are reported under
+fn totals() -> impl Iterator<Item = (&'static str, Vec<GameState>)> {
+    Category::ALL.iter()
+        .map(|&category| Some(category))
+        .chain(std::iter::once(None))
+        .map(|category| {
+            let states = positions(category).into_iter().map(|(_, state)| state).collect();
+            (category.map_or("total", Category::name), states)
+        })
+}
+
+
+// The throughput of every position is the number of moves it generates, criterion reports it as moves per second
+fn generate_moves_per_position(criterion: &mut Criterion) {
+    for category in Category::ALL {
+        let mut group = criterion.benchmark_group(format!("movegen {}", category.name()));
+        for (label, state) in positions(Some(category)) {
+            group.throughput(Throughput::Elements(state.pseudo_legal_moves().len() as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(label), &state, |bencher, state| {
+                let mut state = state.clone();
+                let color = state.current;
+                bencher.iter(| | state.generate_moves(black_box(color)));
+            });
+        }
+        group.finish();
+    }
+}
+
+fn generate_moves(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("movegen per category");
+    for (name, mut states) in totals() {
+        let moves: usize = states.iter().map(|state| state.pseudo_legal_moves().len()).sum();
+        group.throughput(Throughput::Elements(moves as u64));
+        group.bench_function(name, |bencher| bencher.iter(| | {
+            for state in states.iter_mut() {
+                let color = state.current;
+                state.generate_moves(black_box(color));
+            }
+        }));
+    }
+    group.finish();
+}
+
+// Generation with the filter for moves that leave the king in check
+fn legal_moves(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("movegen legal per category");
+    for (name, states) in totals() {
+        let moves: usize = states.iter().map(|state| state.legal_moves().len()).sum();
+        group.throughput(Throughput::Elements(moves as u64));
+        group.bench_function(name, |bencher| bencher.iter(| | {
+            states.iter().map(|state| black_box(state).legal_moves().len()).sum::<usize>()
+        }));
+    }
+    group.finish();
+}
+
+// Check, defence and pin masks of the side to move, the throughput is in positions
+fn generate_king_masks(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("movegen king masks per category");
+    for (name, states) in totals() {
+        group.throughput(Throughput::Elements(states.len() as u64));
+        group.bench_function(name, |bencher| bencher.iter(| | {
+            for state in &states {
+                black_box(state.generate_king_masks(black_box(state.current)));
+            }
+        }));
+    }
+    group.finish();
+}
+
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .plotting_backend(criterion::PlottingBackend::Plotters)
+        .sample_size(20)
+        .warm_up_time(std::time::Duration::from_secs(1))
+        .measurement_time(std::time::Duration::from_secs(2));
+    targets =
+        generate_moves_per_position,
+        generate_moves,
+        legal_moves,
+        generate_king_masks,
+);
@@ -41,210 +41,194 @@ impl std::ops::Add for MoveCountInfo {
 
 
 // https://www.chessprogramming.org/Perft_Results
-fn get_move_count(state: GameState, depth: usize) -> MoveCountInfo {
-    /*for (index, tile) in state.board.iter().enumerate() {
-        if index % 8 == 0 {
-            println!("");
-        }
-        print!(" {} ", if tile.is_occupied() { "#" } else {"."})
+// The statistics describe the moves of the last ply, the ones leading to the counted leaves
+fn get_move_count(state: &mut GameState, depth: usize) -> MoveCountInfo {
+    if depth == 0 {
+        return MoveCountInfo {
+            nodes: 1,
+            ..Default::default()
+        };
     }
-    println!("");*/
 
     let mut info = MoveCountInfo::default();
-
-    let pieces = match state.current {
-        Color::White => &state.white.pieces,
-        Color::Black => &state.black.pieces,
-    };
-
-    if depth > 0 {
-        for (_, _, moves) in pieces {
-            for movement in moves {
-                if movement.is_capture() {
-                    /*if let (PieceType::King, _) = state.board[movement.get_target() as usize].get_piece() {
-                        panic!("King capture\n{}", state.to_fen());
-                    }*/
-                    if state.current == state.board[movement.get_target() as usize].get_color() {
-                        panic!("Self capture\n{}", state.to_fen());
-                    }
-                    info.captures += 1;
-                }
-                if movement.is_promotion() {
-                    info.promotions += 1;
-                }
-
-                let mut state = state.clone();
-                state.play_move_unchecked(*movement);
-                state.current = !state.current;
-                state.generate_moves(state.current);
-                //state.validate_moves();
-                
-                if state.is_king_checked(state.current) {
-                    info.checks += 1;
-                    if state.is_king_checkmated(state.current) {
-                        info.checkmates += 1;
-                    }
-                }
-
-                if !state.is_king_checked(!state.current) {
-                    info = info + get_move_count(state, depth - 1);
-                }
-            }
-        }
-        info
-    } else {
-        MoveCountInfo {
-            nodes: 1,
-            ..Default::default()
-        }
+    for movement in state.legal_moves() {
+        let undo = state.make_move(movement);
+        info = info + if depth == 1 {
+            get_leaf_info(state, movement)
+        } else {
+            get_move_count(state, depth - 1)
+        };
+        state.unmake_move(movement, &undo);
     }
+    info
 }
 
-
-
-
-macro_rules! create_perft_testbench {
-    ($ply:expr, $name:ident, $($info:tt)*) => {
-        fn $name(criterion: &mut Criterion) {
-            let mut state = GameState::from_fen(GameState::FEN_CLASSIC).expect("Fen parsing failed. Use fen tests");
-            state.generate_moves(state.current);
-
-            criterion.bench_function(concat!("perft for ply ", stringify!($ply)), |bencher| bencher.iter(| | {
-                assert_eq!(get_move_count(state.clone(), $ply), $($info)*);
-            }));
-        }
+// The statistics of a single move of the last ply, given the state after it
+fn get_leaf_info(state: &GameState, movement: Move) -> MoveCountInfo {
+    let move_type = movement.get_type();
+    let is_castle = matches!(move_type, MoveType::KingCastle | MoveType::QueenCastle);
+    let checkers = state.checkers(state.current).0;
+
+    // A check is discovered if the only checker is not the piece that moved, which is the rook when castling
+    let target = movement.get_target();
+    let moved = match move_type {
+        MoveType::KingCastle => target - 1,
+        MoveType::QueenCastle => target + 1,
+        _ => target,
     };
-}
-
-
+    let is_discovery_check = checkers.count_ones() == 1 && checkers & (1 << moved) == 0;
 
+    MoveCountInfo {
+        nodes: 1,
+        captures: movement.is_capture() as usize,
+        en_passants: (move_type == MoveType::CaptureEnPassant) as usize,
+        castles: is_castle as usize,
+        promotions: movement.is_promotion() as usize,
+        checks: (checkers != 0) as usize,
+        discovery_checks: is_discovery_check as usize,
+        double_checks: (checkers.count_ones() > 1) as usize,
+        checkmates: (checkers != 0 && state.legal_moves().is_empty()) as usize,
+    }
+}
 
-fn generate_pseudo_legal_moves(criterion: &mut Criterion) {
-    let mut state = GameState::from_fen(GameState::FEN_CLASSIC).expect("Fen parsing failed. Use fen tests");
 
-    criterion.bench_function("generate pseudo legal moves", |bencher| bencher.iter(| | {
-        state.generate_moves(Color::White);
-        state.generate_moves(Color::Black);
-    }));
+// One row per depth starting at 1, with the fields of MoveCountInfo in order:
+// nodes, captures, en passants, castles, promotions, checks, discovery checks, double checks, checkmates
+struct PerftTable {
+    name: &'static str,
+    fen: &'static str,
+    rows: &'static [[usize; 9]],
 }
-fn generate_legal_moves(criterion: &mut Criterion) {
-    let mut state = GameState::from_fen(GameState::FEN_CLASSIC).expect("Fen parsing failed. Use fen tests");
-
-    criterion.bench_function("generate legal moves", |bencher| bencher.iter(| | {
-        state.generate_moves(Color::White);
-        state.generate_moves(Color::Black);
-        //state.validate_moves();
-    }));
+impl From<[usize; 9]> for MoveCountInfo {
+    fn from(row: [usize; 9]) -> Self {
+        let [nodes, captures, en_passants, castles, promotions, checks, discovery_checks, double_checks, checkmates] = row;
+        Self { nodes, captures, en_passants, castles, promotions, checks, discovery_checks, double_checks, checkmates }
+    }
 }
 
-fn move_validation(criterion: &mut Criterion) {
-    let mut state = GameState::from_fen(GameState::FEN_CLASSIC).expect("Fen parsing failed. Use fen tests");
-    state.generate_moves(Color::White);
-    state.generate_moves(Color::Black);
-
-    criterion.bench_function("move validation", |bencher| bencher.iter(| | {
-        let mut state = state.clone();
-        //state.validate_moves();
-    }));
+// The numbers of chessprogramming.org where it lists them. It only lists the node counts for positions 5, 6 and the
+// promotion position and leaves the discovered and double checks of position 4 out, the rest of those rows comes from
+// this counter, which agrees with every number that is listed.
+const PERFT_TABLES: &[PerftTable] = &[
+    PerftTable {
+        name: "initial",
+        fen: GameState::FEN_CLASSIC,
+        rows: &[
+            [20, 0, 0, 0, 0, 0, 0, 0, 0],
+            [400, 0, 0, 0, 0, 0, 0, 0, 0],
+            [8902, 34, 0, 0, 0, 12, 0, 0, 0],
+            [197281, 1576, 0, 0, 0, 469, 0, 0, 8],
+            [4865609, 82719, 258, 0, 0, 27351, 6, 0, 347],
+            [119060324, 2812008, 5248, 0, 0, 809099, 329, 46, 10828],
+        ],
+    },
+    PerftTable {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        rows: &[
+            [48, 8, 0, 2, 0, 0, 0, 0, 0],
+            [2039, 351, 1, 91, 0, 3, 0, 0, 0],
+            [97862, 17102, 45, 3162, 0, 993, 0, 0, 1],
+            [4085603, 757163, 1929, 128013, 15172, 25523, 42, 6, 43],
+        ],
+    },
+    PerftTable {
+        name: "position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        rows: &[
+            [14, 1, 0, 0, 0, 2, 0, 0, 0],
+            [191, 14, 0, 0, 0, 10, 0, 0, 0],
+            [2812, 209, 2, 0, 0, 267, 3, 0, 0],
+            [43238, 3348, 123, 0, 0, 1680, 106, 0, 17],
+            [674624, 52051, 1165, 0, 0, 52950, 1292, 3, 0],
+            [11030083, 940350, 33325, 0, 7552, 452473, 26067, 0, 2733],
+        ],
+    },
+    PerftTable {
+        name: "position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        rows: &[
+            [6, 0, 0, 0, 0, 0, 0, 0, 0],
+            [264, 87, 0, 6, 48, 10, 0, 0, 0],
+            [9467, 1021, 4, 0, 120, 38, 2, 0, 22],
+            [422333, 131393, 0, 7795, 60032, 15492, 19, 0, 5],
+            [15833292, 2046173, 6512, 0, 329464, 200568, 11621, 50, 50562],
+        ],
+    },
+    PerftTable {
+        name: "position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        rows: &[
+            [44, 6, 0, 1, 4, 0, 0, 0, 0],
+            [1486, 222, 0, 0, 0, 117, 0, 0, 0],
+            [62379, 8517, 0, 1081, 5068, 1201, 0, 0, 44],
+            [2103487, 296153, 0, 0, 0, 158486, 10877, 1770, 240],
+        ],
+    },
+    PerftTable {
+        name: "position 6",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        rows: &[
+            [46, 4, 0, 0, 0, 1, 0, 0, 0],
+            [2079, 203, 0, 0, 0, 40, 0, 0, 0],
+            [89890, 9470, 0, 0, 0, 1783, 0, 0, 0],
+            [3894594, 440388, 0, 0, 0, 68985, 62, 20, 0],
+        ],
+    },
+    PerftTable {
+        name: "promotions",
+        fen: "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        rows: &[
+            [24, 11, 0, 0, 12, 3, 0, 0, 0],
+            [496, 203, 0, 0, 252, 59, 0, 0, 0],
+            [9483, 2921, 0, 0, 3224, 992, 0, 0, 0],
+            [182838, 50647, 0, 0, 65620, 20154, 64, 16, 0],
+            [3605103, 754747, 0, 0, 955220, 399962, 2295, 1041, 134],
+        ],
+    },
+];
+
+// The positions of the tables, for other checks to start from
+#[cfg(feature = "qperft")]
+pub fn perft_table_fens() -> impl Iterator<Item = &'static str> {
+    PERFT_TABLES.iter().map(|table| table.fen)
 }
 
-fn generate_king_check_mask(criterion: &mut Criterion) {
-    let mut state = GameState::from_fen(GameState::FEN_CLASSIC).expect("Fen parsing failed. Use fen tests");
-    state.generate_moves(Color::White);
-    state.generate_moves(Color::Black);
-    let state = state.clone();
-
-    criterion.bench_function("generate_king_check_mask", |bencher| bencher.iter(| | {
-        state.generate_king_masks(Color::White);
-        state.generate_king_masks(Color::Black);
-    }));
+// Every table entry up to the given number of nodes, with the statistics the counter finds
+fn perft_entries(max_nodes: usize) -> impl Iterator<Item = (&'static PerftTable, usize, MoveCountInfo)> {
+    PERFT_TABLES.iter()
+        .flat_map(|table| table.rows.iter().enumerate().map(move |(index, &row)| (table, index + 1, MoveCountInfo::from(row))))
+        .filter(move |(_, _, expected)| expected.nodes <= max_nodes)
 }
 
-
-create_perft_testbench!(0, perft_ply_0,
-    MoveCountInfo {
-        nodes: 1,
-        captures: 0,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 0,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 0,
-    }
-);
-
-create_perft_testbench!(1, perft_ply_1,
-    MoveCountInfo {
-        nodes: 20,
-        captures: 0,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 0,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 0,
-    }
-);
-
-
-create_perft_testbench!(2, perft_ply_2,
-    MoveCountInfo {
-        nodes: 400,
-        captures: 0,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 0,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 0,
-    }
-);
-
-create_perft_testbench!(3, perft_ply_3,
-    MoveCountInfo {
-        nodes: 8902,
-        captures: 34,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 12,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 0,
+// Compares the tables up to the given number of nodes per entry and prints every entry, returns whether all of them match
+pub fn check_perft_tables(max_nodes: usize) -> bool {
+    let mut is_valid = true;
+    for (table, depth, expected) in perft_entries(max_nodes) {
+        let mut state = GameState::from_fen(table.fen).expect("Fen parsing failed. Use fen tests");
+        let found = get_move_count(&mut state, depth);
+        if found == expected {
+            println!("{} ply {}: {} nodes", table.name, depth, found.nodes);
+        } else {
+            println!("{} ply {}: expected\n{:#?}\nfound\n{:#?}", table.name, depth, expected, found);
+            is_valid = false;
+        }
     }
-);
+    is_valid
+}
 
-create_perft_testbench!(4, perft_ply_4,
-    MoveCountInfo {
-        nodes: 197281,
-        captures: 1576,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 469,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 8,
-    }
-);
 
-create_perft_testbench!(5, perft_ply_5,
-    MoveCountInfo {
-        nodes: 197281,
-        captures: 1576,
-        en_passants: 0,
-        castles: 0,
-        promotions: 0,
-        checks: 469,
-        discovery_checks: 0,
-        double_checks: 0,
-        checkmates: 8,
+// The shallow entries, checked once against the table before only the count is timed.
+// Every move is taken back, so the iterations share one state.
+fn perft_statistics(criterion: &mut Criterion) {
+    for (table, depth, expected) in perft_entries(100_000) {
+        let mut state = GameState::from_fen(table.fen).expect("Fen parsing failed. Use fen tests");
+        assert_eq!(get_move_count(&mut state, depth), expected, "{} ply {}", table.name, depth);
+        criterion.bench_function(&format!("perft statistics {} ply {}", table.name, depth), |bencher| bencher.iter(| | {
+            get_move_count(black_box(&mut state), depth)
+        }));
     }
-);
+}
 
 
 fn pext(criterion: &mut Criterion) {
@@ -314,17 +298,8 @@ criterion_group!(
         //.measurement_time(std::time::Duration::from_secs(5))
         ;
     targets = 
-        //generate_pseudo_legal_moves,
-        //generate_king_check_mask,
-        //generate_legal_moves,
         pext,
         board,
         hashmap,
-        //move_validation,
-        //perft_ply_0,
-        //perft_ply_1,
-        //perft_ply_2,
-        //perft_ply_3,
-        //perft_ply_4,
-        //perft_ply_5,
+        perft_statistics,
 );
@@ -0,0 +1,194 @@
+use crate::*;
+
+use nanorand::{
+    Rng,
+    WyRand,
+};
+use std::ffi::CString;
+use std::os::raw::{
+    c_char,
+    c_int,
+    c_longlong,
+    c_ulonglong,
+};
+use std::sync::Mutex;
+
+
+// qperft, H.G. Muller's move generator in c.c, built by build.rs
+extern "C" {
+    fn qperft_perft(fen: *const c_char, depth: c_int) -> c_longlong;
+    fn qperft_divide(fen: *const c_char, depth: c_int, moves: *mut c_int, counts: *mut c_ulonglong, capacity: c_int) -> c_int;
+}
+
+// qperft keeps the position in globals, so only one call can run at a time
+static QPERFT: Mutex<()> = Mutex::new(());
+
+// More than the 218 legal moves any position can have
+const MAX_MOVES: usize = 256;
+
+
+// The node count of qperft, None if it cannot read the FEN
+pub fn perft(fen: &str, depth: usize) -> Option<u64> {
+    let fen = CString::new(fen).ok()?;
+    let _lock = QPERFT.lock().unwrap();
+    let nodes = unsafe { qperft_perft(fen.as_ptr(), depth as c_int) };
+    u64::try_from(nodes).ok()
+}
+
+// The root moves of qperft in UCI notation with the count below each of them, sorted by move
+pub fn divide(fen: &str, depth: usize) -> Option<Vec<(String, u64)>> {
+    let fen = CString::new(fen).ok()?;
+    let mut moves = [0; MAX_MOVES];
+    let mut counts = [0; MAX_MOVES];
+    let length = {
+        let _lock = QPERFT.lock().unwrap();
+        unsafe { qperft_divide(fen.as_ptr(), depth as c_int, moves.as_mut_ptr(), counts.as_mut_ptr(), MAX_MOVES as c_int) }
+    };
+    let length = usize::try_from(length).ok()?.min(MAX_MOVES);
+
+    let mut result: Vec<(String, u64)> = moves[..length].iter()
+        .zip(&counts[..length])
+        .map(|(&movement, &nodes)| (qperft_move_to_uci(movement), nodes))
+        .collect();
+    result.sort();
+    Some(result)
+}
+
+// qperft moves are from | to << 8 | promotion << 16, with 0x88 squares offset by the guard band
+fn qperft_move_to_uci(movement: c_int) -> String {
+    let square = |square: c_int| {
+        let square = square - 0x22;
+        format!("{}{}", (b'a' + (square & 7) as u8) as char, (b'1' + (square >> 4) as u8) as char)
+    };
+    let mut result = square(movement & 0xff) + &square(movement >> 8 & 0xff);
+    match movement >> 16 {
+        6 => result.push('q'),
+        5 => result.push('r'),
+        4 => result.push('b'),
+        3 => result.push('n'),
+        _ => (),
+    }
+    result
+}
+
+// The root moves of core in the same form as divide
+fn core_divide(state: &mut GameState, depth: usize) -> Vec<(String, u64)> {
+    let mut result: Vec<(String, u64)> = core::perft::divide(state, depth).into_iter()
+        .map(|(movement, nodes)| (san::move_to_uci(movement), nodes))
+        .collect();
+    result.sort();
+    result
+}
+
+
+// Positions that exercise the rules perft usually gets wrong, on top of the perft table positions
+const CURATED_FENS: &[&str] = &[
+    // En passant captures that expose the king along the rank
+    "8/8/3p4/KPp4r/1R3p1k/8/4P1P1/8 w - c6 0 2",
+    "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+    // En passant out of a pawn check
+    "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+    // Castling out of, through and into check
+    "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+    "r3k2r/8/8/8/4r3/8/8/R3K2R w KQkq - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K1rR w KQkq - 0 1",
+    "1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1",
+    // Promotions with captures, checks and pins
+    "4k3/1P6/8/8/8/8/6p1/4K2R b K - 0 1",
+    "r3k3/1P6/8/8/8/8/8/4K3 w q - 0 1",
+    "3qk3/2P5/8/8/8/8/8/4K3 w - - 0 1",
+    // Double checks and pinned pieces
+    "4k3/8/8/8/1b6/8/3N4/R3K3 w Q - 0 1",
+    "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1",
+    "k7/8/8/3q4/8/8/6P1/7K w - - 0 1",
+];
+
+// Random walks from the curated and perft table positions, the same seed gives the same positions
+fn random_fens(count: usize, seed: u64) -> impl Iterator<Item = String> {
+    let starts: Vec<&str> = CURATED_FENS.iter().copied().chain(perft::perft_table_fens()).collect();
+    let mut rng = WyRand::new_seed(seed);
+    (0..count).map(move |_| {
+        let mut state = GameState::from_fen(starts[rng.generate_range(0..starts.len())]).expect("Fen parsing failed. Use fen tests");
+        for _ in 0..rng.generate_range(0..=60usize) {
+            let moves = state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            state.make_move(moves[rng.generate_range(0..moves.len())]);
+        }
+        state.to_fen()
+    })
+}
+
+
+// Follows the first root move whose counts differ down to depth 1, where the move lists themselves differ
+fn reduce(fen: &str, depth: usize) {
+    let mut state = GameState::from_fen(fen).expect("Fen parsing failed. Use fen tests");
+    for depth in (1..=depth).rev() {
+        let ours = core_divide(&mut state, depth);
+        let Some(theirs) = divide(&state.to_fen(), depth) else {
+            println!("qperft cannot read '{}'", state.to_fen());
+            return;
+        };
+
+        let only_ours: Vec<&str> = ours.iter().filter(|(movement, _)| !theirs.iter().any(|(other, _)| other == movement)).map(|(movement, _)| movement.as_str()).collect();
+        let only_theirs: Vec<&str> = theirs.iter().filter(|(movement, _)| !ours.iter().any(|(other, _)| other == movement)).map(|(movement, _)| movement.as_str()).collect();
+        if depth == 1 || !only_ours.is_empty() || !only_theirs.is_empty() {
+            println!("\nMinimal difference at depth 1:\n{}\n{}", state.to_fen(), state.to_diagram(false));
+            println!("core moves:   {}", ours.iter().map(|(movement, _)| movement.as_str()).collect::<Vec<_>>().join(" "));
+            println!("qperft moves: {}", theirs.iter().map(|(movement, _)| movement.as_str()).collect::<Vec<_>>().join(" "));
+            println!("only in core:   {}", only_ours.join(" "));
+            println!("only in qperft: {}", only_theirs.join(" "));
+            return;
+        }
+
+        let Some((movement, nodes, other)) = ours.iter()
+            .zip(&theirs)
+            .find(|((_, nodes), (_, other))| nodes != other)
+            .map(|((movement, nodes), (_, other))| (movement, nodes, other))
+        else {
+            println!("No root move differs at depth {} of '{}'", depth, state.to_fen());
+            return;
+        };
+        println!("{} at depth {}: core {}, qperft {}", movement, depth, nodes, other);
+        let movement = state.legal_moves().into_iter()
+            .find(|&candidate| &san::move_to_uci(candidate) == movement)
+            .expect("divide returned a move that is not legal");
+        state.make_move(movement);
+    }
+}
+
+// Compares core against qperft on every curated position and the random ones, up to the depth.
+// Stops at the first position where the counts differ and reduces it, returns whether all of them match.
+pub fn differential(positions: usize, depth: usize, seed: u64) -> bool {
+    let fens = CURATED_FENS.iter()
+        .copied()
+        .chain(perft::perft_table_fens())
+        .map(String::from)
+        .chain(random_fens(positions, seed));
+
+    let mut checked = 0;
+    let mut total = 0;
+    for fen in fens {
+        let mut state = GameState::from_fen(&fen).expect("Fen parsing failed. Use fen tests");
+        for depth in 1..=depth {
+            let ours = core::perft::perft(&mut state, depth);
+            let Some(theirs) = perft(&fen, depth) else {
+                println!("qperft cannot read '{}'", fen);
+                return false;
+            };
+            if ours != theirs {
+                println!("Position {} differs at depth {}: core {}, qperft {}\n{}", checked + 1, depth, ours, theirs, fen);
+                reduce(&fen, depth);
+                return false;
+            }
+            total += ours;
+        }
+        checked += 1;
+        if checked % 1000 == 0 {
+            println!("{} positions match", checked);
+        }
+    }
+    println!("All {} positions match up to depth {}, {} nodes", checked, depth, total);
+    true
+}
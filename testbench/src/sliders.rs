@@ -0,0 +1,31 @@
+use crate::*;
+
+use core::bitboard::Bitboard;
+use core::sliders::{sample_occupancies, SlidingBackend};
+
+
+fn sliding_attacks(criterion: &mut Criterion) {
+    let occupancies = sample_occupancies(64);
+    let mut group = criterion.benchmark_group("sliding attacks");
+    for backend in SlidingBackend::ALL {
+        let sliders = backend.get();
+        group.bench_function(backend.name(), |bencher| bencher.iter(| | {
+            let mut result = Bitboard(0);
+            for (square, &occupancy) in Square::ALL.iter().zip(&occupancies) {
+                result |= sliders.queen_attacks(black_box(*square), black_box(occupancy));
+            }
+            result
+        }));
+    }
+    group.finish();
+}
+
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .plotting_backend(criterion::PlottingBackend::Plotters)
+        .sample_size(10);
+    targets =
+        sliding_attacks,
+);
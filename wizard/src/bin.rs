@@ -1,51 +1,273 @@
-mod lib;
-use lib::*;
+use wizard::*;
+use wizard::masks::*;
+use wizard::verify::square_name;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+
+const USAGE: &str = "\
+Usage: wizard <command> [options]
+
+Commands:
+    generate-reference  [--output PATH]
+        Enumerates every occlusion of every square with the attacks it leaves, starting from the known magics
+    build               [--reference PATH] [--magics PATH] [--output PATH]
+        Builds the database from the reference, with the magics of an existing database if they are valid
+    search-magics       [--reference PATH] [--database PATH] [--output PATH] [--rounds N] [--seconds N] [--square S] [--piece rook|bishop] [--seed N]
+        Looks for smaller magics and saves the rebuilt database, a seed gives the same magics for the same rounds
+    verify              [--database PATH]
+        Checks every table of the database against the generators, for every occupancy of every magic
+    stats               [--database PATH]
+        Prints the table sizes and the magic shifts
+    dump                --square S --occupancy HEX [--piece rook|bishop] [--database PATH]
+        Prints the attacks the database holds for a square and occupancy
+    emit-rust           [--database PATH] [--output PATH]
+        Writes the magics, sliding table and leaper tables as Rust source, by default to the file core includes
+
+Squares are given as a name like e4 or an index from 0 (a8) to 63 (h1).
+Paths default to the files in ./wizard, and ./core/src for emit-rust. Exits with 1 if a command fails and 2 on bad arguments.
+";
+
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, options)) = arguments.split_first() else {
+        eprint!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let options = match Options::parse(options) {
+        Ok(options) => options,
+        Err(error) => return usage_error(&error),
+    };
 
-mod known;
+    let result = match command.as_str() {
+        "generate-reference" => generate_reference(&options),
+        "build" => build(&options),
+        "search-magics" => search_magics(&options),
+        "verify" => verify(&options),
+        "stats" => stats(&options),
+        "dump" => dump(&options),
+        "emit-rust" => emit_rust(&options),
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => return usage_error(&format!("unknown command '{}'", command)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(error)) => usage_error(&error),
+        Err(Error::Failed(error)) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+fn usage_error(error: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", error, USAGE);
+    ExitCode::from(2)
+}
 
-use std::path::Path;
 
-use nanorand::{Rng, WyRand};
+enum Error {
+    // Bad arguments, exit code 2
+    Usage(String),
+    // The command ran but did not succeed, exit code 1
+    Failed(String),
+}
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Failed(error.to_string())
+    }
+}
 
+// "--name value" pairs, in any order
+struct Options(Vec<(String, String)>);
+impl Options {
+    fn parse(arguments: &[String]) -> Result<Self, String> {
+        let mut result = Vec::new();
+        let mut arguments = arguments.iter();
+        while let Some(name) = arguments.next() {
+            let Some(name) = name.strip_prefix("--") else {
+                return Err(format!("unexpected argument '{}'", name));
+            };
+            let value = arguments.next().ok_or_else(|| format!("--{} needs a value", name))?;
+            result.push((name.to_string(), value.clone()));
+        }
+        Ok(Self(result))
+    }
 
-fn main() -> Result<(), std::io::Error> {
-    let reference_path = Path::new(REFERENCE_PATH);
-    let reference = if !reference_path.exists() || std::env::args().len() > 1 {
-        println!("No reference found, creating new");
-        let reference = create_initial();
-        reference.write(reference_path)?;
-        reference
-    } else {
-        println!("Using reference as base");
-        ReferenceDatabase::read(reference_path)?
-    };
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+    fn path(&self, name: &str, default: &str) -> PathBuf {
+        PathBuf::from(self.get(name).unwrap_or(default))
+    }
+    fn parse_with<T>(&self, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, Error> {
+        self.get(name)
+            .map(|value| parse(value).ok_or_else(|| Error::Usage(format!("invalid value '{}' for --{}", value, name))))
+            .transpose()
+    }
+    fn required<T>(&self, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<T, Error> {
+        self.parse_with(name, parse)?.ok_or_else(|| Error::Usage(format!("--{} is required", name)))
+    }
+}
+
+fn parse_square(name: &str) -> Option<usize> {
+    if let Ok(index) = name.parse::<usize>() {
+        return Some(index).filter(|&index| index < 64);
+    }
+    let mut characters = name.chars();
+    let file = characters.next()?;
+    let rank = characters.next()?.to_digit(10)?;
+    if characters.next().is_some() || !('a'..='h').contains(&file) || !(1..=8).contains(&rank) {
+        return None;
+    }
+    Some((8 - rank as usize) * 8 + (file as usize - 'a' as usize))
+}
+// The offsets of the pieces in the magic list, rooks take the first 64 entries and bishops the next
+fn parse_piece(name: &str) -> Option<usize> {
+    match name {
+        "rook" => Some(0),
+        "bishop" => Some(64),
+        _ => None,
+    }
+}
+fn hex(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
 
-    let database_path = Path::new(DATABASE_PATH);
-    if cfg!(not(target_feature = "pext")) {
-        let old_magics = if let Ok(old) = Database::read(database_path) {
-            old.magics
-        } else {
+fn read<T: Cacheable>(path: &Path) -> Result<T, Error> {
+    T::read(path).map_err(|error| Error::Failed(format!("could not read {}: {}", path.display(), error)))
+}
+fn write<T: Cacheable>(value: &T, path: &Path) -> Result<(), Error> {
+    value.write(path).map_err(|error| Error::Failed(format!("could not write {}: {}", path.display(), error)))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+// The magics of an existing database, the known magics of the reference if it is missing or out of date
+fn starting_magics(path: Option<&Path>, reference: &ReferenceDatabase) -> Vec<MagicSquare> {
+    match path.map(Database::read) {
+        Some(Ok(database)) if search::verify_magics(&database.magics, reference) => database.magics,
+        Some(_) => {
+            println!("The magics of {} do not match the reference, starting from the known magics", path.unwrap().display());
             reference.magics.clone()
-        };
-
-        //let magics = upgrade_magics(old_magics, &reference);
-        let magics = old_magics;
-        let database = build_database(magics, &reference);
-        database.write(database_path)?;
-
-        for (index, magic) in database.magics.iter().skip(64).enumerate() {
-            for _ in 0..5 {//let b = WyRand::new().generate();
-                let occ: u64 = nanorand::WyRand::new().generate();
-                let occ = magic.mask;
-                debug_print(index, database.sliding_table[magic.key(occ)], occ, 1000);
-            }
         }
-    } else {
-        let database = build_database(reference.magics.clone(), &reference);
-        database.write(database_path)?;
+        None => reference.magics.clone(),
+    }
+}
+
+
+fn generate_reference(options: &Options) -> Result<(), Error> {
+    let reference = create_initial();
+    write(&reference, &options.path("output", REFERENCE_PATH))
+}
+
+fn build(options: &Options) -> Result<(), Error> {
+    let reference: ReferenceDatabase = read(&options.path("reference", REFERENCE_PATH))?;
+    let magics = options.get("magics").map(Path::new);
+    let database = build_database(starting_magics(magics, &reference), &reference);
+    write(&database, &options.path("output", DATABASE_PATH))
+}
+
+fn search_magics(options: &Options) -> Result<(), Error> {
+    let reference: ReferenceDatabase = read(&options.path("reference", REFERENCE_PATH))?;
+    let database_path = options.path("database", DATABASE_PATH);
+    let rounds = options.parse_with("rounds", |value| value.parse::<usize>().ok())?.unwrap_or(10);
+    let seconds = options.parse_with("seconds", |value| value.parse::<u64>().ok())?;
+    let seed = options.parse_with("seed", |value| value.parse::<u64>().ok())?.unwrap_or(0);
+    let square = options.parse_with("square", parse_square)?;
+    let piece = options.parse_with("piece", parse_piece)?;
+
+    let squares: Vec<usize> = (0..128)
+        .filter(|index| square.is_none_or(|square| index % 64 == square))
+        .filter(|index| piece.is_none_or(|piece| index / 64 * 64 == piece))
+        .collect();
+    let magics = starting_magics(database_path.exists().then_some(database_path.as_path()), &reference);
+
+    println!("Searching {} squares for {} rounds with seed {}", squares.len(), rounds, seed);
+    let deadline = seconds.map(|seconds| Instant::now() + Duration::from_secs(seconds));
+    let (improved, searched) = search::improve_magics(&magics, &reference, &squares, seed, rounds, deadline);
+    if searched < rounds {
+        println!("Stopped after {} rounds, pass --rounds {} to reproduce", searched, searched);
+    }
+    for &index in &squares {
+        let (old, new) = (magics[index], improved[index]);
+        if new.shift > old.shift {
+            let piece = if index < 64 { "rook" } else { "bishop" };
+            println!("{} {}: {} -> {} bits, magic {:#018x}", piece, square_name(index % 64), 64 - old.shift, 64 - new.shift, new.value);
+        }
+    }
+
+    let database = build_database(improved, &reference);
+    write(&database, &options.path("output", database_path.to_str().unwrap_or(DATABASE_PATH)))
+}
+
+fn verify(options: &Options) -> Result<(), Error> {
+    let database: Database = read(&options.path("database", DATABASE_PATH))?;
+
+    let failures = verify::verify_database(&database);
+    if failures.is_empty() {
+        println!("All tables are valid");
+        return Ok(());
+    }
+    for failure in &failures {
+        println!("{}", failure);
+    }
+    Err(Error::Failed(format!("{} squares or tables are wrong", failures.len())))
+}
+
+fn stats(options: &Options) -> Result<(), Error> {
+    let database: Database = read(&options.path("database", DATABASE_PATH))?;
+
+    println!("magics:              {}", database.magics.len());
+    println!("sliding table:       {} entries, {} KiB", database.sliding_table.len(), database.sliding_table.len() * 8 / 1024);
+    for (name, magics) in [("rook", &database.magics[..64.min(database.magics.len())]), ("bishop", database.magics.get(64..).unwrap_or(&[]))] {
+        let bits: Vec<usize> = magics.iter().map(|magic| 64 - magic.shift).collect();
+        let relevant: usize = magics.iter().map(|magic| magic.mask.count_ones() as usize).sum();
+        println!("{:<20} {} bits in total, {} relevant occupancy bits, {} entries", format!("{} shifts:", name), bits.iter().sum::<usize>(), relevant, bits.iter().map(|bits| 1 << bits).sum::<usize>());
+        for rank in bits.chunks(8) {
+            println!("    {}", rank.iter().map(|bits| format!("{:2}", bits)).collect::<Vec<_>>().join(" "));
+        }
+    }
+    println!("pawns, knights, kings: {}, {}, {}", database.pawns.len(), database.knights.len(), database.kings.len());
+    println!("between, lines, rays:  {}, {}, {}", database.between.len(), database.lines.len(), database.rays.len());
+    println!("eval masks:            {} forward files, {} attack spans, {} passed spans, {} king zones, {} adjacent files",
+        database.forward_files.len(), database.pawn_attack_spans.len(), database.passed_pawn_spans.len(), database.king_zones.len(), database.adjacent_files.len());
+    Ok(())
+}
+
+fn dump(options: &Options) -> Result<(), Error> {
+    let database: Database = read(&options.path("database", DATABASE_PATH))?;
+    let square = options.required("square", parse_square)?;
+    let occupancy = options.required("occupancy", hex)?;
+    let pieces = match options.parse_with("piece", parse_piece)? {
+        Some(piece) => vec![piece],
+        None => vec![0, 64],
+    };
+
+    for piece in pieces {
+        let magic = database.magics.get(piece + square)
+            .ok_or_else(|| Error::Failed("the database has no magic for this square".to_string()))?;
+        let attacks = database.sliding_table.get(magic.key(occupancy))
+            .ok_or_else(|| Error::Failed("the magic points outside of the sliding table".to_string()))?;
+        println!("{} on {}, key {}, attacks {:#018x}", if piece == 0 { "rook" } else { "bishop" }, square_name(square), magic.key(occupancy), attacks);
+        print!("{}", verify::render_board(square, *attacks, occupancy));
     }
+    Ok(())
+}
 
-    //database.write(database_path)?;
+fn emit_rust(options: &Options) -> Result<(), Error> {
+    let database: Database = read(&options.path("database", DATABASE_PATH))?;
+    let path = options.path("output", RUST_TABLES_PATH);
+    std::fs::write(&path, wizard::codegen::emit_rust(&database))
+        .map_err(|error| Error::Failed(format!("could not write {}: {}", path.display(), error)))?;
+    println!("Wrote {}", path.display());
     Ok(())
 }
 
@@ -61,16 +283,13 @@ fn create_initial() -> ReferenceDatabase {
         occlusion_gen: &dyn Fn(usize) -> u64, 
         attack_gen: &dyn Fn(usize, u64) -> u64, 
         known_values: Option<&([usize; 64], [u64; 64])>,
-        debug: bool,
     | {
         for square in 0..64 {
             // Create the magic square with relevant info
             // the magic number will be found later
             let mask = occlusion_gen(square);
             let (shift, value) = known_values
-                .and_then(|(shifts, values)| {
-                    Some((64 - shifts[square], values[square]))
-                })
+                .map(|(shifts, values)| (64 - shifts[square], values[square]))
                 .unwrap_or((64 - mask.count_ones() as usize, 0));
             magics.push(MagicSquare {
                 offset,
@@ -89,16 +308,10 @@ fn create_initial() -> ReferenceDatabase {
 
                 offset += 1;
                 occlusion = occlusion.wrapping_sub(mask) & mask;
-                /*if occlusion == mask && debug {
-                    debug_print(square, attacks, mask, 1000);
-                }*/
                 if occlusion == 0 {
                     break;
                 }
             }
-            if debug {
-                //debug_print(square, moves[magics.last().unwrap().key(mask)], 0, 1000);
-            }
         }
     };
 
@@ -106,13 +319,11 @@ fn create_initial() -> ReferenceDatabase {
         &generate_rook_occlusion_mask, 
         &generate_rook_attack_mask,
         Some(&known::KNOWN_ROOK_MAGIC_VALUES),
-        false,
     );
     create(
         &generate_bishop_occlusion_mask, 
         &generate_bishop_attack_mask,
         Some(&known::KNOWN_BISHOP_MAGIC_VALUES),
-        true,
     );
 
     ReferenceDatabase {
@@ -155,223 +366,11 @@ fn build_database(magics: Vec<MagicSquare>, reference: &ReferenceDatabase) -> Da
         }
     }
 
-    Database {
+    let mut result = Database {
         magics,
         sliding_table: moves,
-
-        pawns: (0..64).map(generate_pawn_attack_mask).flatten().collect(),
-        knights: (0..64).map(generate_knight_attack_mask).collect(),
-        kings: (0..64).map(generate_king_attack_mask).collect()
-    }
-}
-
-
-// https://github.com/maksimKorzh/chess_programming/blob/master/src/bbc/init_magics/bbc.c
-fn upgrade_magics(mut magics: Vec<MagicSquare>, reference: &ReferenceDatabase) -> Vec<MagicSquare> {
-    for magic in &mut magics {
-        magic.shift = if magic.value == 0 {
-            magic.shift
-        } else {
-            (magic.shift + 1).max(64)
-        };
-
-
-        let mut rng = WyRand::new_seed(0);
-        'outer: loop {
-            // Generate a random magic with a high-ish population count
-            loop {
-                magic.value = rng.generate::<u64>();
-                if ((magic.value * magic.value) >> 56).count_ones() > 5 {
-                    break;
-                }
-            }
-
-            let mut used_moves = vec![0; 0x1000];
-
-            // Test the random magic to see ...
-            let iter = reference.moves.iter().skip(magic.offset).zip(
-                reference.occluders.iter().skip(magic.offset)
-            );
-            for (i, (&truth, &occlusion)) in iter.enumerate() {
-                if i >= (0x1000 - 1) {
-                    break 'outer;
-                }
-                let key = magic.key(occlusion) as usize;
-                let index = key - magic.offset;
-                
-                if used_moves[index] == 0 {
-                    // Magic has not yet been used
-                    used_moves[index] = truth;
-                } else if used_moves[index] != truth {
-                    // Magic has been used, but with a bad collision
-                    continue 'outer;
-                }
-            }
-            break;
-        }
-        println!("magic found: {:x}", magic.value);
-    }
-
-    magics
-}
-
-
-
-
-
-fn generate_rook_occlusion_mask(square: usize) -> u64 {
-    let occupancy = 1 << square;
-    (
-        generate_line_mask(occupancy, 0, Direction::North) & NOT_RANK_1 | 
-        generate_line_mask(occupancy, 0, Direction::South) & NOT_RANK_8 | 
-        generate_line_mask(occupancy, 0, Direction::East) & NOT_FILE_H | 
-        generate_line_mask(occupancy, 0, Direction::West) & NOT_FILE_A
-    ) & !occupancy
-}
-fn generate_rook_attack_mask(square: usize, occlusion: u64) -> u64 {
-    let occupancy = 1 << square;
-    (
-        generate_line_mask(occupancy, occlusion, Direction::North) | 
-        generate_line_mask(occupancy, occlusion, Direction::South) | 
-        generate_line_mask(occupancy, occlusion, Direction::East) | 
-        generate_line_mask(occupancy, occlusion, Direction::West)
-    ) & !occupancy
-}
-
-fn generate_bishop_occlusion_mask(square: usize) -> u64 {
-    let occupancy = 1 << square;
-    (
-        generate_line_mask(occupancy, 0, Direction::NorthEast) & (NOT_RANK_1 & NOT_FILE_H) | 
-        generate_line_mask(occupancy, 0, Direction::NorthWest) & (NOT_RANK_1 & NOT_FILE_A) | 
-        generate_line_mask(occupancy, 0, Direction::SouthEast) & (NOT_RANK_8 & NOT_FILE_H) | 
-        generate_line_mask(occupancy, 0, Direction::SouthWest) & (NOT_RANK_8 & NOT_FILE_A) 
-    ) & !occupancy
-}
-fn generate_bishop_attack_mask(square: usize, occlusion: u64) -> u64 {
-    let occupancy = 1 << square;
-    (
-        generate_line_mask(occupancy, occlusion, Direction::NorthEast) | 
-        generate_line_mask(occupancy, occlusion, Direction::NorthWest) | 
-        generate_line_mask(occupancy, occlusion, Direction::SouthEast) | 
-        generate_line_mask(occupancy, occlusion, Direction::SouthWest)
-    ) & !occupancy
-}
-
-fn generate_pawn_attack_mask(square: usize) -> [u64; 2] {
-    let occupancy = 1 << square;
-    let white = (occupancy >> 7) & NOT_FILE_A | (occupancy >> 9) & NOT_FILE_H;
-    let black = (occupancy << 9) & NOT_FILE_A | (occupancy << 7) & NOT_FILE_H;
-    [white, black]
-}
-fn generate_knight_attack_mask(square: usize) -> u64 {
-    let occupancy = 1 << square;
-    let north_east: u64 = (occupancy << 1) & NOT_FILE_A;
-    let north_west: u64 = (occupancy >> 1) & NOT_FILE_H;
-    let south_east: u64 = (occupancy << 2) & NOT_FILE_AB;
-    let south_west: u64 = (occupancy >> 2) & NOT_FILE_GH;
-    let north: u64 = north_east | north_west;
-    let south: u64 = south_east | south_west;
-    
-    {
-        (north << 16) | 
-        (north >> 16) | 
-        (south << 8) | 
-        (south >> 8)
-    }
-}
-fn generate_king_attack_mask(square: usize) -> u64 {
-    let occupancy = 1 << square;
-    {
-        // north and south
-        occupancy >> 8 |
-        occupancy << 8 |
-        // east and west
-        (occupancy << 1) & NOT_FILE_A |
-        (occupancy >> 1) & NOT_FILE_H | 
-        // north east and west
-        (occupancy >> 7) & NOT_FILE_A |
-        (occupancy >> 9) & NOT_FILE_H |
-        // south east and west
-        (occupancy << 9) & NOT_FILE_A |
-        (occupancy << 7) & NOT_FILE_H
-    }
-}
-
-
-
-
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum Direction {
-    North,
-    South,
-    East, 
-    West, 
-    NorthEast,
-    NorthWest,
-    SouthEast,
-    SouthWest,
-}
-
-const NOT_FILE_A: u64 = 0xfefefefefefefefe;
-const NOT_FILE_H: u64 = 0x7f7f7f7f7f7f7f7f;
-const NOT_FILE_AB: u64 = 0xfcfcfcfcfcfcfcfc;
-const NOT_FILE_GH: u64 = 0x3f3f3f3f3f3f3f3f;
-
-const NOT_RANK_1: u64 = 0xffffffffffffff00;
-const NOT_RANK_8: u64 = 0x00ffffffffffffff;
-
-
-fn generate_line_mask(mut occupancy: u64, mut occlusion: u64, direction: Direction) -> u64 {
-    let (left_shift, right_shift, mask) = match direction {
-        Direction::North        => (0, 8, !0),
-        Direction::South        => (8, 0, !0),
-        Direction::East         => (1, 0, NOT_FILE_A),
-        Direction::West         => (0, 1, NOT_FILE_H),
-        Direction::NorthEast    => (0, 7, NOT_FILE_A),
-        Direction::NorthWest    => (0, 9, NOT_FILE_H),
-        Direction::SouthEast    => (9, 0, NOT_FILE_A),
-        Direction::SouthWest    => (7, 0, NOT_FILE_H),
+        ..Default::default()
     };
-    let shift = |value: u64, multiplier: usize| -> u64 {
-        (value >> (right_shift * multiplier)) << (left_shift * multiplier)
-    };
-
-    occlusion = mask & !shift(occlusion & !occupancy, 1);
-    occupancy |= occlusion & shift(occupancy, 1);
-    occlusion &= shift(occlusion, 1);
-    occupancy |= occlusion & shift(occupancy, 2);
-    occlusion &= shift(occlusion, 2);
-    occupancy |= occlusion & shift(occupancy, 4);
-    occupancy
+    result.generate_geometry();
+    result
 }
-
-
-
-
-fn debug_print(square: usize, movement: u64, occlusion: u64, timeout: u64) {
-    print!("{esc}[2J{esc}[0;0H", esc = 27 as char);
-    for i in 0..64 {
-        if i % 8 == 0 && i != 0 {
-            println!("");
-        }
-
-        let is_occluded = occlusion & (1 << i) != 0;
-        let is_attacking = movement & (1 << i) != 0;
-
-        let sym = if i == square {
-            " * "
-        } else if is_attacking && is_occluded {
-            " X "
-        } else if is_occluded {
-            " # "
-        } else if is_attacking {
-            " - "
-        } else {
-            " . "
-        };
-
-        print!("{}", sym);
-    }
-    println!("");
-    std::thread::sleep(std::time::Duration::from_millis(timeout))
-}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use crate::*;
+use crate::verify::square_name;
+
+use std::fmt::Write;
+
+
+// Rust source of every table of the database as static arrays, for include!.
+// Everything is written in index order with fixed width hex, so regenerating only changes the lines that differ.
+pub fn emit_rust(database: &Database) -> String {
+    let mut result = String::new();
+    result.push_str("// Generated by `wizard emit-rust`, do not edit by hand.\n");
+    result.push_str("// Magics 0..64 are rooks and 64..128 bishops, squares count from a8 to h1.\n\n");
+
+    let magics = &database.magics;
+    emit_array(&mut result, "MAGIC_MASKS", "u64", magics.iter().map(|magic| hex(magic.mask)), 4);
+    emit_array(&mut result, "MAGIC_VALUES", "u64", magics.iter().map(|magic| hex(magic.value)), 4);
+    emit_array(&mut result, "MAGIC_SHIFTS", "u8", magics.iter().map(|magic| magic.shift.to_string()), 16);
+    emit_array(&mut result, "MAGIC_OFFSETS", "u32", magics.iter().map(|magic| magic.offset.to_string()), 8);
+
+    // The sliding table is split into the segments of the squares, in the order they appear in the table
+    let mut starts: Vec<(usize, usize)> = magics.iter()
+        .enumerate()
+        .map(|(index, magic)| (magic.offset, index))
+        .collect();
+    starts.sort();
+    writeln!(result, "pub static SLIDING_TABLE: [u64; {}] = [", database.sliding_table.len()).unwrap();
+    let mut starts = starts.into_iter().peekable();
+    let mut line = Vec::new();
+    for (offset, &entry) in database.sliding_table.iter().enumerate() {
+        while let Some((_, index)) = starts.next_if(|&(start, _)| start <= offset) {
+            flush_line(&mut result, &mut line);
+            let piece = if index < 64 { "rook" } else { "bishop" };
+            writeln!(result, "    // {} {}, offset {}", piece, square_name(index % 64), offset).unwrap();
+        }
+        line.push(hex(entry));
+        if line.len() == 4 {
+            flush_line(&mut result, &mut line);
+        }
+    }
+    flush_line(&mut result, &mut line);
+    result.push_str("];\n\n");
+
+    // [white, black] per square, as in the database
+    emit_array(&mut result, "PAWN_ATTACKS", "[u64; 2]", pairs(&database.pawns), 2);
+    emit_array(&mut result, "KNIGHT_ATTACKS", "u64", database.knights.iter().map(|&mask| hex(mask)), 4);
+    emit_array(&mut result, "KING_ATTACKS", "u64", database.kings.iter().map(|&mask| hex(mask)), 4);
+
+    // Square pairs are indexed by from * 64 + to, rays by square * 8 + direction in the order
+    // north, south, east, west, north east, north west, south east, south west
+    emit_array(&mut result, "BETWEEN", "u64", database.between.iter().map(|&mask| hex(mask)), 4);
+    emit_array(&mut result, "LINES", "u64", database.lines.iter().map(|&mask| hex(mask)), 4);
+    emit_array(&mut result, "RAYS", "u64", database.rays.iter().map(|&mask| hex(mask)), 4);
+    emit_array(&mut result, "CHEBYSHEV_DISTANCES", "u8", database.chebyshev_distances.iter().map(|distance| distance.to_string()), 16);
+    emit_array(&mut result, "MANHATTAN_DISTANCES", "u8", database.manhattan_distances.iter().map(|distance| distance.to_string()), 16);
+
+    emit_array(&mut result, "FORWARD_FILES", "[u64; 2]", pairs(&database.forward_files), 2);
+    emit_array(&mut result, "PAWN_ATTACK_SPANS", "[u64; 2]", pairs(&database.pawn_attack_spans), 2);
+    emit_array(&mut result, "PASSED_PAWN_SPANS", "[u64; 2]", pairs(&database.passed_pawn_spans), 2);
+    emit_array(&mut result, "KING_ZONES", "[u64; 2]", pairs(&database.king_zones), 2);
+    emit_array(&mut result, "ADJACENT_FILES", "u64", database.adjacent_files.iter().map(|&mask| hex(mask)), 4);
+    // [white, black] and [light, dark]
+    emit_array(&mut result, "OUTPOSTS", "u64", database.outposts.iter().map(|&mask| hex(mask)), 2);
+    emit_array(&mut result, "SQUARE_COLORS", "u64", database.square_colors.iter().map(|&mask| hex(mask)), 2);
+
+    // The lookup the tables are made for, so the included file does not depend on wizard types
+    result.push_str("\
+// Attacks of the rook (index 0..64) or bishop (index 64..128) on a square
+pub fn sliding_attacks(index: usize, occupancy: u64) -> u64 {
+    let relevant = occupancy & MAGIC_MASKS[index];
+    let key = MAGIC_OFFSETS[index] as usize + (relevant.wrapping_mul(MAGIC_VALUES[index]) >> MAGIC_SHIFTS[index]) as usize;
+    SLIDING_TABLE[key]
+}
+");
+    result
+}
+
+fn emit_array(result: &mut String, name: &str, kind: &str, entries: impl ExactSizeIterator<Item = String>, per_line: usize) {
+    writeln!(result, "pub static {}: [{}; {}] = [", name, kind, entries.len()).unwrap();
+    let entries: Vec<String> = entries.collect();
+    for line in entries.chunks(per_line) {
+        writeln!(result, "    {},", line.join(", ")).unwrap();
+    }
+    result.push_str("];\n\n");
+}
+// Masks stored as [white, black] per square
+fn pairs(masks: &[u64]) -> impl ExactSizeIterator<Item = String> + '_ {
+    masks.chunks(2).map(|pair| format!("[{}, {}]", hex(pair[0]), hex(pair[1])))
+}
+fn flush_line(result: &mut String, line: &mut Vec<String>) {
+    if !line.is_empty() {
+        writeln!(result, "    {},", line.join(", ")).unwrap();
+        line.clear();
+    }
+}
+fn hex(value: u64) -> String {
+    format!("0x{:016x}", value)
+}
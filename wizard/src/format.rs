@@ -0,0 +1,250 @@
+use std::fmt;
+
+use nanoserde::DeBin;
+
+
+// Every table file starts with this header, followed by the nanoserde payload.
+//  0..4:   tag naming the kind of file
+//  4..8:   format version of the payload, little endian like the rest of the header
+//  8:      byte order of the payload, 0 little endian, 1 big endian
+//  9:      width of usize in the payload in bytes, nanoserde writes usize as is
+//  10:     layout of the sliding table, see TableLayout
+//  11:     reserved
+//  12..20: length of the payload in bytes
+//  20..28: FNV-1a checksum of the payload
+//  28..32: number of tables
+//  32..:   entry count of each table, 8 bytes each
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Header {
+    pub tag: [u8; 4],
+    pub version: u32,
+    pub big_endian: bool,
+    pub usize_width: u8,
+    pub layout: TableLayout,
+    pub payload_length: u64,
+    pub checksum: u64,
+    pub entry_counts: Vec<u64>,
+}
+impl Header {
+    pub const FIXED_SIZE: usize = 32;
+
+    // A header describing a payload written on this machine
+    pub fn new(tag: [u8; 4], version: u32, layout: TableLayout, payload: &[u8], entry_counts: Vec<u64>) -> Self {
+        Self {
+            tag,
+            version,
+            big_endian: cfg!(target_endian = "big"),
+            usize_width: std::mem::size_of::<usize>() as u8,
+            layout,
+            payload_length: payload.len() as u64,
+            checksum: checksum(payload),
+            entry_counts,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        Self::FIXED_SIZE + 8 * self.entry_counts.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.size());
+        result.extend_from_slice(&self.tag);
+        result.extend_from_slice(&self.version.to_le_bytes());
+        result.push(self.big_endian as u8);
+        result.push(self.usize_width);
+        result.push(self.layout as u8);
+        result.push(0);
+        result.extend_from_slice(&self.payload_length.to_le_bytes());
+        result.extend_from_slice(&self.checksum.to_le_bytes());
+        result.extend_from_slice(&(self.entry_counts.len() as u32).to_le_bytes());
+        for count in &self.entry_counts {
+            result.extend_from_slice(&count.to_le_bytes());
+        }
+        result
+    }
+
+    // Only checks the header can be read, see Cacheable::read for the checks against the payload
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        if bytes.len() < Self::FIXED_SIZE {
+            return Err(FormatError::SizeMismatch { expected: Self::FIXED_SIZE as u64, found: bytes.len() as u64 });
+        }
+        let table_count = u32_at(28) as usize;
+        let size = Self::FIXED_SIZE + 8 * table_count;
+        if bytes.len() < size {
+            return Err(FormatError::SizeMismatch { expected: size as u64, found: bytes.len() as u64 });
+        }
+
+        Ok(Self {
+            tag: bytes[0..4].try_into().unwrap(),
+            version: u32_at(4),
+            big_endian: bytes[8] != 0,
+            usize_width: bytes[9],
+            layout: TableLayout::try_from(bytes[10]).map_err(FormatError::UnknownLayout)?,
+            payload_length: u64_at(12),
+            checksum: u64_at(20),
+            entry_counts: (0..table_count).map(|index| u64_at(Self::FIXED_SIZE + 8 * index)).collect(),
+        })
+    }
+}
+
+
+// How the sliding table is indexed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TableLayout {
+    Magic = 0,
+    Pext = 1,
+}
+impl TryFrom<u8> for TableLayout {
+    type Error = u8;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Magic),
+            1 => Ok(Self::Pext),
+            _ => Err(value),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io(std::io::Error),
+    // The file does not start with the tag of the expected kind of file
+    WrongTag { expected: [u8; 4], found: [u8; 4] },
+    // The file has no header and could not be read as a file from before the header either
+    MigrationFailed { found: [u8; 4], source: Box<FormatError> },
+    // Written by a newer wizard, or an older one no migration exists for
+    UnsupportedVersion { found: u32, supported: u32 },
+    // Written on a machine with a different byte order or usize width
+    WrongPlatform { big_endian: bool, usize_width: u8 },
+    UnknownLayout(u8),
+    WrongLayout { expected: TableLayout, found: TableLayout },
+    // The file is shorter or longer than the header says
+    SizeMismatch { expected: u64, found: u64 },
+    ChecksumMismatch { expected: u64, found: u64 },
+    EntryCountMismatch { table: usize, expected: u64, found: u64 },
+    Deserialize(String),
+}
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::WrongTag { expected, found } => write!(
+                f, "not a {} file, it starts with {:?}",
+                String::from_utf8_lossy(expected), String::from_utf8_lossy(found),
+            ),
+            Self::MigrationFailed { found, source } => write!(
+                f, "no header, it starts with {:?}, and it is not a file from before the header either: {}",
+                String::from_utf8_lossy(found), source,
+            ),
+            Self::UnsupportedVersion { found, supported } => write!(
+                f, "format version {} is not supported, this build reads version {}, rebuild the file with the wizard",
+                found, supported,
+            ),
+            Self::WrongPlatform { big_endian, usize_width } => write!(
+                f, "written on a {} endian machine with {} byte usize, rebuild the file on this machine",
+                if *big_endian { "big" } else { "little" }, usize_width,
+            ),
+            Self::UnknownLayout(layout) => write!(f, "unknown table layout {}", layout),
+            Self::WrongLayout { expected, found } => write!(f, "expected a {:?} table layout but found {:?}", expected, found),
+            Self::SizeMismatch { expected, found } => write!(f, "truncated or padded file, expected {} bytes but found {}", expected, found),
+            Self::ChecksumMismatch { expected, found } => write!(f, "checksum mismatch, expected {:#018x} but found {:#018x}", expected, found),
+            Self::EntryCountMismatch { table, expected, found } => write!(
+                f, "table {} should have {} entries but has {}",
+                table, expected, found,
+            ),
+            Self::Deserialize(error) => write!(f, "could not deserialize the payload: {}", error),
+        }
+    }
+}
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::MigrationFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+impl From<std::io::Error> for FormatError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+
+// 64 bit FNV-1a, enough to catch truncation and corruption
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// Deserializes a payload that has to be used up completely
+pub fn deserialize_exact<T: DeBin>(payload: &[u8]) -> Result<T, FormatError> {
+    let mut offset = 0;
+    let result = T::de_bin(&mut offset, payload).map_err(|error| FormatError::Deserialize(error.to_string()))?;
+    if offset != payload.len() {
+        return Err(FormatError::Deserialize(format!("{} bytes left over", payload.len() - offset)));
+    }
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nanoserde::SerBin;
+
+    fn header() -> Header {
+        Header::new(*b"TEST", 3, TableLayout::Pext, &[1, 2, 3], vec![4, 5])
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let header = header();
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.size());
+        assert_eq!(Header::from_bytes(&bytes).unwrap(), header);
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = header().to_bytes();
+        // Inside the fixed part, then inside the entry counts
+        assert!(matches!(
+            Header::from_bytes(&bytes[..20]),
+            Err(FormatError::SizeMismatch { expected: 32, found: 20 }),
+        ));
+        assert!(matches!(
+            Header::from_bytes(&bytes[..40]),
+            Err(FormatError::SizeMismatch { expected: 48, found: 40 }),
+        ));
+    }
+
+    #[test]
+    fn unknown_layout() {
+        let mut bytes = header().to_bytes();
+        bytes[10] = 7;
+        assert!(matches!(Header::from_bytes(&bytes), Err(FormatError::UnknownLayout(7))));
+    }
+
+    #[test]
+    fn checksum_is_fnv1a() {
+        assert_eq!(checksum(&[]), 0xcbf29ce484222325);
+        assert_eq!(checksum(b"a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(checksum(&[1, 2, 3]), checksum(&[1, 2, 4]));
+    }
+
+    #[test]
+    fn payload_has_to_be_used_up() {
+        let mut payload = Vec::new();
+        7u64.ser_bin(&mut payload);
+        assert_eq!(deserialize_exact::<u64>(&payload).unwrap(), 7);
+
+        payload.push(0);
+        assert!(matches!(deserialize_exact::<u64>(&payload), Err(FormatError::Deserialize(_))));
+        assert!(matches!(deserialize_exact::<u64>(&payload[..4]), Err(FormatError::Deserialize(_))));
+    }
+}
@@ -1,9 +1,22 @@
-use std::fs::OpenOptions;
-use std::io::prelude::*;
 use std::path::Path;
 
 use nanoserde::*;
 
+pub mod masks;
+
+pub mod format;
+
+pub mod known;
+
+pub mod search;
+
+pub mod verify;
+
+pub mod codegen;
+
+use format::{Header, FormatError, TableLayout};
+use masks::*;
+
 
 
 #[derive(Copy, Clone, Default, Debug, SerBin, DeBin)]
@@ -33,34 +46,91 @@ impl MagicSquare {
 }
 
 
+// Tables written to disk behind a format::Header, which is checked on every read
 pub trait Cacheable where Self: Sized + SerBin + DeBin {
-    fn read(path: &Path) -> Result<Self, std::io::Error> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .open(path)?;
+    // Names the kind of file in the header
+    const TAG: [u8; 4];
+    // Bump this whenever the fields change, and teach migrate to read the previous version
+    const VERSION: u32;
 
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
+    // Entries of each table in field order, stored in the header and compared on read
+    fn entry_counts(&self) -> Vec<u64>;
+    fn layout(&self) -> TableLayout {
+        TableLayout::Magic
+    }
 
-        let result = Self::deserialize_bin(&buffer[..]).or_else(|error| {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other, 
-                format!("Failed to deserialize database:\n{}", error)
-            ))
-        });
-        Ok(result?)
+    // Reads the payload of an older version, header is None for files from before the header existed
+    fn migrate(header: Option<&Header>, _payload: &[u8]) -> Result<Self, FormatError> {
+        Err(FormatError::UnsupportedVersion {
+            found: header.map_or(0, |header| header.version),
+            supported: Self::VERSION,
+        })
     }
 
-    fn write(&self, path: &Path) -> Result<(), std::io::Error> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)?;
+    fn read(path: &Path) -> Result<Self, FormatError> {
+        Self::from_file_bytes(&std::fs::read(path)?)
+    }
+    fn from_file_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if bytes.get(..4) != Some(&Self::TAG[..]) {
+            // Either a file from before the header or not a file of this kind at all.
+            // Without a headerless version to migrate from only the tag is wrong, otherwise the reason the migration failed is kept.
+            let found = bytes.get(..4).and_then(|tag| tag.try_into().ok()).unwrap_or_default();
+            return Self::migrate(None, bytes).map_err(|error| match error {
+                FormatError::UnsupportedVersion { .. } => FormatError::WrongTag { expected: Self::TAG, found },
+                source => FormatError::MigrationFailed { found, source: Box::new(source) },
+            });
+        }
+
+        let header = Header::from_bytes(bytes)?;
+        if header.version > Self::VERSION {
+            return Err(FormatError::UnsupportedVersion { found: header.version, supported: Self::VERSION });
+        }
+        let expected = Header::new(Self::TAG, Self::VERSION, header.layout, &[], Vec::new());
+        if header.big_endian != expected.big_endian || header.usize_width != expected.usize_width {
+            return Err(FormatError::WrongPlatform { big_endian: header.big_endian, usize_width: header.usize_width });
+        }
+        let payload = &bytes[header.size()..];
+        if payload.len() as u64 != header.payload_length {
+            return Err(FormatError::SizeMismatch {
+                expected: (header.size() as u64) + header.payload_length,
+                found: bytes.len() as u64,
+            });
+        }
+        let checksum = format::checksum(payload);
+        if checksum != header.checksum {
+            return Err(FormatError::ChecksumMismatch { expected: header.checksum, found: checksum });
+        }
+        if header.version < Self::VERSION {
+            return Self::migrate(Some(&header), payload);
+        }
 
-        let mut buffer = self.serialize_bin();
-        file.write_all(&mut buffer)?;
-        Ok(())
+        let result: Self = format::deserialize_exact(payload)?;
+        let counts = result.entry_counts();
+        if counts.len() != header.entry_counts.len() {
+            return Err(FormatError::EntryCountMismatch {
+                table: counts.len().min(header.entry_counts.len()),
+                expected: header.entry_counts.len() as u64,
+                found: counts.len() as u64,
+            });
+        }
+        if let Some((table, (&expected, &found))) = header.entry_counts.iter().zip(&counts).enumerate().find(|(_, (expected, found))| expected != found) {
+            return Err(FormatError::EntryCountMismatch { table, expected, found });
+        }
+        if result.layout() != header.layout {
+            return Err(FormatError::WrongLayout { expected: result.layout(), found: header.layout });
+        }
+        Ok(result)
+    }
+
+    fn write(&self, path: &Path) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_file_bytes())
+    }
+    fn to_file_bytes(&self) -> Vec<u8> {
+        let payload = self.serialize_bin();
+        let header = Header::new(Self::TAG, Self::VERSION, self.layout(), &payload, self.entry_counts());
+        let mut result = header.to_bytes();
+        result.extend_from_slice(&payload);
+        result
     }
 }
 
@@ -71,7 +141,24 @@ pub struct ReferenceDatabase {
     pub moves: Vec<u64>,
     pub occluders: Vec<u64>,
 }
-impl Cacheable for ReferenceDatabase {}
+impl Cacheable for ReferenceDatabase {
+    const TAG: [u8; 4] = *b"WZRF";
+    const VERSION: u32 = 1;
+
+    fn entry_counts(&self) -> Vec<u64> {
+        [self.magics.len(), self.moves.len(), self.occluders.len()]
+            .iter()
+            .map(|&count| count as u64)
+            .collect()
+    }
+    fn migrate(header: Option<&Header>, payload: &[u8]) -> Result<Self, FormatError> {
+        match header {
+            // Version 1 is the layout from before the header
+            None => format::deserialize_exact(payload),
+            Some(header) => Err(FormatError::UnsupportedVersion { found: header.version, supported: Self::VERSION }),
+        }
+    }
+}
 
 
 #[derive(Clone, Default, Debug, SerBin, DeBin)]
@@ -86,15 +173,357 @@ pub struct Database {
     pub pawns: Vec<u64>,
     pub knights: Vec<u64>,
     pub kings: Vec<u64>,
+
+    // 64 * 64, indexed by from * 64 + to
+    pub between: Vec<u64>,
+    pub lines: Vec<u64>,
+    // 64 * 8, indexed by square * 8 + direction in the order of Direction::ALL
+    pub rays: Vec<u64>,
+    // 64 * 64, indexed by from * 64 + to
+    pub chebyshev_distances: Vec<u8>,
+    pub manhattan_distances: Vec<u8>,
+
+    // Evaluation masks, [white, black] per square like the pawns
+    pub forward_files: Vec<u64>,
+    pub pawn_attack_spans: Vec<u64>,
+    pub passed_pawn_spans: Vec<u64>,
+    pub king_zones: Vec<u64>,
+    // One per square
+    pub adjacent_files: Vec<u64>,
+    // [white, black]
+    pub outposts: Vec<u64>,
+    // [light, dark]
+    pub square_colors: Vec<u64>,
+}
+impl Database {
+    // Fills the tables that only depend on the board, everything but the magics and the sliding table
+    pub fn generate_geometry(&mut self) {
+        let pairs = || (0..64 * 64).map(|index| (index / 64, index % 64));
+        self.pawns = (0..64).flat_map(generate_pawn_attack_mask).collect();
+        self.knights = (0..64).map(generate_knight_attack_mask).collect();
+        self.kings = (0..64).map(generate_king_attack_mask).collect();
+
+        self.between = pairs().map(|(from, to)| generate_between_mask(from, to)).collect();
+        self.lines = pairs().map(|(from, to)| generate_through_mask(from, to)).collect();
+        self.rays = (0..64 * 8).map(|index| generate_ray_mask(index / 8, Direction::ALL[index % 8])).collect();
+        self.chebyshev_distances = pairs().map(|(from, to)| generate_chebyshev_distance(from, to)).collect();
+        self.manhattan_distances = pairs().map(|(from, to)| generate_manhattan_distance(from, to)).collect();
+
+        self.forward_files = (0..64).flat_map(generate_forward_file_mask).collect();
+        self.pawn_attack_spans = (0..64).flat_map(generate_pawn_attack_span_mask).collect();
+        self.passed_pawn_spans = (0..64).flat_map(generate_passed_pawn_span_mask).collect();
+        self.king_zones = (0..64).flat_map(generate_king_zone_mask).collect();
+        self.adjacent_files = (0..64).map(generate_adjacent_files_mask).collect();
+        self.outposts = generate_outpost_masks().to_vec();
+        self.square_colors = generate_square_color_masks().to_vec();
+    }
+
+    // A database around searched magics, every other table only depends on the board
+    fn from_magics(magics: Vec<MagicSquare>, sliding_table: Vec<u64>) -> Self {
+        let mut result = Self {
+            magics,
+            sliding_table,
+            ..Default::default()
+        };
+        result.generate_geometry();
+        result
+    }
+}
+impl Cacheable for Database {
+    const TAG: [u8; 4] = *b"WZDB";
+    const VERSION: u32 = 2;
+
+    fn entry_counts(&self) -> Vec<u64> {
+        [
+            self.magics.len(),
+            self.sliding_table.len(),
+            self.pawns.len(),
+            self.knights.len(),
+            self.kings.len(),
+            self.between.len(),
+            self.lines.len(),
+            self.rays.len(),
+            self.chebyshev_distances.len(),
+            self.manhattan_distances.len(),
+            self.forward_files.len(),
+            self.pawn_attack_spans.len(),
+            self.passed_pawn_spans.len(),
+            self.king_zones.len(),
+            self.adjacent_files.len(),
+            self.outposts.len(),
+            self.square_colors.len(),
+        ]
+            .iter()
+            .map(|&count| count as u64)
+            .collect()
+    }
+    // Only the magics and the sliding table are kept, the rest is regenerated in the current layout
+    fn migrate(header: Option<&Header>, payload: &[u8]) -> Result<Self, FormatError> {
+        match header.map(|header| header.version) {
+            // Files from before the header hold version 1, or the first layout without the geometry tables
+            None => match format::deserialize_exact::<DatabaseV1>(payload) {
+                Ok(database) => Ok(Self::from_magics(database.magics, database.sliding_table)),
+                Err(_) => {
+                    let legacy: LegacyDatabase = format::deserialize_exact(payload)?;
+                    Ok(Self::from_magics(legacy.magics, legacy.sliding_table))
+                }
+            },
+            Some(1) => {
+                let database: DatabaseV1 = format::deserialize_exact(payload)?;
+                Ok(Self::from_magics(database.magics, database.sliding_table))
+            }
+            Some(version) => Err(FormatError::UnsupportedVersion { found: version, supported: Self::VERSION }),
+        }
+    }
+}
+
+// Version 1, before the evaluation masks were added
+#[derive(DeBin)]
+#[allow(dead_code)]
+struct DatabaseV1 {
+    magics: Vec<MagicSquare>,
+    sliding_table: Vec<u64>,
+    pawns: Vec<u64>,
+    knights: Vec<u64>,
+    kings: Vec<u64>,
+    between: Vec<u64>,
+    lines: Vec<u64>,
+    rays: Vec<u64>,
+    chebyshev_distances: Vec<u8>,
+    manhattan_distances: Vec<u8>,
+}
+
+// The database before the between, line, ray and distance tables were added
+#[derive(DeBin)]
+struct LegacyDatabase {
+    magics: Vec<MagicSquare>,
+    sliding_table: Vec<u64>,
+    #[allow(dead_code)]
+    pawns: Vec<u64>,
+    #[allow(dead_code)]
+    knights: Vec<u64>,
+    #[allow(dead_code)]
+    kings: Vec<u64>,
 }
-impl Cacheable for Database {}
 
 
 
 pub const DATABASE_PATH: &'static str = "./wizard/database.bin";
 pub const REFERENCE_PATH: &'static str = "./wizard/reference.bin";
+// Included by core, see codegen
+pub const RUST_TABLES_PATH: &str = "./core/src/generated_tables.rs";
 
 
-pub fn read_database(path: Option<&Path>) -> Result<Database, std::io::Error> {
+pub fn read_database(path: Option<&Path>) -> Result<Database, FormatError> {
     Database::read(path.unwrap_or(Path::new(DATABASE_PATH)))
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file kind without a version before the header, so the default migrate applies
+    #[derive(Clone, Default, Debug, PartialEq, SerBin, DeBin)]
+    struct Sample {
+        values: Vec<u64>,
+        bytes: Vec<u8>,
+    }
+    impl Cacheable for Sample {
+        const TAG: [u8; 4] = *b"TEST";
+        const VERSION: u32 = 2;
+
+        fn entry_counts(&self) -> Vec<u64> {
+            vec![self.values.len() as u64, self.bytes.len() as u64]
+        }
+    }
+
+    fn sample() -> Sample {
+        Sample { values: vec![1, 2, 3], bytes: vec![4, 5] }
+    }
+
+    // A file with a valid header around the payload
+    fn file(version: u32, layout: TableLayout, payload: &[u8], entry_counts: Vec<u64>) -> Vec<u8> {
+        let mut result = Header::new(Sample::TAG, version, layout, payload, entry_counts).to_bytes();
+        result.extend_from_slice(payload);
+        result
+    }
+
+    // The magics of a database, written out field by field like the older versions did
+    fn magics() -> Vec<MagicSquare> {
+        vec![MagicSquare { offset: 0, shift: 52, mask: 0x7e, value: 0x1234 }; 2]
+    }
+    fn legacy_payload(with_geometry: bool) -> Vec<u8> {
+        let mut result = Vec::new();
+        magics().ser_bin(&mut result);
+        vec![7u64; 3].ser_bin(&mut result);
+        for _ in 0..3 {
+            Vec::<u64>::new().ser_bin(&mut result);
+        }
+        if with_geometry {
+            for _ in 0..3 {
+                Vec::<u64>::new().ser_bin(&mut result);
+            }
+            for _ in 0..2 {
+                Vec::<u8>::new().ser_bin(&mut result);
+            }
+        }
+        result
+    }
+
+
+    #[test]
+    fn file_round_trips() {
+        let bytes = sample().to_file_bytes();
+        assert_eq!(Sample::from_file_bytes(&bytes).unwrap(), sample());
+    }
+
+    #[test]
+    fn missing_file() {
+        let error = Sample::read(Path::new("./does/not/exist.bin")).unwrap_err();
+        assert!(matches!(error, FormatError::Io(_)));
+    }
+
+    #[test]
+    fn wrong_tag() {
+        let mut bytes = sample().to_file_bytes();
+        bytes[..4].copy_from_slice(b"OTHR");
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::WrongTag { expected, found }) if expected == *b"TEST" && found == *b"OTHR",
+        ));
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = sample().to_file_bytes();
+        assert!(matches!(Sample::from_file_bytes(&bytes[..12]), Err(FormatError::SizeMismatch { .. })));
+    }
+
+    #[test]
+    fn newer_version() {
+        let payload = sample().serialize_bin();
+        let bytes = file(3, TableLayout::Magic, &payload, sample().entry_counts());
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::UnsupportedVersion { found: 3, supported: 2 }),
+        ));
+    }
+
+    #[test]
+    fn older_version_without_migration() {
+        let payload = sample().serialize_bin();
+        let bytes = file(1, TableLayout::Magic, &payload, sample().entry_counts());
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::UnsupportedVersion { found: 1, supported: 2 }),
+        ));
+    }
+
+    #[test]
+    fn wrong_platform() {
+        let mut bytes = sample().to_file_bytes();
+        bytes[8] ^= 1;
+        assert!(matches!(Sample::from_file_bytes(&bytes), Err(FormatError::WrongPlatform { .. })));
+
+        let mut bytes = sample().to_file_bytes();
+        bytes[9] = if std::mem::size_of::<usize>() == 8 { 4 } else { 8 };
+        assert!(matches!(Sample::from_file_bytes(&bytes), Err(FormatError::WrongPlatform { .. })));
+    }
+
+    #[test]
+    fn truncated_or_padded_payload() {
+        let bytes = sample().to_file_bytes();
+        let expected = bytes.len() as u64;
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes[..bytes.len() - 1]),
+            Err(FormatError::SizeMismatch { expected: size, .. }) if size == expected,
+        ));
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(matches!(Sample::from_file_bytes(&padded), Err(FormatError::SizeMismatch { .. })));
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut bytes = sample().to_file_bytes();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        assert!(matches!(Sample::from_file_bytes(&bytes), Err(FormatError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn payload_that_does_not_deserialize() {
+        let bytes = file(2, TableLayout::Magic, &[1, 2, 3], sample().entry_counts());
+        assert!(matches!(Sample::from_file_bytes(&bytes), Err(FormatError::Deserialize(_))));
+    }
+
+    #[test]
+    fn entry_count_mismatch() {
+        let payload = sample().serialize_bin();
+        // A different count, then a table too many
+        let bytes = file(2, TableLayout::Magic, &payload, vec![3, 9]);
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::EntryCountMismatch { table: 1, expected: 9, found: 2 }),
+        ));
+        let bytes = file(2, TableLayout::Magic, &payload, vec![3, 2, 1]);
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::EntryCountMismatch { table: 2, expected: 3, found: 2 }),
+        ));
+    }
+
+    #[test]
+    fn wrong_layout() {
+        let payload = sample().serialize_bin();
+        let bytes = file(2, TableLayout::Pext, &payload, sample().entry_counts());
+        assert!(matches!(
+            Sample::from_file_bytes(&bytes),
+            Err(FormatError::WrongLayout { expected: TableLayout::Magic, found: TableLayout::Pext }),
+        ));
+    }
+
+
+    #[test]
+    fn headerless_databases_migrate() {
+        for with_geometry in [true, false] {
+            let database = Database::from_file_bytes(&legacy_payload(with_geometry)).unwrap();
+            assert_eq!(database.magics.len(), 2);
+            assert_eq!(database.magics[1].value, 0x1234);
+            assert_eq!(database.sliding_table, vec![7; 3]);
+            // The rest is generated in the current layout
+            assert_eq!(database.between.len(), 64 * 64);
+            assert_eq!(database.outposts, generate_outpost_masks().to_vec());
+        }
+    }
+
+    #[test]
+    fn version_one_database_migrates() {
+        let payload = legacy_payload(true);
+        let mut bytes = Header::new(Database::TAG, 1, TableLayout::Magic, &payload, Vec::new()).to_bytes();
+        bytes.extend_from_slice(&payload);
+        let database = Database::from_file_bytes(&bytes).unwrap();
+        assert_eq!(database.sliding_table, vec![7; 3]);
+        assert_eq!(database.king_zones.len(), 2 * 64);
+    }
+
+    #[test]
+    fn corrupt_headerless_database_keeps_the_reason() {
+        let payload = legacy_payload(false);
+        let error = Database::from_file_bytes(&payload[..payload.len() - 3]).unwrap_err();
+        match error {
+            FormatError::MigrationFailed { source, .. } => assert!(matches!(*source, FormatError::Deserialize(_))),
+            error => panic!("expected a failed migration, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn current_database_round_trips() {
+        let mut database = Database { magics: magics(), sliding_table: vec![7; 3], ..Default::default() };
+        database.generate_geometry();
+        let read = Database::from_file_bytes(&database.to_file_bytes()).unwrap();
+        assert_eq!(read.magics.len(), 2);
+        assert_eq!(read.entry_counts(), database.entry_counts());
+    }
+}
@@ -0,0 +1,230 @@
+// Attack and geometry masks, bit 0 is a8 and bit 63 is h1
+pub fn generate_rook_occlusion_mask(square: usize) -> u64 {
+    let occupancy = 1 << square;
+    (
+        generate_line_mask(occupancy, 0, Direction::North) & NOT_RANK_1 | 
+        generate_line_mask(occupancy, 0, Direction::South) & NOT_RANK_8 | 
+        generate_line_mask(occupancy, 0, Direction::East) & NOT_FILE_H | 
+        generate_line_mask(occupancy, 0, Direction::West) & NOT_FILE_A
+    ) & !occupancy
+}
+pub fn generate_rook_attack_mask(square: usize, occlusion: u64) -> u64 {
+    let occupancy = 1 << square;
+    (
+        generate_line_mask(occupancy, occlusion, Direction::North) | 
+        generate_line_mask(occupancy, occlusion, Direction::South) | 
+        generate_line_mask(occupancy, occlusion, Direction::East) | 
+        generate_line_mask(occupancy, occlusion, Direction::West)
+    ) & !occupancy
+}
+
+pub fn generate_bishop_occlusion_mask(square: usize) -> u64 {
+    let occupancy = 1 << square;
+    (
+        generate_line_mask(occupancy, 0, Direction::NorthEast) & (NOT_RANK_1 & NOT_FILE_H) | 
+        generate_line_mask(occupancy, 0, Direction::NorthWest) & (NOT_RANK_1 & NOT_FILE_A) | 
+        generate_line_mask(occupancy, 0, Direction::SouthEast) & (NOT_RANK_8 & NOT_FILE_H) | 
+        generate_line_mask(occupancy, 0, Direction::SouthWest) & (NOT_RANK_8 & NOT_FILE_A) 
+    ) & !occupancy
+}
+pub fn generate_bishop_attack_mask(square: usize, occlusion: u64) -> u64 {
+    let occupancy = 1 << square;
+    (
+        generate_line_mask(occupancy, occlusion, Direction::NorthEast) | 
+        generate_line_mask(occupancy, occlusion, Direction::NorthWest) | 
+        generate_line_mask(occupancy, occlusion, Direction::SouthEast) | 
+        generate_line_mask(occupancy, occlusion, Direction::SouthWest)
+    ) & !occupancy
+}
+
+pub fn generate_pawn_attack_mask(square: usize) -> [u64; 2] {
+    let occupancy = 1 << square;
+    let white = (occupancy >> 7) & NOT_FILE_A | (occupancy >> 9) & NOT_FILE_H;
+    let black = (occupancy << 9) & NOT_FILE_A | (occupancy << 7) & NOT_FILE_H;
+    [white, black]
+}
+pub fn generate_knight_attack_mask(square: usize) -> u64 {
+    let occupancy = 1 << square;
+    let north_east: u64 = (occupancy << 1) & NOT_FILE_A;
+    let north_west: u64 = (occupancy >> 1) & NOT_FILE_H;
+    let south_east: u64 = (occupancy << 2) & NOT_FILE_AB;
+    let south_west: u64 = (occupancy >> 2) & NOT_FILE_GH;
+    let north: u64 = north_east | north_west;
+    let south: u64 = south_east | south_west;
+    
+    {
+        (north << 16) | 
+        (north >> 16) | 
+        (south << 8) | 
+        (south >> 8)
+    }
+}
+pub fn generate_king_attack_mask(square: usize) -> u64 {
+    let occupancy = 1 << square;
+    {
+        // north and south
+        occupancy >> 8 |
+        occupancy << 8 |
+        // east and west
+        (occupancy << 1) & NOT_FILE_A |
+        (occupancy >> 1) & NOT_FILE_H | 
+        // north east and west
+        (occupancy >> 7) & NOT_FILE_A |
+        (occupancy >> 9) & NOT_FILE_H |
+        // south east and west
+        (occupancy << 9) & NOT_FILE_A |
+        (occupancy << 7) & NOT_FILE_H
+    }
+}
+
+
+
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    North,
+    South,
+    East, 
+    West, 
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+pub const NOT_FILE_A: u64 = 0xfefefefefefefefe;
+pub const NOT_FILE_H: u64 = 0x7f7f7f7f7f7f7f7f;
+pub const NOT_FILE_AB: u64 = 0xfcfcfcfcfcfcfcfc;
+pub const NOT_FILE_GH: u64 = 0x3f3f3f3f3f3f3f3f;
+
+pub const NOT_RANK_1: u64 = 0xffffffffffffff00;
+pub const NOT_RANK_8: u64 = 0x00ffffffffffffff;
+
+
+pub fn generate_line_mask(mut occupancy: u64, mut occlusion: u64, direction: Direction) -> u64 {
+    let (left_shift, right_shift, mask) = match direction {
+        Direction::North        => (0, 8, !0),
+        Direction::South        => (8, 0, !0),
+        Direction::East         => (1, 0, NOT_FILE_A),
+        Direction::West         => (0, 1, NOT_FILE_H),
+        Direction::NorthEast    => (0, 7, NOT_FILE_A),
+        Direction::NorthWest    => (0, 9, NOT_FILE_H),
+        Direction::SouthEast    => (9, 0, NOT_FILE_A),
+        Direction::SouthWest    => (7, 0, NOT_FILE_H),
+    };
+    let shift = |value: u64, multiplier: usize| -> u64 {
+        (value >> (right_shift * multiplier)) << (left_shift * multiplier)
+    };
+
+    occlusion = mask & !shift(occlusion & !occupancy, 1);
+    occupancy |= occlusion & shift(occupancy, 1);
+    occlusion &= shift(occlusion, 1);
+    occupancy |= occlusion & shift(occupancy, 2);
+    occlusion &= shift(occlusion, 2);
+    occupancy |= occlusion & shift(occupancy, 4);
+    occupancy
+}
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+}
+
+
+// Every square from the given one to the edge of the board, not including the square itself
+pub fn generate_ray_mask(square: usize, direction: Direction) -> u64 {
+    let occupancy = 1 << square;
+    generate_line_mask(occupancy, 0, direction) & !occupancy
+}
+// The squares strictly between two squares on a shared rank, file or diagonal, empty otherwise
+pub fn generate_between_mask(from: usize, to: usize) -> u64 {
+    match direction_between(from, to) {
+        Some(direction) => generate_ray_mask(from, direction) & generate_ray_mask(to, reverse(direction)),
+        None => 0,
+    }
+}
+// The whole line through two squares from edge to edge, empty if they do not share a rank, file or diagonal
+pub fn generate_through_mask(from: usize, to: usize) -> u64 {
+    match direction_between(from, to) {
+        Some(direction) => generate_line_mask(1 << from, 0, direction) | generate_line_mask(1 << from, 0, reverse(direction)),
+        None => 0,
+    }
+}
+// The number of king moves between two squares
+pub fn generate_chebyshev_distance(from: usize, to: usize) -> u8 {
+    let (file_distance, rank_distance) = file_rank_distance(from, to);
+    file_distance.max(rank_distance)
+}
+// The number of rook moves on an empty board between two squares, going one square at a time
+pub fn generate_manhattan_distance(from: usize, to: usize) -> u8 {
+    let (file_distance, rank_distance) = file_rank_distance(from, to);
+    file_distance + rank_distance
+}
+
+fn file_rank_distance(from: usize, to: usize) -> (u8, u8) {
+    let file_distance = (from % 8).abs_diff(to % 8) as u8;
+    let rank_distance = (from / 8).abs_diff(to / 8) as u8;
+    (file_distance, rank_distance)
+}
+// The direction to walk from one square to reach the other, if they are aligned
+fn direction_between(from: usize, to: usize) -> Option<Direction> {
+    Direction::ALL.iter()
+        .copied()
+        .find(|&direction| generate_ray_mask(from, direction) & (1 << to) != 0)
+}
+fn reverse(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+        Direction::NorthEast => Direction::SouthWest,
+        Direction::NorthWest => Direction::SouthEast,
+        Direction::SouthEast => Direction::NorthWest,
+        Direction::SouthWest => Direction::NorthEast,
+    }
+}
+
+
+// Evaluation masks. Those that depend on the side are [white, black], white moves north towards rank 8.
+
+// The squares in front of a square on its file
+pub fn generate_forward_file_mask(square: usize) -> [u64; 2] {
+    [generate_ray_mask(square, Direction::North), generate_ray_mask(square, Direction::South)]
+}
+// The files next to the file of a square, where the pawns that keep a pawn from being isolated stand
+pub fn generate_adjacent_files_mask(square: usize) -> u64 {
+    let occupancy = 1 << square;
+    let file = generate_line_mask(occupancy, 0, Direction::North) | generate_line_mask(occupancy, 0, Direction::South);
+    (file << 1) & NOT_FILE_A | (file >> 1) & NOT_FILE_H
+}
+// The squares in front of a square on the adjacent files, every square a pawn could attack it from later on
+pub fn generate_pawn_attack_span_mask(square: usize) -> [u64; 2] {
+    generate_forward_file_mask(square).map(|forward| (forward << 1) & NOT_FILE_A | (forward >> 1) & NOT_FILE_H)
+}
+// The squares an enemy pawn must not be on for a pawn on the square to be passed
+pub fn generate_passed_pawn_span_mask(square: usize) -> [u64; 2] {
+    let [white_file, black_file] = generate_forward_file_mask(square);
+    let [white_span, black_span] = generate_pawn_attack_span_mask(square);
+    [white_file | white_span, black_file | black_span]
+}
+// The king, its neighbours and the three squares two ranks in front of it
+pub fn generate_king_zone_mask(square: usize) -> [u64; 2] {
+    let ring = generate_king_attack_mask(square) | 1 << square;
+    [ring | ring >> 8, ring | ring << 8]
+}
+// Ranks 4 to 6 from the side of each player, where a minor piece supported by a pawn is hard to dislodge
+pub fn generate_outpost_masks() -> [u64; 2] {
+    [0x000000ffffff0000, 0x0000ffffff000000]
+}
+// [light, dark], a8 and h1 are light
+pub fn generate_square_color_masks() -> [u64; 2] {
+    [0xaa55aa55aa55aa55, 0x55aa55aa55aa55aa]
+}
@@ -0,0 +1,184 @@
+use crate::*;
+
+use nanorand::{Rng, WyRand};
+use rayon::prelude::*;
+
+use std::time::Instant;
+
+
+// Every occlusion of one square with the attacks it leaves, as stored in the reference database
+pub fn reference_entries(reference: &ReferenceDatabase, index: usize) -> Vec<(u64, u64)> {
+    let start = reference.magics[index].offset;
+    let end = reference.magics.get(index + 1).map_or(reference.moves.len(), |magic| magic.offset);
+    reference.occluders[start..end].iter()
+        .copied()
+        .zip(reference.moves[start..end].iter().copied())
+        .collect()
+}
+
+// Whether the magic sends every occlusion to a slot of its own, or to one holding the same attacks
+pub fn verify_magic(value: u64, bits: usize, entries: &[(u64, u64)]) -> bool {
+    let mut table = vec![None; 1 << bits];
+    entries.iter().all(|&(occlusion, attacks)| {
+        let key = (occlusion.wrapping_mul(value) >> (64 - bits)) as usize;
+        *table[key].get_or_insert(attacks) == attacks
+    })
+}
+
+// Tries random candidates until one fits in the given number of bits
+pub fn find_magic(bits: usize, entries: &[(u64, u64)], rng: &mut WyRand, attempts: usize) -> Option<u64> {
+    let mut table = vec![0; 1 << bits];
+    // The attempt that last wrote each slot, so the table does not have to be cleared between candidates
+    let mut written = vec![0; 1 << bits];
+
+    for attempt in 1..=attempts {
+        // Sparse candidates suit the large rook masks and dense ones the small bishop masks, so both are tried
+        let mut value = rng.generate::<u64>();
+        for _ in 0..attempt % 3 {
+            value &= rng.generate::<u64>();
+        }
+
+        let is_valid = entries.iter().all(|&(occlusion, attacks)| {
+            let key = (occlusion.wrapping_mul(value) >> (64 - bits)) as usize;
+            if written[key] != attempt {
+                written[key] = attempt;
+                table[key] = attacks;
+                true
+            } else {
+                table[key] == attacks
+            }
+        });
+        if is_valid {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// Candidates tried per square in each round of improve_magics
+pub const ATTEMPTS_PER_ROUND: usize = 100_000;
+
+// Searches the given squares in parallel for magics one bit smaller than their current one, for the given number of rounds
+// or until the optional deadline, which is only checked between rounds. Squares keep their magic if nothing better is found.
+// The same seed gives the same magics after the same number of rounds, the offsets are reassigned to pack the smaller tables.
+// Returns the magics with the number of rounds searched, which reproduces the result without the deadline.
+pub fn improve_magics(magics: &[MagicSquare], reference: &ReferenceDatabase, squares: &[usize], seed: u64, rounds: usize, deadline: Option<Instant>) -> (Vec<MagicSquare>, usize) {
+    let mut searches: Vec<SquareSearch> = squares.iter()
+        .map(|&index| SquareSearch {
+            index,
+            magic: magics[index],
+            rng: WyRand::new_seed(seed.wrapping_add(index as u64)),
+            entries: reference_entries(reference, index),
+        })
+        .collect();
+
+    let mut searched = 0;
+    while searched < rounds && deadline.is_none_or(|deadline| Instant::now() < deadline) {
+        searches.par_iter_mut().for_each(|search| {
+            let magic = &mut search.magic;
+            // A shift of 64 would leave a single slot, which only works for an empty mask
+            if magic.shift >= 63 {
+                return;
+            }
+            if let Some(value) = find_magic(64 - magic.shift - 1, &search.entries, &mut search.rng, ATTEMPTS_PER_ROUND) {
+                magic.value = value;
+                magic.shift += 1;
+            }
+        });
+        searched += 1;
+    }
+
+    let mut result = magics.to_vec();
+    for search in searches {
+        result[search.index] = search.magic;
+    }
+    assign_offsets(&mut result);
+    (result, searched)
+}
+
+struct SquareSearch {
+    index: usize,
+    magic: MagicSquare,
+    rng: WyRand,
+    entries: Vec<(u64, u64)>,
+}
+
+// Lays the tables of all squares out back to back
+pub fn assign_offsets(magics: &mut [MagicSquare]) {
+    let mut offset = 0;
+    for magic in magics {
+        magic.offset = offset;
+        offset += 1 << (64 - magic.shift);
+    }
+}
+
+// Whether a set of magics uses the reference masks and every square is free of destructive collisions
+pub fn verify_magics(magics: &[MagicSquare], reference: &ReferenceDatabase) -> bool {
+    magics.len() == reference.magics.len() &&
+    magics.iter().enumerate().all(|(index, magic)| {
+        magic.mask == reference.magics[index].mask &&
+        verify_magic(magic.value, 64 - magic.shift, &reference_entries(reference, index))
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::masks::*;
+
+    // Every occlusion of a rook on the square with the attacks it leaves
+    fn rook_entries(square: usize) -> Vec<(u64, u64)> {
+        let mask = generate_rook_occlusion_mask(square);
+        let mut result = Vec::new();
+        let mut occlusion = 0;
+        loop {
+            result.push((occlusion, generate_rook_attack_mask(square, occlusion)));
+            occlusion = occlusion.wrapping_sub(mask) & mask;
+            if occlusion == 0 {
+                return result;
+            }
+        }
+    }
+
+    // A reference holding only the rook on the given squares
+    fn reference(squares: &[usize]) -> ReferenceDatabase {
+        let mut result = ReferenceDatabase::default();
+        for &square in squares {
+            let entries = rook_entries(square);
+            let mask = generate_rook_occlusion_mask(square);
+            result.magics.push(MagicSquare { offset: result.moves.len(), shift: 64 - mask.count_ones() as usize, mask, value: 0 });
+            result.occluders.extend(entries.iter().map(|&(occlusion, _)| occlusion));
+            result.moves.extend(entries.iter().map(|&(_, attacks)| attacks));
+        }
+        result
+    }
+
+
+    #[test]
+    fn found_magic_verifies() {
+        // d4 has the fewest occlusions of any rook square, so a fixed budget finds one quickly
+        let entries = rook_entries(35);
+        let bits = generate_rook_occlusion_mask(35).count_ones() as usize;
+        let value = find_magic(bits, &entries, &mut WyRand::new_seed(7), 100_000).unwrap();
+        assert!(verify_magic(value, bits, &entries));
+        assert_eq!(find_magic(bits, &entries, &mut WyRand::new_seed(7), 100_000), Some(value));
+    }
+
+    #[test]
+    fn rounds_reproduce_the_search() {
+        let reference = reference(&[27, 35]);
+        let mut magics = reference.magics.clone();
+        for (index, magic) in magics.iter_mut().enumerate() {
+            magic.value = find_magic(64 - magic.shift, &reference_entries(&reference, index), &mut WyRand::new_seed(1), 100_000).unwrap();
+        }
+
+        let (first, rounds) = improve_magics(&magics, &reference, &[0, 1], 3, 2, None);
+        let (second, _) = improve_magics(&magics, &reference, &[0, 1], 3, 2, None);
+        assert_eq!(rounds, 2);
+        assert!(verify_magics(&first, &reference));
+        for (first, second) in first.iter().zip(&second) {
+            assert_eq!((first.value, first.shift, first.offset), (second.value, second.shift, second.offset));
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::*;
+
+use std::fmt;
+
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Failure {
+    Length {
+        table: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    // The first wrong entry of a table for a square
+    Entry {
+        table: &'static str,
+        square: usize,
+        // The occupancy the lookup was made with, 0 for tables that do not depend on it
+        occupancy: u64,
+        expected: u64,
+        // None if the lookup fell outside of the table
+        found: Option<u64>,
+        // How many occupancies or entries of the square are wrong
+        count: usize,
+    },
+    // A mask that covers the whole board rather than one square
+    Mask {
+        table: &'static str,
+        expected: u64,
+        found: u64,
+    },
+}
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (table, square, occupancy, expected, found, count) = match *self {
+            Failure::Length { table, expected, found } => {
+                return writeln!(f, "{} table has {} entries instead of {}", table, found, expected);
+            }
+            Failure::Entry { table, square, occupancy, expected, found, count } => (table, square, occupancy, expected, found, count),
+            Failure::Mask { table, expected, found } => {
+                writeln!(f, "{} mask", table)?;
+                writeln!(f, "    expected{:9}found", "")?;
+                // No square to mark
+                let expected = render_board(64, expected, 0);
+                let found = render_board(64, found, 0);
+                for (expected, found) in expected.lines().zip(found.lines()) {
+                    writeln!(f, "    {}  {}", expected, found)?;
+                }
+                return Ok(());
+            }
+        };
+
+        write!(f, "{} on {}", table, square_name(square))?;
+        if count > 1 {
+            write!(f, ", {} entries wrong", count)?;
+        }
+        if occupancy != 0 {
+            write!(f, ", first with occupancy {:#018x}", occupancy)?;
+        }
+        writeln!(f)?;
+        match found {
+            None => writeln!(f, "    the lookup is outside of the table"),
+            Some(found) if table.ends_with("distance") => writeln!(f, "    expected {} but found {}", expected, found),
+            Some(found) => {
+                writeln!(f, "    expected{:9}found", "")?;
+                let expected = render_board(square, expected, occupancy);
+                let found = render_board(square, found, occupancy);
+                for (expected, found) in expected.lines().zip(found.lines()) {
+                    writeln!(f, "    {}  {}", expected, found)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+// Checks every table of the database against the generators, every occupancy subset of every magic mask included
+pub fn verify_database(database: &Database) -> Vec<Failure> {
+    let mut failures = Vec::new();
+    if database.magics.len() != 128 {
+        failures.push(Failure::Length { table: "magics", expected: 128, found: database.magics.len() });
+    }
+
+    for (index, magic) in database.magics.iter().enumerate().take(128) {
+        let square = index % 64;
+        let is_rook = index < 64;
+        let table = if is_rook { "rook" } else { "bishop" };
+        let occlusion_mask = if is_rook { generate_rook_occlusion_mask(square) } else { generate_bishop_occlusion_mask(square) };
+        let attack_mask = |occupancy| if is_rook {
+            generate_rook_attack_mask(square, occupancy)
+        } else {
+            generate_bishop_attack_mask(square, occupancy)
+        };
+        if magic.mask != occlusion_mask {
+            failures.push(Failure::Entry {
+                table: if is_rook { "rook mask" } else { "bishop mask" },
+                square,
+                occupancy: 0,
+                expected: occlusion_mask,
+                found: Some(magic.mask),
+                count: 1,
+            });
+            continue;
+        }
+
+        // Every subset of the mask, from the empty board up
+        let mut first = None;
+        let mut count = 0;
+        let mut occupancy = 0u64;
+        loop {
+            let expected = attack_mask(occupancy);
+            let found = database.sliding_table.get(magic.key(occupancy)).copied();
+            if found != Some(expected) {
+                first.get_or_insert((occupancy, expected, found));
+                count += 1;
+            }
+            occupancy = occupancy.wrapping_sub(magic.mask) & magic.mask;
+            if occupancy == 0 {
+                break;
+            }
+        }
+        if let Some((occupancy, expected, found)) = first {
+            failures.push(Failure::Entry { table, square, occupancy, expected, found, count });
+        }
+    }
+
+    let mut expected = Database::default();
+    expected.generate_geometry();
+    // The pawn table and the evaluation masks interleave white and black per square
+    let by_color = |table: &[u64], color: usize| table.iter().skip(color).step_by(2).copied().collect::<Vec<u64>>();
+    let distances = |distances: &[u8]| distances.iter().map(|&distance| distance as u64).collect::<Vec<u64>>();
+    let tables = [
+        ("white pawn", by_color(&database.pawns, 0), by_color(&expected.pawns, 0), 1),
+        ("black pawn", by_color(&database.pawns, 1), by_color(&expected.pawns, 1), 1),
+        ("knight", database.knights.clone(), expected.knights, 1),
+        ("king", database.kings.clone(), expected.kings, 1),
+        ("between", database.between.clone(), expected.between, 64),
+        ("line", database.lines.clone(), expected.lines, 64),
+        ("ray", database.rays.clone(), expected.rays, 8),
+        ("chebyshev distance", distances(&database.chebyshev_distances), distances(&expected.chebyshev_distances), 64),
+        ("manhattan distance", distances(&database.manhattan_distances), distances(&expected.manhattan_distances), 64),
+        // The evaluation masks are checked against their definitions rather than the generators
+        ("white forward file", by_color(&database.forward_files, 0), brute_force(0, |file, rank| file == 0 && rank < 0), 1),
+        ("black forward file", by_color(&database.forward_files, 1), brute_force(1, |file, rank| file == 0 && rank < 0), 1),
+        ("white pawn attack span", by_color(&database.pawn_attack_spans, 0), brute_force(0, |file, rank| file.abs() == 1 && rank < 0), 1),
+        ("black pawn attack span", by_color(&database.pawn_attack_spans, 1), brute_force(1, |file, rank| file.abs() == 1 && rank < 0), 1),
+        ("white passed pawn span", by_color(&database.passed_pawn_spans, 0), brute_force(0, |file, rank| file.abs() <= 1 && rank < 0), 1),
+        ("black passed pawn span", by_color(&database.passed_pawn_spans, 1), brute_force(1, |file, rank| file.abs() <= 1 && rank < 0), 1),
+        ("white king zone", by_color(&database.king_zones, 0), brute_force(0, |file, rank| file.abs() <= 1 && (-2..=1).contains(&rank)), 1),
+        ("black king zone", by_color(&database.king_zones, 1), brute_force(1, |file, rank| file.abs() <= 1 && (-2..=1).contains(&rank)), 1),
+        ("adjacent files", database.adjacent_files.clone(), brute_force(0, |file, _| file.abs() == 1), 1),
+    ];
+    for (table, found, expected, stride) in tables {
+        if found.len() != expected.len() {
+            failures.push(Failure::Length { table, expected: expected.len(), found: found.len() });
+            continue;
+        }
+        // One failure per square, with the first wrong entry
+        for (square, (found, expected)) in found.chunks(stride).zip(expected.chunks(stride)).enumerate() {
+            let mut wrong = found.iter().zip(expected).filter(|(found, expected)| found != expected);
+            if let Some((&found, &expected)) = wrong.next() {
+                failures.push(Failure::Entry {
+                    table,
+                    square,
+                    occupancy: 0,
+                    expected,
+                    found: Some(found),
+                    count: 1 + wrong.count(),
+                });
+            }
+        }
+    }
+
+    // Whole board masks, ranks counted from the side of each player
+    let rank = |square: usize, color: usize| if color == 0 { 8 - square / 8 } else { 1 + square / 8 };
+    let outposts = |color| (0..64).filter(|&square| (4..=6).contains(&rank(square, color))).fold(0, |mask, square| mask | 1 << square);
+    let light = (0..64).filter(|square| (square % 8 + square / 8) % 2 == 0).fold(0, |mask, square| mask | 1 << square);
+    let masks = [
+        ("white outposts", database.outposts.first(), outposts(0)),
+        ("black outposts", database.outposts.get(1), outposts(1)),
+        ("light squares", database.square_colors.first(), light),
+        ("dark squares", database.square_colors.get(1), !light),
+    ];
+    for (table, found, expected) in masks {
+        match found {
+            Some(&found) if found != expected => failures.push(Failure::Mask { table, expected, found }),
+            Some(_) => {}
+            None => failures.push(Failure::Length { table, expected: 2, found: 0 }),
+        }
+    }
+    failures
+}
+
+// One mask per square, with the squares that satisfy the definition given their file and rank offsets from the square.
+// Rank offsets are negative in front of the square from the side of the given color, 0 white and 1 black.
+fn brute_force(color: usize, is_included: impl Fn(isize, isize) -> bool) -> Vec<u64> {
+    let coordinates = |square: usize| ((square % 8) as isize, (square / 8) as isize);
+    (0..64)
+        .map(|square| {
+            let (file, row) = coordinates(square);
+            (0..64)
+                .filter(|&other| {
+                    let (other_file, other_row) = coordinates(other);
+                    // Rows count from the 8th rank, so white moves towards smaller rows
+                    let rank = if color == 0 { other_row - row } else { row - other_row };
+                    is_included(other_file - file, rank)
+                })
+                .fold(0, |mask, other| mask | 1 << other)
+        })
+        .collect()
+}
+
+// The board with a8 in the top left: * the square, # occupied, - attacked, X attacked and occupied
+pub fn render_board(square: usize, attacks: u64, occupancy: u64) -> String {
+    let mut result = String::with_capacity(8 * 17);
+    for rank in 0..8 {
+        for file in 0..8 {
+            let index = rank * 8 + file;
+            let is_occupied = occupancy & (1 << index) != 0;
+            let is_attacked = attacks & (1 << index) != 0;
+            let symbol = match (index == square, is_attacked, is_occupied) {
+                (true, _, _) => '*',
+                (_, true, true) => 'X',
+                (_, false, true) => '#',
+                (_, true, false) => '-',
+                (_, false, false) => '.',
+            };
+            result.push(symbol);
+            result.push(if file == 7 { '\n' } else { ' ' });
+        }
+    }
+    result
+}
+
+pub fn square_name(square: usize) -> String {
+    format!("{}{}", (b'a' + (square % 8) as u8) as char, 8 - square / 8)
+}